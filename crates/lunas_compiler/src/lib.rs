@@ -1,5 +1,12 @@
-use lunas_generator::lunas_compile_from_block;
-use lunas_parser::parse_lunas_file;
+mod plugin;
+
+use lunas_generator::{
+    lunas_compile_from_block, lunas_compile_hydratable_from_block,
+    lunas_compile_hydratable_ssr_from_block, lunas_compile_ssr_from_block,
+};
+use lunas_parser::{diagnostics::codes, parse_lunas_file};
+pub use lunas_parser::{CustomBlock, Diagnostic};
+pub use plugin::{CustomBlockMetadata, CustomBlockOutput, CustomBlockPlugin};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -8,6 +15,10 @@ use wasm_bindgen::prelude::*;
 pub struct LunasCompilerOutput {
     js: String,
     css: Option<String>,
+    css_lang: String,
+    custom_block_metadata: Vec<CustomBlockMetadata>,
+    js_source_map: String,
+    css_source_map: Option<String>,
 }
 
 #[wasm_bindgen]
@@ -23,20 +34,566 @@ impl LunasCompilerOutput {
             None => None,
         }
     }
+    /// Language of the emitted CSS (`css`, or the `lang` attribute of the style block).
+    #[wasm_bindgen(getter, js_name = cssLang)]
+    pub fn css_lang(&self) -> String {
+        self.css_lang.clone()
+    }
+    /// Source Map v3 JSON of `js`, leading back to the `.lun` file.
+    #[wasm_bindgen(getter, js_name = jsSourceMap)]
+    pub fn js_source_map(&self) -> String {
+        self.js_source_map.clone()
+    }
+    /// Source Map v3 JSON of `css`, when the component has a style block.
+    #[wasm_bindgen(getter, js_name = cssSourceMap)]
+    pub fn css_source_map(&self) -> Option<String> {
+        self.css_source_map.clone()
+    }
+    /// Metadata produced by custom block plugins, as `{ block_name, value }` objects.
+    #[wasm_bindgen(getter, js_name = customBlockMetadata)]
+    pub fn custom_block_metadata(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.custom_block_metadata).unwrap_or(JsValue::NULL)
+    }
+}
+
+impl LunasCompilerOutput {
+    pub fn custom_block_metadata_list(&self) -> &[CustomBlockMetadata] {
+        &self.custom_block_metadata
+    }
+}
+
+/// Name of the compiled file in source maps when none is set.
+const DEFAULT_SOURCE_NAME: &str = "component.lun";
+
+/// What a component is compiled into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompileTarget {
+    /// A module that builds and updates the component in the browser.
+    #[default]
+    Client,
+    /// A module that renders the markup of the component to a string, for server-side
+    /// rendering. The engine path names the server runtime, `lunas/server` by default.
+    Server,
+    /// A client module that can also adopt the markup rendered by the `HydratableServer` target
+    /// through the `hydrate` method of the component, instead of building it again.
+    Hydrate,
+    /// A `Server` module whose markup also carries the hydration markers the `Hydrate` target
+    /// adopts it by.
+    HydratableServer,
 }
 
+impl std::str::FromStr for CompileTarget {
+    type Err = Diagnostic;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        match target {
+            "client" => Ok(CompileTarget::Client),
+            "server" => Ok(CompileTarget::Server),
+            "hydrate" => Ok(CompileTarget::Hydrate),
+            "hydratable-server" => Ok(CompileTarget::HydratableServer),
+            _ => Err(Diagnostic::error(
+                codes::INVALID_TARGET,
+                format!(
+                    "Unknown compile target `{}`; expected `client`, `server`, `hydrate` or \
+                     `hydratable-server`",
+                    target
+                ),
+            )),
+        }
+    }
+}
+
+/// Compiler entry point that custom block plugins can be registered on.
+#[derive(Default)]
+pub struct LunasCompiler {
+    custom_block_plugins: Vec<Box<dyn CustomBlockPlugin>>,
+    source_name: Option<String>,
+    target: CompileTarget,
+}
+
+impl LunasCompiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_custom_block_plugin(
+        &mut self,
+        plugin: impl CustomBlockPlugin + 'static,
+    ) -> &mut Self {
+        self.custom_block_plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Sets the name the source maps give to the compiled file, `component.lun` by default.
+    pub fn set_source_name(&mut self, source_name: impl Into<String>) -> &mut Self {
+        self.source_name = Some(source_name.into());
+        self
+    }
+
+    /// Sets what components are compiled into, a client module by default.
+    pub fn set_target(&mut self, target: CompileTarget) -> &mut Self {
+        self.target = target;
+        self
+    }
+
+    /// Compiles a `.lun` source, returning every diagnostic that made the compilation fail.
+    pub fn compile(
+        &self,
+        lunas_code: &str,
+        engine_path: Option<String>,
+    ) -> Result<LunasCompilerOutput, Vec<Diagnostic>> {
+        let blocks = parse_lunas_file(lunas_code)?;
+        let compiled = match self.target {
+            CompileTarget::Client => lunas_compile_from_block(&blocks, engine_path)?,
+            CompileTarget::Server => lunas_compile_ssr_from_block(&blocks, engine_path)?,
+            CompileTarget::Hydrate => lunas_compile_hydratable_from_block(&blocks, engine_path)?,
+            CompileTarget::HydratableServer => {
+                lunas_compile_hydratable_ssr_from_block(&blocks, engine_path)?
+            }
+        };
+        let source_name = self.source_name.as_deref().unwrap_or(DEFAULT_SOURCE_NAME);
+        let js_source_map = compiled
+            .js_source_map
+            .to_json(source_name, Some(lunas_code));
+        let css_source_map = compiled
+            .css_source_map
+            .map(|map| map.to_json(source_name, Some(lunas_code)));
+        // Code added by plugins follows the compiled code and is left unmapped
+        let mut js = compiled.js;
+
+        let mut custom_block_metadata = vec![];
+        let mut diagnostics = vec![];
+        for block in &blocks.detailed_language_blocks.custom_blocks {
+            let plugins = self
+                .custom_block_plugins
+                .iter()
+                .filter(|plugin| plugin.block_name() == block.name);
+            for plugin in plugins {
+                match plugin.transform(block) {
+                    Ok(output) => {
+                        if let Some(plugin_js) = output.js {
+                            js.push_str(&plugin_js);
+                            js.push('\n');
+                        }
+                        if let Some(value) = output.metadata {
+                            custom_block_metadata.push(CustomBlockMetadata {
+                                block_name: block.name.clone(),
+                                value,
+                            });
+                        }
+                    }
+                    Err(errors) => {
+                        diagnostics.extend(errors.into_iter().map(|e| e.or_range(block.range)))
+                    }
+                }
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        Ok(LunasCompilerOutput {
+            js,
+            css: compiled.css,
+            css_lang: blocks.detailed_language_blocks.css_lang().to_string(),
+            custom_block_metadata,
+            js_source_map,
+            css_source_map,
+        })
+    }
+}
+
+/// Compiles a `.lun` source, returning every diagnostic that made the compilation fail.
+pub fn compile_lunas(
+    lunas_code: &str,
+    engine_path: Option<String>,
+) -> Result<LunasCompilerOutput, Vec<Diagnostic>> {
+    LunasCompiler::new().compile(lunas_code, engine_path)
+}
+
+/// Compiles a `.lun` source. `source_name` is the file name given in the source maps and
+/// `target` is `"client"` (the default), `"server"` for server-side rendering, `"hydrate"` for a
+/// client module that adopts server-rendered markup or `"hydratable-server"` for server-side
+/// rendering into markup it can adopt. On failure the thrown value is an array of diagnostics
+/// (`{ severity, code, message, range? }`).
 #[wasm_bindgen]
 pub fn compile(
     lunas_code: String,
     engine_path: Option<String>,
-) -> Result<LunasCompilerOutput, String> {
-    let blocks = match parse_lunas_file(&lunas_code) {
-        Ok(r) => Ok(r),
-        Err(e) => Err(e.to_string()),
-    }?;
-    let code = lunas_compile_from_block(&blocks, engine_path)?;
-    Ok(LunasCompilerOutput {
-        js: code.0,
-        css: code.1,
-    })
+    source_name: Option<String>,
+    target: Option<String>,
+) -> Result<LunasCompilerOutput, JsValue> {
+    let to_js_value = |diagnostics: Vec<Diagnostic>| {
+        serde_wasm_bindgen::to_value(&diagnostics)
+            .unwrap_or_else(|e| JsValue::from_str(&e.to_string()))
+    };
+    let mut compiler = LunasCompiler::new();
+    if let Some(source_name) = source_name {
+        compiler.set_source_name(source_name);
+    }
+    if let Some(target) = target {
+        let target = target
+            .parse()
+            .map_err(|diagnostic| to_js_value(vec![diagnostic]))?;
+        compiler.set_target(target);
+    }
+    compiler
+        .compile(&lunas_code, engine_path)
+        .map_err(to_js_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lunas_parser::diagnostics::codes;
+
+    struct DocsPlugin;
+
+    impl CustomBlockPlugin for DocsPlugin {
+        fn block_name(&self) -> &str {
+            "docs"
+        }
+
+        fn transform(&self, block: &CustomBlock) -> Result<CustomBlockOutput, Vec<Diagnostic>> {
+            if block.content.is_empty() {
+                return Err(vec![Diagnostic::error(
+                    codes::INVALID_CUSTOM_BLOCK,
+                    "empty docs",
+                )]);
+            }
+            Ok(CustomBlockOutput {
+                js: Some(format!("export const docs = {:?};", block.content)),
+                metadata: Some(serde_json::json!({ "length": block.content.len() })),
+            })
+        }
+    }
+
+    #[test]
+    fn test_custom_block_plugin() {
+        let mut compiler = LunasCompiler::new();
+        compiler.register_custom_block_plugin(DocsPlugin);
+        let output = compiler
+            .compile("html:\n  <div></div>\ndocs:\n  Hello\n", None)
+            .unwrap();
+        assert!(output.js.ends_with("export const docs = \"Hello\";\n"));
+        assert_eq!(output.custom_block_metadata_list()[0].block_name, "docs");
+        assert_eq!(output.custom_block_metadata_list()[0].value["length"], 5);
+    }
+
+    #[test]
+    fn test_custom_block_without_plugin_is_ignored() {
+        assert!(compile_lunas("html:\n  <div></div>\ni18n:\n  en: Hi\n", None).is_ok());
+    }
+
+    #[test]
+    fn test_template_diagnostic_points_at_attribute() {
+        let diagnostics =
+            compile_lunas("html:\n  <div>\n    <p :title></p>\n  </div>\n", None).unwrap_err();
+        assert_eq!(diagnostics[0].code, codes::INVALID_TEMPLATE);
+        let range = diagnostics[0].range.unwrap();
+        assert_eq!((range.start.line, range.start.column), (3, 8));
+        assert_eq!(range.end.column, 14);
+    }
+
+    #[test]
+    fn test_svg_elements_are_created_in_their_namespace() {
+        let output = compile_lunas(
+            "html:\n  <div>\n    <svg viewBox=\"0 0 10 10\">\n      <rect :if=\"show\" :width=\"w\"></rect>\n    </svg>\n    <input :if=\"show\">\n  </div>\nscript:\n  let show = true\n  let w = 5\n",
+            None,
+        )
+        .unwrap();
+        assert!(output
+            .js
+            .contains(r#""rect", {}, "http://www.w3.org/2000/svg")"#));
+        assert!(output.js.contains(r#"$$createLunasElement(``, "input")"#));
+    }
+
+    #[test]
+    fn test_static_markup_is_escaped_for_its_context() {
+        let output = compile_lunas(
+            "html:\n  <div title=\"&quot;quoted&quot;\">Use `a &lt; b` or \\n, &amp; ${count}</div>\nscript:\n  let count = 0\n",
+            None,
+        )
+        .unwrap();
+        assert!(output
+            .js
+            .contains(r#"Use \`a &lt; b\` or \\n, &amp; ${$$lunasEscapeHtml(count.v)}"#));
+        assert!(output.js.contains(r#""title": `"quoted"`"#));
+    }
+
+    #[test]
+    fn test_component_markup_is_minified() {
+        let output = compile_lunas(
+            "html:\n  <div>\n    <p class=\"note\">Hello</p>\n  </div>\n",
+            None,
+        )
+        .unwrap();
+        assert!(output.js.contains("`<p class=note>Hello</p>`"));
+    }
+
+    #[test]
+    fn test_whitespace_is_collapsed_outside_of_preserving_elements() {
+        let output = compile_lunas(
+            "html(whitespace: collapse):\n  <div>\n    <p>\n      Hello\n      world\n    </p>\n    <pre>\n\n      kept  as is</pre>\n  </div>\n",
+            None,
+        )
+        .unwrap();
+        assert!(output
+            .js
+            .contains(r"`<p> Hello world </p><pre>\n\n    kept  as is</pre>`"));
+    }
+
+    #[test]
+    fn test_whitespace_is_kept_unless_asked_to_collapse() {
+        let output = compile_lunas(
+            "html:\n  <div>\n    <p class=\"code\">a\n      b</p>\n  </div>\nstyle:\n  .code{white-space:pre}\n",
+            None,
+        )
+        .unwrap();
+        assert!(output.js.contains(r"`<p class=code>a\n    b</p>`"));
+    }
+
+    #[test]
+    fn test_scoped_styles_apply_to_the_elements_of_the_component() {
+        let output = compile_lunas(
+            "@use Child from \"./child.lun\"\nhtml:\n  <div class=\"card\">\n    <p>Hi</p>\n    <b :if=\"show\"></b>\n    <Child></Child>\n  </div>\nstyle(scoped: true):\n  .card p, :global(body) {\n    color: red;\n  }\nscript:\n  let show = true\n",
+            None,
+        )
+        .unwrap();
+        let css = output.css.unwrap();
+        let attribute = css
+            .split(['[', ']'])
+            .nth(1)
+            .expect("scoped selector")
+            .to_string();
+        assert!(attribute.starts_with("data-lunas-"));
+        assert_eq!(
+            css,
+            format!(".card p[{}], body {{\n  color: red;\n}}", attribute)
+        );
+        assert!(output.js.contains(&format!("`<p {}>Hi</p>`", attribute)));
+        assert!(output.js.contains(&format!("\"{}\": ``", attribute)));
+        assert!(output.js.contains(&format!(
+            "$$createLunasElement(``, \"b\", {{\"{}\": ``,}})",
+            attribute
+        )));
+        // Component tags pass their attributes to the component instead
+        assert!(output.js.contains("Child({})"));
+    }
+
+    #[test]
+    fn test_server_target_renders_markup() {
+        let mut compiler = LunasCompiler::new();
+        compiler.set_target(CompileTarget::Server);
+        let output = compiler
+            .compile(
+                "@input title: string\n@use Child from \"./child.lun\"\nhtml:\n  <ul :class=\"{ shown: open }\" @click=\"toggle\">\n    <li :for=\"item of items\">${item}</li>\n    <b :if=\"open\">${title}</b>\n    <i :else></i>\n    <Child :value=\"open\"></Child>\n  </ul>\nscript:\n  let open = false\n  let items = [1, 2]\n  function toggle() {\n    open = !open\n  }\n",
+                None,
+            )
+            .unwrap();
+        assert!(output.js.contains("from \"lunas/server\";"));
+        assert!(output
+            .js
+            .contains("$$lunasInitSsrComponent($$lunasProps, [\"title\"], [\"title\"]);"));
+        assert!(output.js.contains(
+            "return `<ul ${$$lunasRenderAttr(\"class\", {\n        shown: open.v\n    }, null)}>${$$lunasRenderList(items.v, (item) => `<li>${$$lunasEscapeHtml(item)}</li>`)}${(open.v) ? `<b>${$$lunasEscapeHtml(title.v)}</b>` : \"\"}${(!(open.v)) ? `<i></i>` : \"\"}${Child({\"value\": open})}</ul>`;"
+        ));
+        assert!(!output.js.contains("$$lunasInitComponent"));
+        assert!(!output.js.contains("toggle)"));
+        let diagnostic = "browser".parse::<CompileTarget>().unwrap_err();
+        assert_eq!(diagnostic.code, codes::INVALID_TARGET);
+    }
+
+    #[test]
+    fn test_server_target_renders_content_bindings() {
+        let mut compiler = LunasCompiler::new();
+        compiler.set_target(CompileTarget::Server);
+        let output = compiler
+            .compile(
+                "html:\n  <div>\n    <p :innerHtml=\"html\">old</p>\n    <p :textContent=\"html\"></p>\n  </div>\nscript:\n  let html = \"<b>a</b>\"\n",
+                None,
+            )
+            .unwrap();
+        assert!(output.js.contains("<p>${html.v}</p>"));
+        assert!(output.js.contains("<p>${$$lunasEscapeHtml(html.v)}</p>"));
+        assert!(!output.js.contains("old"));
+    }
+
+    #[test]
+    fn test_keyed_for_block_passes_its_key() {
+        let compile = |source: &str| LunasCompiler::new().compile(source, None);
+        let output = compile(
+            "html:\n  <ul>\n    <li :for=\"row of rows\" :key=\"row.id + offset\">${row.name}</li>\n  </ul>\nscript:\n  let offset = 0\n  let rows = [{ id: 1, name: \"a\" }]\n",
+        )
+        .unwrap();
+        // The text using the item is a binding, so that a changed item can be patched
+        assert!(output.js.contains(
+            "[0],\n                (row, $$lunasForIndices) => [\n                    [[() => `${$$lunasEscapeHtml(row.name)}`], [1, ...$$lunasForIndices], 0, 1]\n                ],\n                (row) => (row.id + offset.v),\n                true\n"
+        ));
+        assert!(!output.js.contains("key"));
+
+        // The key is given the item as the pattern of `:for` binds it
+        let output = compile(
+            "html:\n  <ul>\n    <li :for=\"[i, row] of rows.entries()\" :key=\"row.id\">${i}: ${row.name}<input :value=\"row.name\" /></li>\n  </ul>\nscript:\n  let rows = [{ id: 1, name: \"a\" }]\n",
+        )
+        .unwrap();
+        assert!(output.js.contains("() => (rows.v.entries()),"));
+        assert!(output.js.contains(
+            "[[() => `${$$lunasEscapeHtml(i)}: ${$$lunasEscapeHtml(row.name)}`], [3, ...$$lunasForIndices], 0, 1]"
+        ));
+        assert!(output
+            .js
+            .contains("[[() => (row.name), \"value\"], [2, ...$$lunasForIndices], 0, 0]"));
+        assert!(output
+            .js
+            .contains("([i, row]) => (row.id),\n                true\n"));
+
+        // Event handlers keep the item they were added with, so such items are rendered again
+        let output = compile(
+            "html:\n  <ul>\n    <li :for=\"{ id, name } of rows\" :key=\"id\"><b @click=\"remove(id)\">${name}</b></li>\n  </ul>\nscript:\n  let rows = [{ id: 1, name: \"a\" }]\n  function remove(id) {}\n",
+        )
+        .unwrap();
+        assert!(output
+            .js
+            .contains("({ id, name }) => (id),\n                false\n"));
+
+        let errors = compile("html:\n  <p :key=\"1\">x</p>\n").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            ":key is only allowed on elements with :for"
+        );
+    }
+
+    #[test]
+    fn test_hydrate_target_adopts_server_rendered_markup() {
+        let source = "html:\n  <p @click=\"toggle\">\n    ${count}\n    <b :if=\"open\">on</b>\n    end\n  </p>\nscript:\n  let open = false\n  let count = 0\n  function toggle() {\n    open = !open\n  }\n";
+        let compile_to = |target| {
+            let mut compiler = LunasCompiler::new();
+            compiler.set_target(target);
+            compiler.compile(source, None).unwrap().js
+        };
+        let server = compile_to(CompileTarget::HydratableServer);
+        let hydrate = compile_to(CompileTarget::Hydrate);
+        let client = compile_to(CompileTarget::Client);
+
+        // Both sides name the nodes by the same markers, which stay the same between builds
+        let marker = server
+            .split("${\"<!--")
+            .nth(1)
+            .unwrap()
+            .split("-->")
+            .next()
+            .unwrap();
+        assert!(marker.starts_with('$'));
+        assert_eq!(compile_to(CompileTarget::HydratableServer), server);
+        assert!(hydrate.contains(&format!("[\"{}\"", marker)));
+        assert_eq!(server.matches("${\"<!--$").count(), 3);
+        assert_eq!(server.matches("${\"<!--/$").count(), 1);
+        assert!(hydrate.contains("null, [\"/$"));
+        // The component element is the element being hydrated, so it is not marked
+        assert!(server.contains("return `<p>"));
+        assert!(!client.contains("<!--"));
+        assert!(!client.contains(marker));
+        assert!(!compile_to(CompileTarget::Server).contains("<!--"));
+        assert!("hydrate".parse::<CompileTarget>().is_ok());
+        assert!("hydratable-server".parse::<CompileTarget>().is_ok());
+    }
+
+    #[test]
+    fn test_hydration_finds_elements_by_markers_instead_of_ids() {
+        let source = "@use Child from \"./child.lun\"\nhtml:\n  <div>\n    <button id=\"add\" @click=\"add\">+</button>\n    <ul>\n      <li :for=\"row of rows\"><b @click=\"pick(row)\">${row}</b><Child :value=\"row\"></Child></li>\n    </ul>\n  </div>\nscript:\n  let rows = [1, 2]\n  function add() {\n    rows = [...rows, rows.length + 1]\n  }\n  function pick(row) {}\n";
+        let compile_to = |target| {
+            let mut compiler = LunasCompiler::new();
+            compiler.set_target(target);
+            compiler.compile(source, None).unwrap().js
+        };
+        let server = compile_to(CompileTarget::HydratableServer);
+        let hydrate = compile_to(CompileTarget::Hydrate);
+        let markers = server
+            .split("${\"<!--")
+            .skip(1)
+            .map(|part| part.split("-->").next().unwrap())
+            .collect::<Vec<_>>();
+        let [button, ul, item, bold, child] = markers[..] else {
+            panic!("unexpected markers {:?}", markers);
+        };
+
+        // Only the id written in the template is rendered, once
+        assert_eq!(server.matches(" id=").count(), 1);
+        assert!(server.contains(&format!("${{\"<!--{}-->\"}}<button id=add>", button)));
+        assert!(hydrate.contains("$$lunasGetElmRefs([\"add\", "));
+        assert!(hydrate.contains(&format!(", 0, [\"{}\", \"{}\"]);", button, ul)));
+
+        // The elements and components of every item of a `:for` block are marked alike
+        assert!(server.contains(&format!(
+            "(row) => `${{\"<!--{}-->\"}}<li>${{\"<!--{}-->\"}}<b>${{$$lunasEscapeHtml(row)}}</b>${{\"<!--{}-->\"}}${{Child(",
+            item, bold, child
+        )));
+        assert!(hydrate.contains(&format!(", [3, ...$$lunasForIndices], [\"{}\"]);", bold)));
+        assert!(hydrate.contains(&format!("$$lunasForIndices, \"{}\");", child)));
+    }
+
+    /// Decodes the mappings of a source map into (generated line, original line) pairs, both
+    /// 0-based.
+    fn mapped_lines(source_map: &str) -> Vec<(usize, usize)> {
+        const DIGITS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let map: serde_json::Value = serde_json::from_str(source_map).unwrap();
+        let mut lines = vec![];
+        let mut original_line = 0i64;
+        for (generated_line, segments) in map["mappings"].as_str().unwrap().split(';').enumerate() {
+            for segment in segments.split(',').filter(|s| !s.is_empty()) {
+                let (mut fields, mut value, mut shift) = (vec![], 0i64, 0);
+                for c in segment.chars() {
+                    let digit = DIGITS.find(c).unwrap() as i64;
+                    value += (digit & 31) << shift;
+                    shift += 5;
+                    if digit & 32 == 0 {
+                        fields.push(if value & 1 == 1 {
+                            -(value >> 1)
+                        } else {
+                            value >> 1
+                        });
+                        (value, shift) = (0, 0);
+                    }
+                }
+                original_line += fields[2];
+                lines.push((generated_line, original_line as usize));
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn test_source_maps_lead_back_to_the_lun_file() {
+        let source = "html:\n  <div>\n    <button @click=\"increment\">${count}</button>\n  </div>\nscript:\n  let count = 0\n  function increment() {\n    count++\n  }\nstyle:\n  button {\n    color: red;\n  }\n";
+        let mut compiler = LunasCompiler::new();
+        compiler.set_source_name("counter.lun");
+        let output = compiler.compile(source, None).unwrap();
+
+        let map: serde_json::Value = serde_json::from_str(&output.js_source_map).unwrap();
+        assert_eq!(map["version"], 3);
+        assert_eq!(map["sources"][0], "counter.lun");
+        assert_eq!(map["sourcesContent"][0], source);
+
+        let js_lines = output.js.lines().collect::<Vec<_>>();
+        let source_lines = source.lines().collect::<Vec<_>>();
+        let mapped = mapped_lines(&output.js_source_map)
+            .into_iter()
+            .map(|(generated, original)| (js_lines[generated].trim(), source_lines[original]))
+            .collect::<Vec<_>>();
+        assert!(mapped.contains(&("count.v++;", "    count++")));
+        assert!(mapped.contains(&("function increment() {", "  function increment() {")));
+        assert!(mapped
+            .iter()
+            .any(|(js, lun)| js.contains("\"click\", increment") && lun.contains("@click")));
+        assert!(
+            mapped
+                .iter()
+                .any(|(js, lun)| js.contains("$$lunasEscapeHtml(count.v)")
+                    && lun.contains("${count}"))
+        );
+
+        let css_lines = mapped_lines(output.css_source_map.as_deref().unwrap());
+        assert_eq!(css_lines, vec![(0, 10), (1, 11), (2, 12)]);
+    }
 }
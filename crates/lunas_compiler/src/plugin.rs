@@ -0,0 +1,31 @@
+use lunas_parser::{CustomBlock, Diagnostic};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Turns a custom top-level block (`docs:`, `i18n:`, ...) into extra output.
+///
+/// Register implementations on a [`LunasCompiler`](crate::LunasCompiler); blocks whose name
+/// has no registered plugin are ignored.
+pub trait CustomBlockPlugin {
+    /// Name of the blocks this plugin handles, e.g. `"docs"` for `docs:` blocks.
+    fn block_name(&self) -> &str;
+
+    /// Called once for every block with a matching name. Diagnostics without a range are
+    /// reported at the block header.
+    fn transform(&self, block: &CustomBlock) -> Result<CustomBlockOutput, Vec<Diagnostic>>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CustomBlockOutput {
+    /// Module-level JavaScript appended to the compiled component.
+    pub js: Option<String>,
+    /// Arbitrary data exposed to tooling through the compiler output.
+    pub metadata: Option<Value>,
+}
+
+/// Metadata produced by a plugin for one custom block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomBlockMetadata {
+    pub block_name: String,
+    pub value: Value,
+}
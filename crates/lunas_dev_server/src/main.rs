@@ -1,4 +1,4 @@
-use lunas_compiler::compile;
+use lunas_compiler::LunasCompiler;
 use warp::Filter;
 
 #[tokio::main]
@@ -15,12 +15,16 @@ async fn main() {
                 Some(v) => Some(v.as_str().expect("engine_path is not a string").to_string()),
                 None => None,
             };
-            match compile(code.clone(), engine_path) {
+            let mut compiler = LunasCompiler::new();
+            if let Some(v) = body.get("sourceName") {
+                compiler.set_source_name(v.as_str().expect("sourceName is not a string"));
+            }
+            match compiler.compile(&code, engine_path) {
                 Ok(r) => {
                     warp::reply::with_status(warp::reply::json(&r), warp::http::StatusCode::OK)
                 }
-                Err(e) => warp::reply::with_status(
-                    warp::reply::json(&e),
+                Err(diagnostics) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({ "diagnostics": diagnostics })),
                     warp::http::StatusCode::BAD_REQUEST,
                 ),
             }
@@ -1,4 +1,8 @@
-use lunas_parser::{DetailedBlock, DetailedMetaData, PropsInput, UseComponentStatement};
+use lunas_html_parser::SerializeOptions;
+use lunas_parser::{
+    diagnostics::{codes, Diagnostic, SourceRange},
+    DetailedBlock, DetailedMetaData, PropsInput, UseComponentStatement,
+};
 
 use crate::{
     consts::ROUTER_VIEW,
@@ -11,24 +15,54 @@ use crate::{
         gen_reference_getter::gen_reference_getter, utils::create_indent,
     },
     orig_html_struct::structs::{Node, NodeContent},
+    source_map::{css_source_map, resolve_mapping_markers, SourceMap},
     structs::{
         ctx::ContextCategories,
+        js_analyze::JsFunctionDeps,
         transform_info::{sort_if_blocks, TextNodeRendererGroup, VariableNameAndAssignedNumber},
         transform_targets::{sort_elm_and_reactive_info, NodeAndReactiveInfo},
     },
     transformers::{
         html_utils::{check_html_elms, create_lunas_internal_component_statement},
-        imports::generate_import_string,
-        inputs::generate_input_variable_decl,
+        hydration::{assign_hydration_markers, use_hydration_markers},
+        imports::{component_tag_names, generate_component_import, generate_import_string},
+        inputs::{generate_input_variable_decl, required_input_names},
         js_utils::{analyze_js, load_lunas_script_variables},
         router::generate_router_initialization_code,
+        scoped_css::{add_scope_attribute, scope_attribute_name, scope_css},
+        whitespace::collapse_template_whitespace,
     },
 };
 
-pub fn generate_js_from_blocks(
-    blocks: &DetailedBlock,
-    engine_path: Option<String>,
-) -> Result<(String, Option<String>), String> {
+/// JavaScript and CSS of a compiled component, with the source maps leading back to the `.lun`
+/// file.
+#[derive(Debug, Clone)]
+pub struct CompiledComponent {
+    pub js: String,
+    pub css: Option<String>,
+    pub js_source_map: SourceMap,
+    pub css_source_map: Option<SourceMap>,
+}
+
+/// What every code generation target needs from a component: the modules it imports, its script
+/// and its template.
+pub(crate) struct ComponentParts<'a> {
+    pub inputs: Vec<&'a PropsInput>,
+    pub component_names: Vec<String>,
+    pub using_auto_routing: bool,
+    /// Imports of the script and of the components it uses
+    pub imports: Vec<String>,
+    /// Statements of the component function, starting with the declaration of the props
+    pub codes: Vec<String>,
+    pub variables: Vec<VariableNameAndAssignedNumber>,
+    pub js_func_deps: Vec<JsFunctionDeps>,
+    /// The template, with whitespace collapsed and the scope attribute added when asked for
+    pub template: Node,
+    /// The style block, with its selectors scoped when asked for
+    pub css: Option<String>,
+}
+
+pub(crate) fn component_parts(blocks: &DetailedBlock) -> Result<ComponentParts<'_>, Diagnostic> {
     let use_component_statements = blocks
         .detailed_meta_data
         .iter()
@@ -48,16 +82,9 @@ pub fn generate_js_from_blocks(
 
     let mut component_names = use_component_statements
         .iter()
-        .map(|use_component| use_component.component_name.clone())
+        .flat_map(|use_component| component_tag_names(use_component))
         .collect::<Vec<String>>();
 
-    let mut imports = vec![];
-
-    #[cfg(not(feature = "playground"))]
-    {
-        imports.push("import { $$lunasRouter } from \"lunas/router\";".to_string());
-    }
-
     let using_auto_routing = blocks
         .detailed_meta_data
         .iter()
@@ -67,10 +94,6 @@ pub fn generate_js_from_blocks(
         });
 
     if using_auto_routing {
-        imports.push(
-            "import { routes as $$lunasGeneratedRoutes } from \"virtual:generated-routes\";"
-                .to_string(),
-        );
         component_names.push(ROUTER_VIEW.to_string());
     }
 
@@ -83,20 +106,20 @@ pub fn generate_js_from_blocks(
     //         _ => false,
     //     });
 
-    let engine_path = match engine_path.is_none() {
-        true => "lunas/engine".to_string(),
-        false => engine_path.unwrap(),
-    };
-
     let mut variables = vec![];
 
-    let props_assignment = generate_input_variable_decl(&inputs, &mut variables);
+    let props_assignment =
+        generate_input_variable_decl(&inputs, &mut variables).map_err(codegen_error)?;
 
     let mut codes = vec![];
 
     let (imports_in_script, (js_output, js_output_tail), js_func_deps, lun_imports) =
         analyze_js(blocks, inputs.len() as u32, &mut variables);
 
+    if let Some(props_assignment) = props_assignment {
+        codes.push(props_assignment);
+    }
+
     codes.push(js_output);
 
     if lun_imports.len() > 0 {
@@ -107,13 +130,130 @@ pub fn generate_js_from_blocks(
         codes.push(js_output_tail);
     }
 
-    imports.extend(imports_in_script.clone());
+    let mut imports = imports_in_script;
     for use_component in use_component_statements {
-        imports.push(format!(
-            "import {} from \"{}\";",
-            use_component.component_name, use_component.component_path
-        ));
+        imports.push(generate_component_import(use_component));
+    }
+
+    let mut template =
+        Node::new_from_dom(&blocks.detailed_language_blocks.dom).map_err(codegen_error)?;
+    if blocks.detailed_language_blocks.collapses_whitespace() {
+        collapse_template_whitespace(&mut template);
+    }
+
+    let language_blocks = &blocks.detailed_language_blocks;
+    let css = match (&language_blocks.css, &language_blocks.css_location) {
+        (Some(css), Some(location)) if language_blocks.scopes_css() => {
+            let attribute = scope_attribute_name(
+                css,
+                &language_blocks.dom.to_markup(&SerializeOptions::minify()),
+            );
+            add_scope_attribute(&mut template, &attribute, &component_names);
+            let scoped =
+                scope_css(css, &attribute, language_blocks.css_lang()).map_err(|message| {
+                    Diagnostic::error(codes::INVALID_STYLE, message).with_range(SourceRange {
+                        start: location.position(css, 0),
+                        end: location.position(css, css.len()),
+                    })
+                })?;
+            Some(scoped)
+        }
+        (css, _) => css.clone(),
+    };
+
+    Ok(ComponentParts {
+        inputs,
+        component_names,
+        using_auto_routing,
+        imports,
+        codes,
+        variables,
+        js_func_deps,
+        template,
+        css,
+    })
+}
+
+/// Strips the source map markers from the assembled module and maps the style block.
+pub(crate) fn compiled_component(
+    blocks: &DetailedBlock,
+    full_js_code: &str,
+    css: Option<String>,
+) -> CompiledComponent {
+    let (js, js_source_map) = resolve_mapping_markers(full_js_code);
+    let css_source_map = css
+        .as_ref()
+        .zip(blocks.detailed_language_blocks.css_location.as_ref())
+        .map(|(css, location)| css_source_map(css, location));
+
+    CompiledComponent {
+        js,
+        css,
+        js_source_map,
+        css_source_map,
+    }
+}
+
+pub fn generate_js_from_blocks(
+    blocks: &DetailedBlock,
+    engine_path: Option<String>,
+) -> Result<CompiledComponent, Diagnostic> {
+    generate_client_js(blocks, engine_path, false)
+}
+
+/// Generates a client module that can also adopt the markup rendered by the server-side
+/// rendering module of the component, through the `hydrate` method of the component.
+///
+/// The elements the client gets references to are found by hydration markers instead of
+/// random ids, and the text nodes, blocks and components it would create are adopted from the
+/// server-rendered markup. Components mounted without it are built as in the client output.
+pub fn generate_hydratable_js_from_blocks(
+    blocks: &DetailedBlock,
+    engine_path: Option<String>,
+) -> Result<CompiledComponent, Diagnostic> {
+    generate_client_js(blocks, engine_path, true)
+}
+
+fn generate_client_js(
+    blocks: &DetailedBlock,
+    engine_path: Option<String>,
+    hydratable: bool,
+) -> Result<CompiledComponent, Diagnostic> {
+    let ComponentParts {
+        inputs,
+        component_names,
+        using_auto_routing,
+        imports: component_imports,
+        mut codes,
+        variables,
+        js_func_deps,
+        template: mut new_node,
+        css,
+    } = component_parts(blocks)?;
+
+    if hydratable {
+        assign_hydration_markers(&mut new_node);
+    }
+
+    let mut imports = vec![];
+
+    #[cfg(not(feature = "playground"))]
+    {
+        imports.push("import { $$lunasRouter } from \"lunas/router\";".to_string());
+    }
+
+    if using_auto_routing {
+        imports.push(
+            "import { routes as $$lunasGeneratedRoutes } from \"virtual:generated-routes\";"
+                .to_string(),
+        );
     }
+    imports.extend(component_imports);
+
+    let engine_path = match engine_path.is_none() {
+        true => "lunas/engine".to_string(),
+        false => engine_path.unwrap(),
+    };
 
     // Clone HTML as mutable reference
     let mut ref_map = vec![];
@@ -130,7 +270,6 @@ pub fn generate_js_from_blocks(
     };
 
     let mut ref_node_ids = vec![];
-    let mut new_node = Node::new_from_dom(&blocks.detailed_language_blocks.dom)?;
 
     let variable_names = &variables
         .iter()
@@ -160,6 +299,7 @@ pub fn generate_js_from_blocks(
         &mut text_node_renderer,
         &mut ctx_cats,
         &vec![],  // ctx
+        &vec![],  // item_bindings
         &vec![0], // ctx_num
         1,        // ctx_num_index
         false,    // is_root
@@ -168,6 +308,17 @@ pub fn generate_js_from_blocks(
     sort_if_blocks(&mut if_blocks_info);
     sort_elm_and_reactive_info(&mut elm_and_var_relation);
 
+    if hydratable {
+        use_hydration_markers(
+            &mut ref_map,
+            &new_node,
+            &mut text_node_renderer,
+            &mut if_blocks_info,
+            &mut for_blocks_info,
+            &mut custom_component_blocks_info,
+        );
+    }
+
     // TODO: reconsider about this unwrap
     let new_elm = match new_node.content {
         NodeContent::Element(elm) => elm,
@@ -183,11 +334,6 @@ pub fn generate_js_from_blocks(
     );
     codes.push(html_insert);
 
-    match props_assignment.is_some() {
-        true => codes.insert(0, props_assignment.unwrap()),
-        false => {}
-    }
-
     let text_node_renderer_group = TextNodeRendererGroup::new(
         &if_blocks_info,
         &for_blocks_info,
@@ -232,7 +378,8 @@ pub fn generate_js_from_blocks(
         &ctx_cats,
         None,
         false,
-    )?;
+    )
+    .map_err(codegen_error)?;
     let (render_for, _) = gen_render_for_blk_func(
         &for_blocks_info,
         &ref_map,
@@ -248,7 +395,8 @@ pub fn generate_js_from_blocks(
         &for_blocks_info,
         None,
         false,
-    )?;
+    )
+    .map_err(codegen_error)?;
     after_mount_code_array.extend(render_if);
     after_mount_code_array.extend(render_for);
     let render_component = gen_render_custom_component_statements(
@@ -257,12 +405,13 @@ pub fn generate_js_from_blocks(
         &variable_names_to_add_value_accessor,
         &mut ref_node_ids,
         false,
-    )?;
+    )
+    .map_err(codegen_error)?;
     if using_auto_routing {
-        after_mount_code_array.push(generate_router_initialization_code(
-            &custom_component_blocks_info,
-            &ref_node_ids,
-        )?);
+        after_mount_code_array.push(
+            generate_router_initialization_code(&custom_component_blocks_info, &ref_node_ids)
+                .map_err(codegen_error)?,
+        );
     }
     after_mount_code_array.extend(render_component);
     let after_mount_code = after_mount_code_array
@@ -282,9 +431,11 @@ pub fn generate_js_from_blocks(
     codes.push("return $$lunasComponentReturn;".to_string());
 
     let full_js_code = gen_full_code(engine_path, imports, codes, inputs);
-    let css_code = blocks.detailed_language_blocks.css.clone();
+    Ok(compiled_component(blocks, &full_js_code, css))
+}
 
-    Ok((full_js_code, css_code))
+pub(crate) fn codegen_error(message: String) -> Diagnostic {
+    Diagnostic::error(codes::CODEGEN, message)
 }
 
 fn gen_full_code(
@@ -301,7 +452,14 @@ fn gen_full_code(
                 .iter()
                 .map(|i| format!("\"{}\"", i.variable_name.clone()))
                 .collect::<Vec<String>>();
-            format!(", [{}]", arr.join(", "))
+            let required = required_input_names(&inputs)
+                .iter()
+                .map(|name| format!("\"{}\"", name))
+                .collect::<Vec<String>>();
+            match required.is_empty() {
+                true => format!(", [{}]", arr.join(", ")),
+                false => format!(", [{}], [{}]", arr.join(", "), required.join(", ")),
+            }
         }
     };
 
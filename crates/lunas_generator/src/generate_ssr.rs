@@ -0,0 +1,122 @@
+use lunas_parser::{DetailedBlock, Diagnostic, PropsInput};
+
+use crate::{
+    generate_js::{compiled_component, component_parts, CompiledComponent, ComponentParts},
+    generate_statements::{gen_ssr_markup::gen_ssr_markup, utils::create_indent},
+    transformers::{
+        hydration::{assign_hydration_markers, hydration_targets, HydrationTargets},
+        imports::generate_import_string,
+        inputs::required_input_names,
+    },
+};
+
+/// Generates a module for server-side rendering, whose default export is a function from the
+/// props of the component to its markup.
+///
+/// The script runs as in the browser, so the markup shows the state right after it ran. Values
+/// passed as props may be plain values or the reactive values passed between components.
+/// `runtime_path` is the module the helpers are imported from, `lunas/server` by default.
+pub fn generate_ssr_from_blocks(
+    blocks: &DetailedBlock,
+    runtime_path: Option<String>,
+) -> Result<CompiledComponent, Diagnostic> {
+    generate_ssr(blocks, runtime_path, false)
+}
+
+/// Generates a module for server-side rendering like [`generate_ssr_from_blocks`], whose markup
+/// also carries the hydration markers the hydratable client output adopts it by.
+pub fn generate_hydratable_ssr_from_blocks(
+    blocks: &DetailedBlock,
+    runtime_path: Option<String>,
+) -> Result<CompiledComponent, Diagnostic> {
+    generate_ssr(blocks, runtime_path, true)
+}
+
+fn generate_ssr(
+    blocks: &DetailedBlock,
+    runtime_path: Option<String>,
+    hydratable: bool,
+) -> Result<CompiledComponent, Diagnostic> {
+    let ComponentParts {
+        inputs,
+        component_names,
+        imports,
+        mut codes,
+        variables,
+        js_func_deps,
+        mut template,
+        css,
+        ..
+    } = component_parts(blocks)?;
+
+    let variable_names = variables
+        .iter()
+        .map(|v| v.name.clone())
+        .collect::<Vec<String>>();
+    let variable_names_to_add_value_accessor = variables
+        .iter()
+        .filter(|v| v.to_add_value_accessor)
+        .map(|v| v.name.clone())
+        .collect::<Vec<String>>();
+
+    let hydration_targets = match hydratable {
+        true => {
+            assign_hydration_markers(&mut template);
+            hydration_targets(
+                &template,
+                &variable_names,
+                &variable_names_to_add_value_accessor,
+                &component_names,
+                &js_func_deps,
+            )?
+        }
+        false => HydrationTargets::default(),
+    };
+    let markup = gen_ssr_markup(
+        &template,
+        &variable_names,
+        &variable_names_to_add_value_accessor,
+        &component_names,
+        &js_func_deps,
+        &hydration_targets,
+    )?;
+    codes.push(format!("return `{}`;", markup));
+
+    let runtime_path = runtime_path.unwrap_or_else(|| "lunas/server".to_string());
+    let full_js_code = gen_full_ssr_code(runtime_path, imports, codes, inputs);
+    Ok(compiled_component(blocks, &full_js_code, css))
+}
+
+fn gen_full_ssr_code(
+    runtime_path: String,
+    imports_string: Vec<String>,
+    codes: Vec<String>,
+    inputs: Vec<&PropsInput>,
+) -> String {
+    let imports_string = generate_import_string(&imports_string);
+    let quote = |names: Vec<String>| {
+        names
+            .iter()
+            .map(|name| format!("\"{}\"", name))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let input_names = quote(inputs.iter().map(|i| i.variable_name.clone()).collect());
+    let required_names = quote(required_input_names(&inputs));
+
+    let code = codes
+        .iter()
+        .map(|c| create_indent(c))
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!(
+        r#"import {{ $$lunasEscapeHtml, $$lunasInitSsrComponent, $$lunasRenderAttr, $$lunasRenderList, $$lunasCreateNonReactive }} from "{}";{}
+
+export default function($$lunasProps = {{}}) {{
+    const {{ args, $$lunasSetImportVars, $$lunasAfterMount, $$lunasAfterUnmount, $$lunasReactive, $$lunasWatch }} = $$lunasInitSsrComponent($$lunasProps, [{}], [{}]);
+{}
+}}
+"#,
+        runtime_path, imports_string, input_names, required_names, code,
+    )
+}
@@ -1,4 +1,5 @@
 use crate::structs::transform_info::TextNodeRendererGroup;
+use lunas_html_parser::escape_template_literal;
 
 use super::utils::create_indent;
 
@@ -12,6 +13,7 @@ pub fn gen_create_anchor_statements(
     let mut create_anchor_statements = vec![];
     let mut iter = text_node_renderer.renderers.iter().peekable();
     let mut amount_of_next_elm = 1;
+    let mut anchor_hydration_markers = vec![];
     while let Some(render) = iter.next() {
         match render {
             crate::structs::transform_info::TextNodeRenderer::ManualRenderer(txt_renderer) => {
@@ -42,11 +44,17 @@ pub fn gen_create_anchor_statements(
                         false => parent_node_idx,
                     }
                 };
+                // Hydratable output names the server-rendered text node to adopt
+                let hydration_marker = match &txt_renderer.hydration_marker {
+                    Some(marker) => format!(", [\"{}\"]", marker),
+                    None => "".to_string(),
+                };
                 let create_anchor_statement = format!(
-                    "[1, {}, {}, `{}`],",
+                    "[1, {}, {}, `{}`{}],",
                     &parent_node_idx,
                     &anchor_idx,
-                    &txt_renderer.content.trim(),
+                    escape_template_literal(&txt_renderer.content),
+                    hydration_marker,
                 );
                 ref_node_ids.push(txt_renderer.text_node_id.clone());
                 create_anchor_statements.push(create_anchor_statement);
@@ -61,6 +69,9 @@ pub fn gen_create_anchor_statements(
                 if &ctx != ctx_condition {
                     continue;
                 }
+                // Anchors are adopted in place of the `<!--/marker-->` comment rendered after
+                // the server-rendered block
+                anchor_hydration_markers.push(render.hydration_marker().map(|m| format!("/{}", m)));
                 if let Some(next_renderer) = next_render {
                     if render.is_next_elm_the_same_anchor(next_renderer) {
                         ref_node_ids.push(format!("{}-anchor", block_id));
@@ -98,9 +109,16 @@ pub fn gen_create_anchor_statements(
                     .position(|id| id == &parent_id)
                     .unwrap()
                     .to_string();
+                let hydration_markers = match anchor_hydration_markers
+                    .drain(..)
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(markers) => format!(", null, [\"{}\"]", markers.join("\", \"")),
+                    None => "".to_string(),
+                };
                 let create_anchor_statement = format!(
-                    "[{}, {}, {}],",
-                    amount_of_next_elm, parent_node_idx, anchor_node_idx
+                    "[{}, {}, {}{}],",
+                    amount_of_next_elm, parent_node_idx, anchor_node_idx, hydration_markers
                 );
                 create_anchor_statements.push(create_anchor_statement);
                 ref_node_ids.push(format!("{}-anchor", block_id));
@@ -1,4 +1,4 @@
-use crate::structs::transform_info::ActionAndTarget;
+use crate::{source_map::mapping_marker, structs::transform_info::ActionAndTarget};
 
 use super::utils::create_indent;
 
@@ -27,7 +27,8 @@ pub fn generate_create_event_listener(
             false => reference_node_idx.to_string(),
         };
         result.push(format!(
-            "[{}, \"{}\", {}]{}",
+            "{}[{}, \"{}\", {}]{}",
+            mapping_marker(&action_and_target.range.start),
             reference_string,
             action_and_target.action_name,
             action_and_target.action.to_string(),
@@ -1,7 +1,11 @@
+use lunas_html_parser::escape_template_literal;
 use num_bigint::BigUint;
 
-use crate::structs::{
-    transform_info::VariableNameAndAssignedNumber, transform_targets::NodeAndReactiveInfo,
+use crate::{
+    source_map::mapping_marker,
+    structs::{
+        transform_info::VariableNameAndAssignedNumber, transform_targets::NodeAndReactiveInfo,
+    },
 };
 
 use super::utils::{create_indent, get_combined_binary_number};
@@ -58,13 +62,14 @@ pub fn gen_create_fragments(
                     };
 
                     let default_value_param = if let Some(default_value) = c.default_value {
-                        format!(r#", "{}""#, default_value)
+                        format!(", {}", serde_json::Value::from(default_value))
                     } else {
                         "".to_string()
                     };
 
                     fragments.push(format!(
-                        "[[() => ({}), \"{}\"{}], {}, {}, {}]",
+                        "{}[[() => ({}), \"{}\"{}], {}, {}, {}]",
+                        mapping_marker(&c.range.start),
                         c.content_of_attr,
                         c.attribute_key,
                         default_value_param,
@@ -75,25 +80,28 @@ pub fn gen_create_fragments(
                 }
             }
             _ => {
-                let (depending_variables, target_id, content) = match elm_and_variable_relation {
-                    NodeAndReactiveInfo::TextAndVariableContentRelation(
-                        text_and_variable_content_relation,
-                    ) => (
-                        text_and_variable_content_relation.dep_vars.clone(),
-                        text_and_variable_content_relation.text_node_id.clone(),
-                        text_and_variable_content_relation
-                            .content_of_element
-                            .clone(),
-                    ),
-                    NodeAndReactiveInfo::ElmAndVariableRelation(
-                        elm_and_variable_content_relation,
-                    ) => (
-                        elm_and_variable_content_relation.dep_vars.clone(),
-                        elm_and_variable_content_relation.elm_id.clone(),
-                        elm_and_variable_content_relation.content_of_element.clone(),
-                    ),
-                    _ => panic!(),
-                };
+                let (depending_variables, target_id, content, range) =
+                    match elm_and_variable_relation {
+                        NodeAndReactiveInfo::TextAndVariableContentRelation(
+                            text_and_variable_content_relation,
+                        ) => (
+                            text_and_variable_content_relation.dep_vars.clone(),
+                            text_and_variable_content_relation.text_node_id.clone(),
+                            text_and_variable_content_relation
+                                .content_of_element
+                                .clone(),
+                            text_and_variable_content_relation.range,
+                        ),
+                        NodeAndReactiveInfo::ElmAndVariableRelation(
+                            elm_and_variable_content_relation,
+                        ) => (
+                            elm_and_variable_content_relation.dep_vars.clone(),
+                            elm_and_variable_content_relation.elm_id.clone(),
+                            elm_and_variable_content_relation.content_of_element.clone(),
+                            elm_and_variable_content_relation.range,
+                        ),
+                        _ => panic!(),
+                    };
 
                 let dep_vars_assigned_numbers = variable_name_and_assigned_numbers
                     .iter()
@@ -118,8 +126,9 @@ pub fn gen_create_fragments(
                 };
 
                 fragments.push(format!(
-                    "[[() => `{}`], {}, {}, {}]",
-                    content,
+                    "{}[[() => `{}`], {}, {}, {}]",
+                    mapping_marker(&range.start),
+                    escape_template_literal(&content),
                     target_node_index,
                     combined_number,
                     "1" // FragmentType.TEXT
@@ -16,6 +16,11 @@ pub fn gen_render_custom_component_statements(
         if custom_component_block.ctx != *ctx {
             continue;
         }
+        // Hydratable output names the server-rendered component to adopt
+        let hydration_marker = match &custom_component_block.hydration_marker {
+            Some(marker) => format!(", \"{}\"", marker),
+            None => "".to_string(),
+        };
         if custom_component_block.have_sibling_elm {
             let anchor = match custom_component_block.distance_to_next_elm > 1 {
                 true => {
@@ -70,14 +75,15 @@ pub fn gen_render_custom_component_statements(
                 false => "null".to_string(),
             };
             render_custom_statements.push(format!(
-                "$$lunasInsertComponent({}({}), {}, {}, {}, {}, {});",
+                "$$lunasInsertComponent({}({}), {}, {}, {}, {}, {}{});",
                 custom_component_block.component_name,
                 custom_component_block.args.to_object(variable_names)?,
                 parent_idx,
                 anchor,
                 ref_idx,
                 latest_ctx,
-                indices
+                indices,
+                hydration_marker
             ));
             ref_node_ids.push(format!(
                 "{}-component",
@@ -108,13 +114,14 @@ pub fn gen_render_custom_component_statements(
                 false => "null".to_string(),
             };
             render_custom_statements.push(format!(
-                "$$lunasMountComponent({}({}), {}, {}, {}, {});",
+                "$$lunasMountComponent({}({}), {}, {}, {}, {}{});",
                 custom_component_block.component_name,
                 custom_component_block.args.to_object(variable_names)?,
                 parent_idx,
                 ref_idx,
                 latest_ctx,
-                indices
+                indices,
+                hydration_marker
             ));
             ref_node_ids.push(format!(
                 "{}-component",
@@ -2,6 +2,7 @@ use num_bigint::BigUint;
 
 use crate::{
     orig_html_struct::structs::NodeContent,
+    source_map::mapping_marker,
     structs::{
         ctx::ContextCategories,
         transform_info::{
@@ -58,10 +59,11 @@ pub fn gen_render_for_blk_func(
         };
 
         let initial_ref_node_ids_len = ref_node_ids.len();
-        let create_internal_element_statement = match &for_block.node.content {
-            NodeContent::Element(elm) => {
-                create_lunas_internal_component_statement(elm, "$$createLunasElement")
-            }
+        let (create_internal_element_statement, iterable_range) = match &for_block.node.content {
+            NodeContent::Element(elm) => (
+                create_lunas_internal_component_statement(elm, "$$createLunasElement"),
+                elm.directive_range(&[":for"]),
+            ),
             _ => panic!(),
         };
 
@@ -268,10 +270,16 @@ pub fn gen_render_for_blk_func(
             },
         };
 
-        let for_fragments = if let Some(fragments) = fragments {
-            format!(",\n{}", fragments)
-        } else {
-            "".to_string()
+        let for_fragments = match (fragments, &for_block.key) {
+            (Some(fragments), None) => format!(",\n{}", fragments),
+            (fragments, Some(key)) => format!(
+                ",\n{},\n({}) => ({}),\n{}",
+                fragments.unwrap_or("null".to_string()),
+                for_block.for_info.raw,
+                key,
+                for_block.patch_items
+            ),
+            (None, None) => "".to_string(),
         };
 
         let anchor_idx = match idx_of_anchor_of_for_blk {
@@ -286,7 +294,7 @@ pub fn gen_render_for_blk_func(
         let create_for_func_inside = format!(
             r#"{},
 ({}, $$lunasForIndices) => {},
-() => ({}),
+{}() => ({}),
 {},
 {},
 {},
@@ -298,6 +306,7 @@ pub fn gen_render_for_blk_func(
             if_blk_name,
             for_block.for_info.raw,
             create_internal_element_statement,
+            mapping_marker(&iterable_range.start),
             for_block.for_info.iterable,
             for_on_create,
             context_if_extracted,
@@ -2,6 +2,7 @@ use num_bigint::BigUint;
 
 use crate::{
     orig_html_struct::structs::NodeContent,
+    source_map::mapping_marker,
     structs::{
         ctx::ContextCategories,
         transform_info::{
@@ -48,10 +49,11 @@ pub fn gen_render_if_blk_func(
             true => format!("[{}, ...$$lunasForIndices]", ref_node_ids.len()),
             false => ref_node_ids.len().to_string(),
         };
-        let create_internal_element_statement = match &if_block.node.content {
-            NodeContent::Element(elm) => {
-                create_lunas_internal_component_statement(elm, "$$createLunasElement")
-            }
+        let (create_internal_element_statement, condition_range) = match &if_block.node.content {
+            NodeContent::Element(elm) => (
+                create_lunas_internal_component_statement(elm, "$$createLunasElement"),
+                elm.directive_range(&[":if", ":elseif", ":else"]),
+            ),
             _ => panic!(),
         };
 
@@ -230,7 +232,7 @@ pub fn gen_render_if_blk_func(
         let create_if_func_inside = format!(
             r#"{},
 () => ({}),
-() => ({}),
+{}() => ({}),
 {},
 {},
 {},
@@ -239,6 +241,7 @@ pub fn gen_render_if_blk_func(
 [{}{}]{}"#,
             if_blk_name,
             create_internal_element_statement,
+            mapping_marker(&condition_range.start),
             if_block.condition,
             if_on_create,
             ctxjs_array,
@@ -73,9 +73,32 @@ pub fn gen_reference_getter(
         )
     };
 
+    // Hydratable output also names the markers of the server-rendered elements, `null` standing
+    // for the component element
+    let hydration_markers_str = if id_based_elements
+        .iter()
+        .any(|id| id.hydration_marker.is_some())
+    {
+        let markers = id_based_elements
+            .iter()
+            .map(|id| match &id.hydration_marker {
+                Some(marker) => format!("\"{}\"", marker),
+                None => "null".to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let offset_str = match offset_str.is_empty() {
+            true => ", 0",
+            false => "",
+        };
+        format!("{}, [{}]", offset_str, markers)
+    } else {
+        String::new()
+    };
+
     let ref_getter_str = format!(
-        "$$lunasGetElmRefs([{}], {}{});",
-        id_names_str, delete_id_map, offset_str
+        "$$lunasGetElmRefs([{}], {}{}{});",
+        id_names_str, delete_id_map, offset_str, hydration_markers_str
     );
 
     Some(ref_getter_str)
@@ -0,0 +1,273 @@
+use lunas_html_parser::{escape_template_literal, MarkupNode, SerializeOptions};
+use lunas_parser::{Diagnostic, ParsedFor, SourceRange};
+
+use crate::{
+    consts::ROUTER_VIEW,
+    orig_html_struct::structs::{Element, Node, NodeContent},
+    structs::{js_analyze::JsFunctionDeps, transform_info::ComponentArgs},
+    transformers::{
+        html_utils::{is_component_tag, replace_text_with_reactive_value, template_error_at},
+        hydration::HydrationTargets,
+        utils::append_v_to_vars_in_html,
+    },
+};
+
+/// Generates the content of a template literal that evaluates to the markup of the template, for
+/// server-side rendering.
+///
+/// Static markup is written the way the client writes it. Bindings become interpolations: `:if`
+/// and `:for` are evaluated in place, reactive attributes go through `$$lunasRenderAttr`, text
+/// is escaped with `$$lunasEscapeHtml` and components are called with their props. Event
+/// listeners are left out.
+///
+/// The hydration markers in `hydration_targets` are rendered for the hydrating client: as a
+/// `<!--marker-->` comment before the elements it gets references to and the text nodes,
+/// conditional and repeated elements and components it adopts, and as a `<!--/marker-->` comment
+/// where it puts the anchor of a block.
+pub fn gen_ssr_markup(
+    template: &Node,
+    variable_names: &Vec<String>,
+    variable_names_to_add_value_accessor: &Vec<String>,
+    component_names: &Vec<String>,
+    func_deps: &Vec<JsFunctionDeps>,
+    hydration_targets: &HydrationTargets,
+) -> Result<String, Diagnostic> {
+    let renderer = SsrRenderer {
+        variable_names,
+        variable_names_to_add_value_accessor,
+        component_names,
+        func_deps,
+        hydration_targets,
+    };
+    let mut template = template.clone();
+    renderer.render_node(&mut template, &mut vec![])?;
+    Ok(escape_template_literal(
+        &template.to_markup(&SerializeOptions::minify()),
+    ))
+}
+
+struct SsrRenderer<'a> {
+    variable_names: &'a Vec<String>,
+    variable_names_to_add_value_accessor: &'a Vec<String>,
+    component_names: &'a Vec<String>,
+    func_deps: &'a Vec<JsFunctionDeps>,
+    hydration_targets: &'a HydrationTargets,
+}
+
+/// A directive that decides whether and how often an element is rendered
+enum Control {
+    If(String),
+    For(ParsedFor),
+}
+
+impl SsrRenderer<'_> {
+    /// Rewrites `node` so that serializing it gives the markup with interpolations for the
+    /// bindings. `conditions` holds the conditions of the `:if` chain the siblings before `node`
+    /// belong to.
+    fn render_node(&self, node: &mut Node, conditions: &mut Vec<String>) -> Result<(), Diagnostic> {
+        let element = match &mut node.content {
+            NodeContent::Element(element) => element,
+            NodeContent::TextNode(text) => {
+                replace_text_with_reactive_value(
+                    text,
+                    self.variable_names,
+                    self.variable_names_to_add_value_accessor,
+                    self.func_deps,
+                )
+                .map_err(|e| template_error_at(SourceRange::from(&node.source_span), e))?;
+                if self.hydration_targets.text_nodes.contains(&node.uuid) {
+                    *text = format!("{}{}", hydration_comment(&node.uuid), text);
+                }
+                return Ok(());
+            }
+            NodeContent::Comment(_) => return Ok(()),
+        };
+
+        if is_component_tag(self.component_names, &element.tag_name) {
+            let mut rendered = match element.tag_name == ROUTER_VIEW {
+                // Routes are only known in the browser
+                true => String::new(),
+                false => format!(
+                    "{}${{{}({})}}",
+                    self.block_marker(&node.uuid),
+                    element.tag_name,
+                    ComponentArgs::new(&element.attributes_without_meta())
+                        .to_object(self.variable_names_to_add_value_accessor)
+                        .map_err(|e| template_error_at(element.range(), e))?
+                ),
+            };
+            rendered.push_str(&self.block_anchor(&node.uuid));
+            node.content = NodeContent::TextNode(rendered);
+            return Ok(());
+        }
+
+        let (controls, content) = self.render_attributes(element, conditions)?;
+        match content {
+            Some(content) => element.children = vec![Node::new_text(&content, &node.source_span)],
+            None => {
+                let mut child_conditions = vec![];
+                for mut child in std::mem::take(&mut element.children) {
+                    self.render_node(&mut child, &mut child_conditions)?;
+                    let is_element = matches!(child.content, NodeContent::Element(_));
+                    if is_element && self.hydration_targets.elements.contains(&child.uuid) {
+                        let comment = hydration_comment(&child.uuid);
+                        element
+                            .children
+                            .push(Node::new_text(&comment, &child.source_span));
+                    }
+                    element.children.push(child);
+                }
+            }
+        }
+        if controls.is_empty() {
+            return Ok(());
+        }
+
+        // The first directive is the outermost, as in the client
+        let mut content = format!(
+            "{}{}",
+            self.block_marker(&node.uuid),
+            escape_template_literal(&node.to_markup(&SerializeOptions::minify()))
+        );
+        for control in controls.iter().rev() {
+            content = match control {
+                Control::If(condition) => format!("${{({}) ? `{}` : \"\"}}", condition, content),
+                Control::For(for_info) => format!(
+                    "${{$$lunasRenderList({}, ({}) => `{}`)}}",
+                    for_info.iterable, for_info.raw, content
+                ),
+            };
+        }
+        content.push_str(&self.block_anchor(&node.uuid));
+        node.content = NodeContent::TextNode(content);
+        Ok(())
+    }
+
+    /// The comment the client finds the block or component of `uuid` by, if it adopts it
+    fn block_marker(&self, uuid: &String) -> String {
+        match self.hydration_targets.blocks.contains(uuid) {
+            true => hydration_comment(uuid),
+            false => String::new(),
+        }
+    }
+
+    /// The comment the anchor of the block of `uuid` is put in place of, if the client inserts one
+    fn block_anchor(&self, uuid: &String) -> String {
+        match self.hydration_targets.block_anchors.contains(uuid) {
+            true => hydration_comment(&format!("/{}", uuid)),
+            false => String::new(),
+        }
+    }
+
+    /// Replaces the bindings among the attributes of `element` with interpolations and returns
+    /// its `:for` and `:if` directives, along with the content set by `:innerHtml` or
+    /// `:textContent` in place of its children.
+    fn render_attributes(
+        &self,
+        element: &mut Element,
+        conditions: &mut Vec<String>,
+    ) -> Result<(Vec<Control>, Option<String>), Diagnostic> {
+        let mut controls = vec![];
+        let mut content = None;
+        for (key, value) in element.clone().attributes_to_array() {
+            let range = element.attribute_range(&key);
+            let expression = |value: &str| {
+                append_v_to_vars_in_html(
+                    value,
+                    self.variable_names,
+                    self.variable_names_to_add_value_accessor,
+                    self.func_deps,
+                    true,
+                )
+                .map(|(expression, _)| expression)
+                .map_err(|e| template_error_at(range, e))
+            };
+
+            if key == ":if" || key == ":elseif" || key == ":else" {
+                if key != ":if" && conditions.is_empty() {
+                    return Err(template_error_at(
+                        range,
+                        format!(r#"No matching :if statement found for "{}""#, key),
+                    ));
+                }
+                let mut condition = conditions
+                    .iter()
+                    .map(|condition| format!("!({})", condition))
+                    .collect::<Vec<_>>();
+                if key == ":if" {
+                    conditions.clear();
+                    condition.clear();
+                }
+                if key != ":else" {
+                    let value = value.clone().ok_or_else(|| {
+                        template_error_at(range, format!("Missing value for {} attribute", key))
+                    })?;
+                    let value = expression(&value)?;
+                    condition.push(value.clone());
+                    conditions.push(value);
+                }
+                controls.push(Control::If(condition.join(" && ")));
+            } else if key == ":for" {
+                let value = value
+                    .clone()
+                    .ok_or_else(|| template_error_at(range, "Missing value for :for attribute"))?;
+                let for_info = ParsedFor::parse(&value)
+                    .map_err(|e| template_error_at(range, e.to_string()))?;
+                let iterable = expression(&for_info.iterable)?;
+                controls.push(Control::For(for_info.clone_with_new_iterable(&iterable)));
+            } else if key == ":key" {
+                // Keys only matter to the client, when the list changes
+            } else if let Some(name) = key.strip_prefix("::") {
+                if let Some(variable) = &value {
+                    let rendered = format!("${{$$lunasRenderAttr(\"{}\", {}.v)}}", name, variable);
+                    element.attributes.insert(rendered, None);
+                }
+            } else if key == ":innerHtml" || key == ":textContent" {
+                let value = value.clone().ok_or_else(|| {
+                    template_error_at(range, format!("Missing value for {}", key))
+                })?;
+                content = Some(match key.as_str() {
+                    ":innerHtml" => format!("${{{}}}", expression(&value)?),
+                    _ => format!("${{$$lunasEscapeHtml({})}}", expression(&value)?),
+                });
+            } else if let Some(name) = key.strip_prefix(':') {
+                let value = value.clone().ok_or_else(|| {
+                    template_error_at(range, format!("value of attribute :{} is null", name))
+                })?;
+                // A static value of the same attribute is the default, as in the client
+                let default_value = match name {
+                    "id" => None,
+                    _ => element.attributes.shift_remove(name).flatten(),
+                };
+                let rendered = format!(
+                    "${{$$lunasRenderAttr(\"{}\", {}, {})}}",
+                    name,
+                    expression(&value)?,
+                    serde_json::Value::from(default_value)
+                );
+                element.attributes.insert(rendered, None);
+            } else if key.starts_with('@') {
+                // Event listeners are only attached in the browser
+            } else {
+                if let Some(Some(value)) = element.attributes.get_mut(&key) {
+                    replace_text_with_reactive_value(
+                        value,
+                        self.variable_names,
+                        self.variable_names_to_add_value_accessor,
+                        self.func_deps,
+                    )
+                    .map_err(|e| template_error_at(range, e))?;
+                }
+                continue;
+            }
+            element.attributes.shift_remove(&key);
+        }
+        Ok((controls, content))
+    }
+}
+
+/// The comment the hydrating client finds the node rendered after it by. It is interpolated, as
+/// the text it is written in is escaped.
+fn hydration_comment(marker: &str) -> String {
+    format!("${{\"<!--{}-->\"}}", marker)
+}
@@ -5,4 +5,5 @@ pub mod gen_custom_component;
 pub mod gen_for_blk;
 pub mod gen_if_blk;
 pub mod gen_reference_getter;
+pub mod gen_ssr_markup;
 pub mod utils;
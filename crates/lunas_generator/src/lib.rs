@@ -1,22 +1,62 @@
 mod ast_analyzer;
 mod consts;
 mod generate_js;
+mod generate_ssr;
 mod generate_statements;
 mod js_utils;
 mod orig_html_struct;
+mod source_map;
 mod structs;
 mod transformers;
 mod utils;
-use generate_js::generate_js_from_blocks;
-use lunas_parser::DetailedBlock;
+pub use generate_js::CompiledComponent;
+use generate_js::{generate_hydratable_js_from_blocks, generate_js_from_blocks};
+use generate_ssr::{generate_hydratable_ssr_from_blocks, generate_ssr_from_blocks};
+use lunas_parser::{DetailedBlock, Diagnostic};
+pub use source_map::SourceMap;
 use utils::rand_id::RAND_ID_GENERATOR;
 extern crate lazy_static;
 
 pub fn lunas_compile_from_block(
     b: &DetailedBlock,
     engine_path: Option<String>,
-) -> Result<(String, Option<String>), String> {
+) -> Result<CompiledComponent, Vec<Diagnostic>> {
     let compiled_code = generate_js_from_blocks(b, engine_path);
     RAND_ID_GENERATOR.lock().unwrap().reset();
-    compiled_code
+    // Errors raised while walking the template are reported at the html block
+    // unless they already know a more precise location.
+    compiled_code.map_err(|e| vec![e.or_range(b.detailed_language_blocks.html_range)])
+}
+
+/// Compiles a component into a client module that can also adopt the markup rendered by its
+/// server-side rendering module, instead of building it again.
+pub fn lunas_compile_hydratable_from_block(
+    b: &DetailedBlock,
+    engine_path: Option<String>,
+) -> Result<CompiledComponent, Vec<Diagnostic>> {
+    let compiled_code = generate_hydratable_js_from_blocks(b, engine_path);
+    RAND_ID_GENERATOR.lock().unwrap().reset();
+    compiled_code.map_err(|e| vec![e.or_range(b.detailed_language_blocks.html_range)])
+}
+
+/// Compiles a component into a module for server-side rendering, whose default export renders
+/// the markup of the component for the props it is given.
+pub fn lunas_compile_ssr_from_block(
+    b: &DetailedBlock,
+    runtime_path: Option<String>,
+) -> Result<CompiledComponent, Vec<Diagnostic>> {
+    let compiled_code = generate_ssr_from_blocks(b, runtime_path);
+    RAND_ID_GENERATOR.lock().unwrap().reset();
+    compiled_code.map_err(|e| vec![e.or_range(b.detailed_language_blocks.html_range)])
+}
+
+/// Compiles a component into a module for server-side rendering whose markup can be adopted by
+/// the client module of [`lunas_compile_hydratable_from_block`].
+pub fn lunas_compile_hydratable_ssr_from_block(
+    b: &DetailedBlock,
+    runtime_path: Option<String>,
+) -> Result<CompiledComponent, Vec<Diagnostic>> {
+    let compiled_code = generate_hydratable_ssr_from_blocks(b, runtime_path);
+    RAND_ID_GENERATOR.lock().unwrap().reset();
+    compiled_code.map_err(|e| vec![e.or_range(b.detailed_language_blocks.html_range)])
 }
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use lunas_html_parser::Attributes;
 
-use lunas_parser::ParsedFor;
+use lunas_parser::{ParsedFor, SourceRange};
 
 #[derive(Debug, Clone)]
 pub struct HtmlManipulator {
@@ -35,6 +35,8 @@ pub struct RemoveChildForIfStatement {
 pub struct RemoveChildForRepeatStatement {
     pub child_uuid: String,
     pub for_info: ParsedFor,
+    pub key: Option<String>,
+    pub patch_items: bool,
     pub block_id: String,
     pub ctx_under_for: Vec<String>,
     pub ctx_over_for: Vec<String>,
@@ -44,7 +46,7 @@ pub struct RemoveChildForRepeatStatement {
 #[derive(Debug, Clone)]
 pub struct RemoveChildForCustomComponent {
     pub component_name: String,
-    pub attributes: HashMap<String, Option<String>>,
+    pub attributes: Attributes,
     pub child_uuid: String,
     pub ctx: Vec<String>,
     pub elm_loc: Vec<usize>,
@@ -56,6 +58,7 @@ pub struct SetIdToParentForChildReactiveText {
     pub depenent_vars: Vec<String>,
     pub ctx: Vec<String>,
     pub elm_loc: Vec<usize>,
+    pub range: SourceRange,
 }
 
 #[derive(Debug, Clone)]
@@ -65,4 +68,5 @@ pub struct RemoveChildTextNode {
     pub elm_loc: Vec<usize>,
     pub child_uuid: String,
     pub content: String,
+    pub range: SourceRange,
 }
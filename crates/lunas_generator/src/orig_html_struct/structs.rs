@@ -1,17 +1,24 @@
-use lunas_html_parser::{Dom as RawDom, Element as RawElm, Node as RawNode};
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use lunas_html_parser::{
+    AttributeSpan, Attributes, Dom as RawDom, Element as RawElm, Markup, MarkupElement, MarkupNode,
+    Namespace, Node as RawNode, ParentNode, SerializeOptions, SourceSpan, TreeNode,
+};
+use lunas_parser::SourceRange;
 
+use crate::transformers::html_utils::is_component_tag;
 use crate::utils::rand_id::RAND_ID_GENERATOR;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Node {
     pub uuid: String,
     pub content: NodeContent,
+    /// Position of the node in the `.lun` file.
+    pub source_span: SourceSpan,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NodeContent {
-    Element(Element),
+    Element(Box<Element>),
     TextNode(String),
     Comment(String),
 }
@@ -19,8 +26,11 @@ pub enum NodeContent {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Element {
     pub tag_name: String,
-    pub attributes: HashMap<String, Option<String>>,
+    pub attributes: Attributes,
     pub children: Vec<Node>,
+    pub source_span: SourceSpan,
+    pub attribute_spans: IndexMap<String, AttributeSpan>,
+    pub namespace: Namespace,
 }
 
 impl Element {
@@ -29,6 +39,37 @@ impl Element {
             attributes: raw_elm.attributes.clone(),
             children: node_vec,
             tag_name: raw_elm.name,
+            source_span: raw_elm.source_span,
+            attribute_spans: raw_elm.attribute_spans,
+            namespace: raw_elm.namespace,
+        }
+    }
+
+    /// Range of the element in the `.lun` file.
+    pub fn range(&self) -> SourceRange {
+        SourceRange::from(&self.source_span)
+    }
+
+    /// Range of an attribute in the `.lun` file, from its key to the end of its value.
+    pub fn attribute_range(&self, key: &str) -> SourceRange {
+        match self.attribute_spans.get(key) {
+            Some(span) => SourceRange {
+                start: SourceRange::from(&span.key).start,
+                end: SourceRange::from(span.value.as_ref().unwrap_or(&span.key)).end,
+            },
+            None => self.range(),
+        }
+    }
+
+    /// Range of the first of `keys` the element was written with, e.g. the `:if`, `:elseif`
+    /// or `:else` of a conditional element.
+    pub fn directive_range(&self, keys: &[&str]) -> SourceRange {
+        match keys
+            .iter()
+            .find(|key| self.attribute_spans.contains_key(**key))
+        {
+            Some(key) => self.attribute_range(key),
+            None => self.range(),
         }
     }
 
@@ -51,7 +92,7 @@ impl Element {
             match &cur_child.content {
                 NodeContent::Element(elm) => {
                     if !elm.attributes.contains_key("$$$conditional$$$")
-                        && !custom_component_names.contains(&elm.tag_name)
+                        && !is_component_tag(custom_component_names, &elm.tag_name)
                     {
                         break (cur as u64 - idx as u64, Some(cur as u64));
                     }
@@ -65,9 +106,9 @@ impl Element {
         (elm_node, idx as u64, distance, idx_of_ref)
     }
 
-    pub fn attributes_without_meta(&self) -> HashMap<String, Option<String>> {
+    pub fn attributes_without_meta(&self) -> Attributes {
         let mut attributes = self.attributes.clone();
-        attributes.remove("$$$conditional$$$");
+        attributes.shift_remove("$$$conditional$$$");
         attributes
     }
 
@@ -102,17 +143,19 @@ impl Element {
 }
 
 impl Node {
-    fn new_comment(comment: &String) -> Node {
+    fn new_comment(comment: &String, source_span: &SourceSpan) -> Node {
         Node {
             uuid: RAND_ID_GENERATOR.lock().unwrap().gen(),
             content: NodeContent::Comment(comment.clone()),
+            source_span: source_span.clone(),
         }
     }
 
-    fn new_text(text: &String) -> Node {
+    pub(crate) fn new_text(text: &String, source_span: &SourceSpan) -> Node {
         Node {
             uuid: RAND_ID_GENERATOR.lock().unwrap().gen(),
             content: NodeContent::TextNode(text.clone()),
+            source_span: source_span.clone(),
         }
     }
 
@@ -123,7 +166,8 @@ impl Node {
         }
         Node {
             uuid: RAND_ID_GENERATOR.lock().unwrap().gen(),
-            content: NodeContent::Element(Element::new_from_raw(elm.clone(), children)),
+            content: NodeContent::Element(Box::new(Element::new_from_raw(elm.clone(), children))),
+            source_span: elm.source_span.clone(),
         }
     }
 
@@ -137,62 +181,84 @@ impl Node {
 
     pub fn new_from_node(raw_node: &RawNode) -> Node {
         match raw_node {
-            RawNode::Text(text) => Node::new_text(text),
+            RawNode::Text(text) => Node::new_text(&text.text, &text.source_span),
             RawNode::Element(elm) => Node::new_from_raw(elm),
-            RawNode::Comment(comment) => Node::new_comment(comment),
+            RawNode::Comment(comment) => Node::new_comment(&comment.comment, &comment.source_span),
         }
     }
 }
 
-impl ToString for Node {
-    fn to_string(&self) -> String {
+impl TreeNode for Node {
+    type Element = Element;
+
+    fn as_element(&self) -> Option<&Element> {
         match &self.content {
-            NodeContent::Element(elm) => elm.to_string(),
-            NodeContent::TextNode(text) => text.clone(),
-            NodeContent::Comment(_) => "".to_string(),
+            NodeContent::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match &mut self.content {
+            NodeContent::Element(element) => Some(element),
+            _ => None,
         }
     }
 }
 
-impl ToString for Element {
-    fn to_string(&self) -> String {
-        let mut attribute_str = String::new();
+impl ParentNode for Element {
+    type Node = Node;
 
-        let mut attributes: Vec<_> = self.attributes.iter().collect();
-        attributes.sort_by(|a, b| a.0.cmp(b.0));
+    fn child_nodes(&self) -> &[Node] {
+        &self.children
+    }
 
-        for (key, value) in attributes {
-            if let Some(value) = value {
-                attribute_str.push_str(&format!(" {}=\"{}\"", key, value));
-            } else {
-                attribute_str.push_str(&format!(" {}", key));
-            }
+    fn child_nodes_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.children
+    }
+
+    fn into_node(self) -> Node {
+        Node {
+            uuid: RAND_ID_GENERATOR.lock().unwrap().gen(),
+            source_span: self.source_span.clone(),
+            content: NodeContent::Element(Box::new(self)),
         }
+    }
+}
 
-        let mut children = String::new();
-        for child in &self.children {
-            children.push_str(&child.to_string());
+impl MarkupNode for Node {
+    fn markup(&self) -> Markup<'_, Element> {
+        match &self.content {
+            NodeContent::Element(element) => Markup::Element(element),
+            NodeContent::TextNode(text) => Markup::Text(text),
+            NodeContent::Comment(comment) => Markup::Comment(comment),
         }
-        format!(
-            "<{}{}>{}</{}>",
-            self.tag_name, attribute_str, children, self.tag_name
-        )
-        // TODO: Make it a self-closing tag for certain tags
-        // match self.children.len() == 0 {
-        //     true => {
-        //         format!("<{}{} />", self.tag_name, attribute_str)
-        //     }
-        //     false => {
-        //         let mut children = String::new();
-        //         for child in &self.children {
-        //             children.push_str(&child.to_string());
-        //         }
-        //         format!(
-        //             "<{}{}>{}</{}>",
-        //             self.tag_name, attribute_str, children, self.tag_name
-        //         )
-        //     }
-        // }
+    }
+}
+
+impl MarkupElement for Element {
+    fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+}
+
+impl ToString for Node {
+    fn to_string(&self) -> String {
+        self.to_markup(&SerializeOptions::default())
+    }
+}
+
+impl ToString for Element {
+    fn to_string(&self) -> String {
+        self.to_markup(&SerializeOptions::default())
     }
 }
 
@@ -205,4 +271,32 @@ mod tests {
         let node = crate::orig_html_struct::structs::Node::new_from_node(&el);
         assert_eq!(node.to_string(), raw_html);
     }
+
+    #[test]
+    fn walk_generator_tree() {
+        use super::*;
+        use lunas_html_parser::{VisitControl, Visitor};
+
+        struct TagNames(Vec<String>);
+
+        impl<'a> Visitor<'a, Node> for TagNames {
+            fn enter(&mut self, node: &'a Node) -> VisitControl {
+                if let Some(element) = node.as_element() {
+                    self.0.push(element.tag_name.clone());
+                }
+                VisitControl::Continue
+            }
+        }
+
+        let dom = RawDom::parse("<div><p>a</p><ul><li>b</li></ul></div>").unwrap();
+        let mut root = Node::new_from_dom(&dom).unwrap();
+        let mut tag_names = TagNames(vec![]);
+        root.walk(&mut tag_names);
+        assert_eq!(tag_names.0, vec!["div", "p", "ul", "li"]);
+
+        let div = root.as_element_mut().unwrap();
+        let ul = div.detach_child(1).unwrap();
+        div.insert_child(0, ul);
+        assert_eq!(root.to_string(), "<div><ul><li>b</li></ul><p>a</p></div>");
+    }
 }
@@ -0,0 +1,241 @@
+//! Source Map v3 output.
+//!
+//! Code generation tags the generated code with `/*$$lunasMap:LINE:COLUMN*/` comments wherever
+//! it writes code that comes from the `.lun` file. Once the module is assembled,
+//! [`resolve_mapping_markers`] removes the comments and records their positions as mappings, so
+//! the code can keep being built by concatenating strings.
+
+use lunas_parser::{BlockLocation, SourcePosition};
+use serde_json::json;
+
+const MARKER_START: &str = "/*$$lunasMap:";
+const MARKER_END: &str = "*/";
+
+/// Returns the comment that maps the generated code following it to `position`.
+pub fn mapping_marker(position: &SourcePosition) -> String {
+    format!(
+        "{}{}:{}{}",
+        MARKER_START, position.line, position.column, MARKER_END
+    )
+}
+
+/// A position of the generated code and the position of the `.lun` file it comes from, all
+/// 0-based as in Source Map v3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mapping {
+    generated_line: usize,
+    generated_column: usize,
+    original_line: usize,
+    original_column: usize,
+}
+
+/// Mappings from generated code back to the `.lun` file it was compiled from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Returns the `.lun` position (1-based line and column) the code at a 1-based generated
+    /// line and column comes from.
+    pub fn original_position(&self, line: usize, column: usize) -> Option<(usize, usize)> {
+        self.mappings
+            .iter()
+            .rev()
+            .find(|m| m.generated_line + 1 == line && m.generated_column < column)
+            .map(|m| (m.original_line + 1, m.original_column + 1))
+    }
+
+    /// Serializes the map as Source Map v3 JSON, with `source_name` as the single source.
+    pub fn to_json(&self, source_name: &str, source_content: Option<&str>) -> String {
+        let mut map = json!({
+            "version": 3,
+            "sources": [source_name],
+            "names": [],
+            "mappings": self.encode_mappings(),
+        });
+        if let Some(content) = source_content {
+            map["sourcesContent"] = json!([content]);
+        }
+        map.to_string()
+    }
+
+    fn encode_mappings(&self) -> String {
+        let mut mappings = self.mappings.clone();
+        mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+        mappings.dedup_by_key(|m| (m.generated_line, m.generated_column));
+
+        let mut encoded = String::new();
+        let (mut line, mut column) = (0, 0);
+        let (mut original_line, mut original_column) = (0, 0);
+        let mut first_in_line = true;
+        for mapping in mappings {
+            while line < mapping.generated_line {
+                encoded.push(';');
+                line += 1;
+                column = 0;
+                first_in_line = true;
+            }
+            if !first_in_line {
+                encoded.push(',');
+            }
+            first_in_line = false;
+            encode_vlq(
+                mapping.generated_column as i64 - column as i64,
+                &mut encoded,
+            );
+            // All mappings point into the single source
+            encode_vlq(0, &mut encoded);
+            encode_vlq(
+                mapping.original_line as i64 - original_line as i64,
+                &mut encoded,
+            );
+            encode_vlq(
+                mapping.original_column as i64 - original_column as i64,
+                &mut encoded,
+            );
+            column = mapping.generated_column;
+            original_line = mapping.original_line;
+            original_column = mapping.original_column;
+        }
+        encoded
+    }
+}
+
+/// Removes the markers of [`mapping_marker`] from `code` and returns the code with the mappings
+/// they stood for.
+pub fn resolve_mapping_markers(code: &str) -> (String, SourceMap) {
+    let mut output = String::with_capacity(code.len());
+    let mut source_map = SourceMap::default();
+    for (generated_line, line) in code.split('\n').enumerate() {
+        if generated_line > 0 {
+            output.push('\n');
+        }
+        let mut rest = line;
+        let mut generated_column = 0;
+        while let Some(start) = rest.find(MARKER_START) {
+            let position = rest[start + MARKER_START.len()..]
+                .split_once(MARKER_END)
+                .and_then(|(position, after)| Some((parse_position(position)?, after)));
+            let (before, after) = match position {
+                Some(((original_line, original_column), after)) => {
+                    generated_column += rest[..start].encode_utf16().count();
+                    source_map.mappings.push(Mapping {
+                        generated_line,
+                        generated_column,
+                        original_line: original_line.saturating_sub(1),
+                        original_column: original_column.saturating_sub(1),
+                    });
+                    (&rest[..start], after)
+                }
+                // Not a marker, e.g. the same text in a string literal
+                None => {
+                    let end = start + MARKER_START.len();
+                    generated_column += rest[..end].encode_utf16().count();
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            output.push_str(before);
+            rest = after;
+        }
+        output.push_str(rest);
+    }
+    (output, source_map)
+}
+
+fn parse_position(position: &str) -> Option<(usize, usize)> {
+    let (line, column) = position.split_once(':')?;
+    Some((line.parse().ok()?, column.parse().ok()?))
+}
+
+/// Maps each line of a style block, passed through as is, to its line in the `.lun` file.
+pub fn css_source_map(css: &str, location: &BlockLocation) -> SourceMap {
+    let mappings = css
+        .split('\n')
+        .zip(&location.line_starts)
+        .enumerate()
+        .filter(|(_, (line, _))| !line.trim().is_empty())
+        .map(|(generated_line, (_, start))| Mapping {
+            generated_line,
+            generated_column: 0,
+            original_line: start.line - 1,
+            original_column: start.column - 1,
+        })
+        .collect();
+    SourceMap { mappings }
+}
+
+const BASE64_DIGITS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Appends `value` as a Base64 VLQ, the number encoding of Source Map v3 mappings.
+fn encode_vlq(value: i64, output: &mut String) {
+    let mut vlq = match value < 0 {
+        true => ((-value) << 1) | 1,
+        false => value << 1,
+    };
+    loop {
+        let mut digit = (vlq & 0b11111) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        output.push(BASE64_DIGITS[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_vlq_values() {
+        let encode = |value| {
+            let mut output = String::new();
+            encode_vlq(value, &mut output);
+            output
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-1000), "x+B");
+    }
+
+    #[test]
+    fn resolve_markers_into_mappings() {
+        let marker = |line, column| {
+            mapping_marker(&SourcePosition {
+                offset: 0,
+                line,
+                column,
+            })
+        };
+        let code = format!(
+            "const a = 1;\n    {}let b = 2;\n{}f(); {}g(\"/*$$lunasMap:x*/\");",
+            marker(3, 3),
+            marker(5, 1),
+            marker(6, 9)
+        );
+        let (code, map) = resolve_mapping_markers(&code);
+        assert_eq!(
+            code,
+            "const a = 1;\n    let b = 2;\nf(); g(\"/*$$lunasMap:x*/\");"
+        );
+        assert_eq!(map.original_position(2, 5), Some((3, 3)));
+        assert_eq!(map.original_position(3, 6), Some((6, 9)));
+        assert_eq!(map.original_position(1, 1), None);
+        let json: serde_json::Value =
+            serde_json::from_str(&map.to_json("app.lun", Some("html:"))).unwrap();
+        assert_eq!(json["version"], 3);
+        assert_eq!(json["sources"][0], "app.lun");
+        assert_eq!(json["mappings"], ";IAEE;AAEF,KACQ");
+    }
+}
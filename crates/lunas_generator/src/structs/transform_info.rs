@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use lunas_html_parser::Attributes;
 
-use lunas_parser::ParsedFor;
+use lunas_parser::{ParsedFor, SourceRange};
 use num_bigint::BigUint;
 
 use crate::{
@@ -57,6 +57,8 @@ pub struct ActionAndTarget {
     pub action: EventTarget,
     pub target: String,
     pub ctx: Vec<String>,
+    /// Range of the attribute that declared the listener in the `.lun` file.
+    pub range: SourceRange,
 }
 
 #[derive(Debug)]
@@ -79,6 +81,9 @@ pub struct IdBasedElementAccess {
     pub node_id: String,
     pub ctx: Vec<String>,
     pub elm_loc: Vec<usize>,
+    /// Marker of the server-rendered element to adopt, for hydratable output. The component
+    /// element itself has none.
+    pub hydration_marker: Option<String>,
 }
 
 impl RefMap {
@@ -166,6 +171,8 @@ pub struct IfBlockInfo {
     pub ctx_over_if: Vec<String>,
     pub if_blk_id: String,
     pub element_location: Vec<usize>,
+    /// Marker of the block, for hydratable output
+    pub hydration_marker: Option<String>,
 }
 
 impl IfBlockInfo {
@@ -207,11 +214,18 @@ pub struct ForBlockInfo {
     pub node: Node,
     pub ref_text_node_id: Option<String>,
     pub for_info: ParsedFor,
+    /// Expression of the `:key` directive, which identifies an item among the rendered ones
+    pub key: Option<String>,
+    /// Whether a changed item with the same key is patched in place instead of being rendered
+    /// again
+    pub patch_items: bool,
     pub dep_vars: Vec<String>,
     pub ctx_under_for: Vec<String>,
     pub ctx_over_for: Vec<String>,
     pub for_blk_id: String,
     pub element_location: Vec<usize>,
+    /// Marker of the block, for hydratable output
+    pub hydration_marker: Option<String>,
 }
 
 impl ForBlockInfo {
@@ -272,6 +286,10 @@ pub struct CustomComponentBlockInfo {
     pub element_location: Vec<usize>,
     pub is_routing_component: bool,
     pub args: ComponentArgs,
+    /// Uuid of the component tag in the template
+    pub node_id: String,
+    /// Marker of the server-rendered component to adopt, for hydratable output
+    pub hydration_marker: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -290,9 +308,9 @@ impl ComponentArg {
             Ok(format!("\"{}\": {}", self.name, value_converted_to_obj))
         } else {
             Ok(format!(
-                "\"{}\": $$lunasCreateNonReactive(\"{}\")",
+                "\"{}\": $$lunasCreateNonReactive({})",
                 self.name,
-                self.value.clone().unwrap()
+                serde_json::Value::from(self.value.clone().unwrap())
             ))
         }
     }
@@ -304,8 +322,7 @@ pub struct ComponentArgs {
 }
 
 impl ComponentArgs {
-    /* pub attributes: HashMap<String, Option<String>>, */
-    pub fn new(attr: &HashMap<String, Option<String>>) -> Self {
+    pub fn new(attr: &Attributes) -> Self {
         let mut args: Vec<ComponentArg> = vec![];
         for (key, value) in attr {
             let bind = key.starts_with(":");
@@ -342,6 +359,8 @@ pub struct ManualRendererForTextNode {
     pub ctx: Vec<String>,
     pub element_location: Vec<usize>,
     pub target_anchor_id: Option<String>,
+    /// Marker of the server-rendered text node to adopt, for hydratable output
+    pub hydration_marker: Option<String>,
 }
 
 pub enum TextNodeRenderer {
@@ -361,6 +380,17 @@ impl TextNodeRenderer {
         }
     }
 
+    pub fn hydration_marker(&self) -> Option<&String> {
+        match self {
+            TextNodeRenderer::ManualRenderer(renderer) => renderer.hydration_marker.as_ref(),
+            TextNodeRenderer::IfBlockRenderer(renderer) => renderer.hydration_marker.as_ref(),
+            TextNodeRenderer::ForBlockRenderer(renderer) => renderer.hydration_marker.as_ref(),
+            TextNodeRenderer::CustomComponentRenderer(renderer) => {
+                renderer.hydration_marker.as_ref()
+            }
+        }
+    }
+
     // DO NOT USE THIS METHOD FOR MANUAL RENDERER
     pub fn get_empty_text_node_info(&self) -> (u64, Vec<String>, Option<String>, String, String) {
         match self {
@@ -1,3 +1,5 @@
+use lunas_parser::SourceRange;
+
 // TODO: リネームする
 // TODO: 2つの共通のフィールドを持つ構造体を作る
 #[derive(Debug)]
@@ -14,6 +16,8 @@ pub struct ElmAndVariableContentRelation {
     pub content_of_element: String,
     pub ctx: Vec<String>,
     pub elm_loc: Vec<usize>,
+    /// Range of the text in the `.lun` file.
+    pub range: SourceRange,
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +27,8 @@ pub struct TextAndVariableContentRelation {
     pub content_of_element: String,
     pub ctx: Vec<String>,
     pub elm_loc: Vec<usize>,
+    /// Range of the text in the `.lun` file.
+    pub range: SourceRange,
 }
 
 #[derive(Debug, Clone)]
@@ -63,4 +69,6 @@ pub struct ReactiveAttr {
     pub content_of_attr: String,
     pub variable_names: Vec<String>,
     pub default_value: Option<String>,
+    /// Range of the attribute in the `.lun` file.
+    pub range: SourceRange,
 }
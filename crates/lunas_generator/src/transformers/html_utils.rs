@@ -1,4 +1,10 @@
-use lunas_parser::ParsedFor;
+use lunas_html_parser::{
+    escape_template_literal, find_interpolation_end, MarkupElement, Namespace, SerializeOptions,
+};
+use lunas_parser::{
+    diagnostics::{codes, Diagnostic},
+    ParsedFor, SourceRange,
+};
 
 use crate::{
     consts::ROUTER_COMPONENTS,
@@ -28,6 +34,25 @@ use crate::{
 
 use super::utils::append_v_to_vars_in_html;
 
+fn template_error(message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(codes::INVALID_TEMPLATE, message)
+}
+
+pub(crate) fn template_error_at(range: SourceRange, message: impl Into<String>) -> Diagnostic {
+    template_error(message).with_range(range)
+}
+
+/// Whether `tag_name` refers to an imported component. Entries ending with `.` come from
+/// namespace imports and match any tag under that namespace.
+pub fn is_component_tag(component_names: &[String], tag_name: &str) -> bool {
+    component_names
+        .iter()
+        .any(|name| match name.strip_suffix('.') {
+            Some(_) => tag_name.len() > name.len() && tag_name.starts_with(name.as_str()),
+            None => name == tag_name,
+        })
+}
+
 // TODO:この関数の責務が多すぎるので、可能な限り分離させる
 // TODO:dep_vars の使い方を再考する
 // TODO: 引数が大きすぎるので、共通の目的を持った引数はstructとしてグループ化する
@@ -49,17 +74,21 @@ pub fn check_html_elms(
     txt_node_renderer: &mut Vec<ManualRendererForTextNode>,
     ctx_cats: &mut ContextCategories,
     ctx: &Vec<String>,
+    // Names bound by the items of the keyed `:for` blocks the node is in
+    item_bindings: &Vec<String>,
     element_location: &Vec<usize>,
     count_of_siblings: usize,
     txt_node_to_be_deleted: bool,
-) -> Result<(), String> {
+) -> Result<(), Diagnostic> {
     let node_id = node.uuid.clone();
     match &mut node.content {
         NodeContent::Element(element) => {
             let mut ctx_array = ctx.clone();
-            if !component_names.contains(&element.tag_name) {
+            let mut item_bindings = item_bindings.clone();
+            if !is_component_tag(component_names, &element.tag_name) {
                 let x = element.clone().attributes_to_array();
                 for (key, action_value) in &x {
+                    let attribute_range = element.attribute_range(key);
                     if key == ":if" || key == ":elseif" || key == ":else" {
                         let (condition, cascade_block_id) = if key == ":if" {
                             let cascade_id = RAND_ID_GENERATOR.lock().unwrap().gen_random();
@@ -112,9 +141,9 @@ pub fn check_html_elms(
                                 .join(" && ");
 
                             if other_conditions.is_empty() {
-                                return Err(format!(
-                                    r#"No matching :if statement found for "{}""#,
-                                    key
+                                return Err(template_error_at(
+                                    attribute_range,
+                                    format!(r#"No matching :if statement found for "{}""#, key),
                                 ));
                             }
 
@@ -152,7 +181,7 @@ pub fn check_html_elms(
                                 },
                             ),
                         });
-                        element.attributes.remove(key);
+                        element.attributes.shift_remove(key);
                         element
                             .attributes
                             .insert("$$$conditional$$$".to_string(), None);
@@ -160,10 +189,19 @@ pub fn check_html_elms(
                     } else if key == ":for" {
                         let action_value = match action_value.clone() {
                             Some(val) => val,
-                            None => return Err("Missing value for :for attribute".to_string()),
+                            None => {
+                                return Err(template_error_at(
+                                    attribute_range,
+                                    "Missing value for :for attribute",
+                                ))
+                            }
                         };
-                        let for_statement =
-                            ParsedFor::parse(&action_value).map_err(|e| e.to_string())?;
+                        let for_statement = ParsedFor::parse(&action_value)
+                            .map_err(|e| template_error_at(attribute_range, e.to_string()))?;
+                        let for_key = x
+                            .iter()
+                            .find(|(key, _)| key == ":key")
+                            .and_then(|(_, value)| value.clone());
 
                         let ctx_under_for = {
                             let mut ctx = ctx_array.clone();
@@ -171,6 +209,20 @@ pub fn check_html_elms(
                             ctx
                         };
                         ctx_cats.for_ctx.push(node.uuid.clone());
+                        // Texts using the item are kept as bindings, so that an item whose key
+                        // stays the same can be patched in place
+                        let patch_items = match for_key {
+                            Some(_) => {
+                                let bindings = for_statement.bindings().map_err(|e| {
+                                    template_error_at(attribute_range, e.to_string())
+                                })?;
+                                let patch_items =
+                                    can_patch_items(element, &bindings, component_names);
+                                item_bindings.extend(bindings);
+                                patch_items
+                            }
+                            None => false,
+                        };
 
                         html_manipulators.push(HtmlManipulator {
                             target_uuid: parent_uuid.unwrap().clone(),
@@ -178,6 +230,8 @@ pub fn check_html_elms(
                                 RemoveChildForRepeatStatement {
                                     child_uuid: node.uuid.clone(),
                                     for_info: for_statement.clone(),
+                                    key: for_key,
+                                    patch_items,
                                     block_id: node_id.clone(),
                                     ctx_over_for: ctx_array.clone(),
                                     ctx_under_for,
@@ -185,11 +239,25 @@ pub fn check_html_elms(
                                 },
                             ),
                         });
-                        element.attributes.remove(key);
+                        element.attributes.shift_remove(key);
                         element
                             .attributes
                             .insert("$$$conditional$$$".to_string(), None);
                         ctx_array.push(node.uuid.clone());
+                    } else if key == ":key" {
+                        if !x.iter().any(|(key, _)| key == ":for") {
+                            return Err(template_error_at(
+                                attribute_range,
+                                ":key is only allowed on elements with :for",
+                            ));
+                        }
+                        if action_value.is_none() {
+                            return Err(template_error_at(
+                                attribute_range,
+                                "Missing value for :key attribute",
+                            ));
+                        }
+                        element.attributes.shift_remove(key);
                     } else if key.starts_with("::") {
                         let binding_attr = &key[2..];
                         set_id_for_needed_elm(
@@ -211,6 +279,7 @@ pub fn check_html_elms(
                                 }),
                                 target: node_id.clone(),
                                 ctx: ctx_array.clone(),
+                                range: attribute_range,
                             });
                             elm_and_var_relation.push(
                                 NodeAndReactiveInfo::ElmAndReactiveAttributeRelation(
@@ -221,6 +290,7 @@ pub fn check_html_elms(
                                             content_of_attr: format!("{}.v", value),
                                             variable_names: vec![value.clone()],
                                             default_value: None,
+                                            range: attribute_range,
                                         }],
                                         ctx: ctx_array.clone(),
                                         elm_loc: element_location.clone(),
@@ -228,13 +298,19 @@ pub fn check_html_elms(
                                 ),
                             );
                         }
-                        element.attributes.remove(key);
+                        element.attributes.shift_remove(key);
                     } else if key.starts_with(":") {
                         // TODO: reconsider about this constraint
                         if key == ":innerHtml" {
-                            Err(format!(":innerHtml is not supported"))?;
+                            Err(template_error_at(
+                                attribute_range,
+                                ":innerHtml is not supported",
+                            ))?;
                         } else if key == ":textContent" {
-                            Err(format!(":textContent is not supported"))?;
+                            Err(template_error_at(
+                                attribute_range,
+                                ":textContent is not supported",
+                            ))?;
                         }
                         let id: String = set_id_for_needed_elm(
                             element,
@@ -254,7 +330,7 @@ pub fn check_html_elms(
                                 None
                             } else if let Some(value) = element.attributes.get(raw_attr_name) {
                                 let val = value.clone();
-                                element.attributes.remove(raw_attr_name);
+                                element.attributes.shift_remove(raw_attr_name);
                                 val
                             } else {
                                 None
@@ -280,9 +356,11 @@ pub fn check_html_elms(
                         };
 
                         // Check if the value is null
-                        // TODO:要素のIndexを返すようにする
                         if raw_attr_value.is_none() {
-                            Err(format!("value of attribute :{} is null", raw_attr_name))?;
+                            Err(template_error_at(
+                                attribute_range,
+                                format!("value of attribute :{} is null", raw_attr_name),
+                            ))?;
                         }
 
                         let mut raw_attr_value = raw_attr_value.unwrap();
@@ -293,15 +371,17 @@ pub fn check_html_elms(
                             variable_names_to_add_value_accessor,
                             func_deps,
                             true,
-                        )?;
+                        )
+                        .map_err(|e| template_error_at(attribute_range, e))?;
 
-                        element.attributes.remove(key);
+                        element.attributes.shift_remove(key);
 
                         let reactive_attr = ReactiveAttr {
                             attribute_key: raw_attr_name.to_string(),
                             content_of_attr: raw_attr_value,
                             variable_names: used_vars,
                             default_value: raw_key_value.clone(),
+                            range: attribute_range,
                         };
 
                         reactive_attr_info.reactive_attr.push(reactive_attr);
@@ -322,12 +402,14 @@ pub fn check_html_elms(
                                     variable_names,
                                     variable_names_to_add_value_accessor,
                                     func_deps,
-                                )?,
+                                )
+                                .map_err(|e| template_error_at(attribute_range, e))?,
                                 target: node_id.clone(),
                                 ctx: ctx_array.clone(),
+                                range: attribute_range,
                             })
                         }
-                        element.attributes.remove(key);
+                        element.attributes.shift_remove(key);
                     }
                 }
 
@@ -362,7 +444,7 @@ pub fn check_html_elms(
                                 .attributes_without_meta()
                                 .iter()
                                 .any(|f| f.0.starts_with(":if"))
-                                || component_names.contains(&next_element.tag_name)
+                                || is_component_tag(component_names, &next_element.tag_name)
                         }
                         _ => true,
                     }
@@ -387,6 +469,7 @@ pub fn check_html_elms(
                     txt_node_renderer,
                     ctx_cats,
                     &ctx_array,
+                    &item_bindings,
                     &new_element_location,
                     count_of_siblings,
                     txt_node_to_be_deleted,
@@ -469,7 +552,8 @@ pub fn check_html_elms(
                                 &variable_names_to_add_value_accessor,
                                 func_deps,
                                 true,
-                            )?;
+                            )
+                            .map_err(template_error)?;
                             if_blocks_info.push(IfBlockInfo {
                                 parent_id: node_id.clone(),
                                 target_if_blk_id: remove_statement.child_uuid.clone(),
@@ -483,6 +567,7 @@ pub fn check_html_elms(
                                 ctx_over_if: remove_statement.ctx_over_if.clone(),
                                 if_blk_id: remove_statement.block_id.clone(),
                                 element_location: remove_statement.elm_loc.clone(),
+                                hydration_marker: None,
                             });
                         }
                         HtmlManipulation::RemoveChildForRepeatStatement(remove_statement) => {
@@ -540,7 +625,22 @@ pub fn check_html_elms(
                                 &variable_names_to_add_value_accessor,
                                 func_deps,
                                 true,
-                            )?;
+                            )
+                            .map_err(template_error)?;
+                            let key = match &remove_statement.key {
+                                Some(key) => Some(
+                                    append_v_to_vars_in_html(
+                                        key,
+                                        variable_names,
+                                        variable_names_to_add_value_accessor,
+                                        func_deps,
+                                        true,
+                                    )
+                                    .map_err(template_error)?
+                                    .0,
+                                ),
+                                None => None,
+                            };
                             for_blocks_info.push(ForBlockInfo {
                                 parent_id: node_id.clone(),
                                 target_for_blk_id: remove_statement.child_uuid.clone(),
@@ -551,11 +651,14 @@ pub fn check_html_elms(
                                 for_info: remove_statement
                                     .for_info
                                     .clone_with_new_iterable(item_collection.as_str()),
+                                key,
+                                patch_items: remove_statement.patch_items,
                                 ctx_over_for: remove_statement.ctx_over_for.clone(),
                                 ctx_under_for: remove_statement.ctx_under_for.clone(),
                                 for_blk_id: remove_statement.block_id.clone(),
                                 element_location: remove_statement.elm_loc.clone(),
                                 dep_vars,
+                                hydration_marker: None,
                             });
                         }
                         HtmlManipulation::RemoveChildForCustomComponent(remove_statement) => {
@@ -605,6 +708,8 @@ pub fn check_html_elms(
                                 is_routing_component: ROUTER_COMPONENTS
                                     .into_iter()
                                     .any(|x| x == remove_statement.component_name),
+                                node_id: remove_statement.child_uuid.clone(),
+                                hydration_marker: None,
                             });
                         }
                         HtmlManipulation::SetIdForReactiveContent(set_id) => {
@@ -622,6 +727,7 @@ pub fn check_html_elms(
                                     content_of_element: set_id.text.clone(),
                                     ctx: set_id.ctx.clone(),
                                     elm_loc: set_id.elm_loc.clone(),
+                                    range: set_id.range,
                                 },
                             ));
                         }
@@ -665,6 +771,7 @@ pub fn check_html_elms(
                                 ctx: remove_text_node.ctx.clone(),
                                 element_location: remove_text_node.elm_loc.clone(),
                                 target_anchor_id: target_anchor_id.clone(),
+                                hydration_marker: None,
                             });
 
                             elm_and_var_relation.push(
@@ -675,6 +782,7 @@ pub fn check_html_elms(
                                         content_of_element: remove_text_node.content.clone(),
                                         ctx: remove_text_node.ctx.clone(),
                                         elm_loc: remove_text_node.elm_loc.clone(),
+                                        range: remove_text_node.range,
                                     },
                                 ),
                             );
@@ -686,12 +794,28 @@ pub fn check_html_elms(
             Ok(())
         }
         NodeContent::TextNode(text) => {
-            let (dep_vars, _) = replace_text_with_reactive_value(
+            let text_range = SourceRange::from(&node.source_span);
+            let item_deps = match item_bindings.is_empty() {
+                true => vec![],
+                false => {
+                    replace_text_with_reactive_value(
+                        &mut text.clone(),
+                        item_bindings,
+                        &vec![],
+                        &vec![],
+                    )
+                    .map_err(template_error)?
+                    .0
+                }
+            };
+            let (mut dep_vars, _) = replace_text_with_reactive_value(
                 text,
                 variable_names,
                 variable_names_to_add_value_accessor,
                 func_deps,
-            )?;
+            )
+            .map_err(template_error)?;
+            dep_vars.extend(item_deps);
             if dep_vars.len() > 0 && count_of_siblings <= 1 {
                 html_manipulators.push(HtmlManipulator {
                     target_uuid: parent_uuid.unwrap().clone(),
@@ -701,6 +825,7 @@ pub fn check_html_elms(
                             depenent_vars: dep_vars,
                             ctx: ctx.clone(),
                             elm_loc: element_location.clone(),
+                            range: text_range,
                         },
                     ),
                 });
@@ -713,6 +838,7 @@ pub fn check_html_elms(
                         elm_loc: element_location.clone(),
                         child_uuid: node_id,
                         content: text.clone(),
+                        range: text_range,
                     }),
                 });
             }
@@ -722,6 +848,74 @@ pub fn check_html_elms(
     }
 }
 
+/// Whether a changed item of a keyed `:for` element can be patched by running the bindings of its
+/// texts and attributes again. Event handlers, conditions, loops and components keep the item
+/// they were created with, so the item is rendered again when they use what it binds.
+fn can_patch_items(
+    element: &Element,
+    bindings: &Vec<String>,
+    component_names: &Vec<String>,
+) -> bool {
+    let handlers_use_bindings = element.attributes.iter().any(|(key, value)| {
+        (key.starts_with('@') || key.starts_with("::"))
+            && value
+                .as_ref()
+                .is_some_and(|value| code_uses_bindings(value, bindings, false))
+    });
+    !handlers_use_bindings
+        && element.children.iter().all(|child| match &child.content {
+            NodeContent::Element(child_element) => {
+                let is_block = is_component_tag(component_names, &child_element.tag_name)
+                    || child_element
+                        .attributes
+                        .keys()
+                        .any(|key| matches!(key.as_str(), ":if" | ":elseif" | ":else" | ":for"));
+                match is_block {
+                    true => !node_uses_bindings(child, bindings),
+                    false => can_patch_items(child_element, bindings, component_names),
+                }
+            }
+            _ => true,
+        })
+}
+
+fn node_uses_bindings(node: &Node, bindings: &Vec<String>) -> bool {
+    match &node.content {
+        NodeContent::Element(element) => {
+            element
+                .attributes
+                .iter()
+                .any(|(key, value)| match (key.as_str(), value) {
+                    (":for", Some(value)) => ParsedFor::parse(value).map_or(true, |for_info| {
+                        code_uses_bindings(&for_info.iterable, bindings, true)
+                    }),
+                    (key, Some(value)) if key.starts_with(':') => {
+                        code_uses_bindings(value, bindings, true)
+                    }
+                    (key, Some(value)) if key.starts_with('@') => {
+                        code_uses_bindings(value, bindings, false)
+                    }
+                    _ => false,
+                })
+                || element
+                    .children
+                    .iter()
+                    .any(|child| node_uses_bindings(child, bindings))
+        }
+        NodeContent::TextNode(text) => {
+            replace_text_with_reactive_value(&mut text.clone(), bindings, &vec![], &vec![])
+                .map_or(true, |(used_bindings, _)| !used_bindings.is_empty())
+        }
+        NodeContent::Comment(_) => false,
+    }
+}
+
+// Code that cannot be parsed counts as using them, its error is reported where it is compiled
+fn code_uses_bindings(code: &str, bindings: &Vec<String>, is_expr: bool) -> bool {
+    append_v_to_vars_in_html(code, bindings, &vec![], &vec![], is_expr)
+        .map_or(true, |(_, used_bindings)| !used_bindings.is_empty())
+}
+
 fn set_id_for_needed_elm(
     element: &mut Element,
     ref_maps: &mut Vec<RefMap>,
@@ -754,6 +948,7 @@ fn set_id_for_needed_elm(
                 node_id: node_id.clone(),
                 ctx: ctx.clone(),
                 elm_loc: elm_loc.clone(),
+                hydration_marker: None,
             }));
             id.clone()
         };
@@ -769,6 +964,7 @@ fn set_id_for_needed_elm(
             node_id: node_id.clone(),
             ctx: ctx.clone(),
             elm_loc: elm_loc.clone(),
+            hydration_marker: None,
         }));
         new_id
     }
@@ -796,7 +992,7 @@ fn create_space_for_ref_map(
 // Give: <div>    ${count} </div>
 // Want: <div>    ${$$lunasEscapeHtml(count)} </div>
 // TODO: count_of_bindingsの返却をやめる
-fn replace_text_with_reactive_value(
+pub(crate) fn replace_text_with_reactive_value(
     code: &mut String,
     variables: &Vec<String>,
     variable_names_to_add_value_accessor: &Vec<String>,
@@ -813,8 +1009,8 @@ fn replace_text_with_reactive_value(
     while let Some(start) = code[last_end..].find(start_tag) {
         count_of_bindings += 1;
         let start = start + last_end;
-        if let Some(end) = code[start..].find(end_tag) {
-            let end = end + start;
+        if let Some(end) = find_interpolation_end(&code[start + 2..]) {
+            let end = end + start + 2;
             let pre_bracket = &code[last_end..start];
             let in_bracket = &code[start + 2..end];
             let _post_bracket = &code[end + 1..];
@@ -834,6 +1030,8 @@ fn replace_text_with_reactive_value(
             last_end = end + 1;
 
             depending_vars.extend(dep_vars);
+        } else {
+            break;
         }
     }
 
@@ -848,9 +1046,9 @@ pub fn create_lunas_internal_component_statement(
 ) -> String {
     let mut code = String::new();
     code.push_str(format!("{}(`", generation_func_name).as_str());
-    for child in &elm.children {
-        code.push_str(&child.to_string());
-    }
+    code.push_str(&escape_template_literal(
+        &elm.children_to_markup(&SerializeOptions::minify()),
+    ));
     code.push_str("`, \"");
     code.push_str(&elm.tag_name);
     code.push_str("\"");
@@ -859,13 +1057,20 @@ pub fn create_lunas_internal_component_statement(
         code.push_str(", {");
         for (key, value) in attrs.iter() {
             let js_value = match value {
-                Some(value) => format!("`{}`", value),
+                Some(value) => format!("`{}`", escape_template_literal(value)),
                 None => "null".to_string(),
             };
             code.push_str(&format!("\"{}\": {},", key, js_value));
         }
         code.push('}');
     }
+    // Elements outside of HTML have to be created with `createElementNS`
+    if elm.namespace != Namespace::Html {
+        if attrs.is_empty() {
+            code.push_str(", {}");
+        }
+        code.push_str(&format!(", \"{}\"", elm.namespace.uri()));
+    }
     code.push_str(")");
     code
 }
@@ -903,6 +1108,25 @@ mod tests {
         assert_eq!(code, "$$lunasEscapeHtml( count2.v + count.v )");
     }
 
+    #[test]
+    fn interpolation_with_nested_braces() {
+        let code = "${items.filter((i) => i.n < count).length} / ${ {a: `}${count}`}.a }";
+        let mut code = code.to_string();
+        let (dep_vars, count_of_bindings) = replace_text_with_reactive_value(
+            &mut code,
+            &vec!["items".to_string(), "count".to_string()],
+            &vec!["items".to_string(), "count".to_string()],
+            &vec![],
+        )
+        .unwrap();
+        assert_eq!(count_of_bindings, 2);
+        assert!(dep_vars.contains(&"items".to_string()));
+        assert_eq!(
+            code,
+            "${$$lunasEscapeHtml(items.v.filter((i)=>i.n < count.v).length)} / ${$$lunasEscapeHtml(({\n    a: `}${count.v}`\n}).a)}"
+        );
+    }
+
     #[test]
     fn exploration3() {
         let code = "${interval==null?'start':'clear'}";
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use lunas_html_parser::{MarkupNode, SerializeOptions, TreeNode, VisitControl, VisitorMut};
+use lunas_parser::Diagnostic;
+
+use crate::{
+    orig_html_struct::structs::Node,
+    structs::{
+        ctx::ContextCategories,
+        js_analyze::JsFunctionDeps,
+        transform_info::{
+            CustomComponentBlockInfo, ForBlockInfo, IfBlockInfo, ManualRendererForTextNode, RefMap,
+        },
+    },
+};
+
+use super::{html_utils::check_html_elms, utils::fnv1a};
+
+/// Replaces the uuid of every node of the template with a marker that stays the same between
+/// builds, so that the markup rendered on the server and the hydrating client agree on it.
+///
+/// Markers are the hash of the template followed by the position of the node in document
+/// order, e.g. `$1f3a9c_4`. They never contain `-`, which separates block names from the
+/// indices of the `:for` blocks they are rendered in.
+pub fn assign_hydration_markers(template: &mut Node) {
+    let hash = fnv1a(template.to_markup(&SerializeOptions::minify()).bytes());
+    template.walk_mut(&mut HydrationMarkerAssigner {
+        prefix: format!("${:06x}", hash & 0xffffff),
+        count: 0,
+    });
+}
+
+struct HydrationMarkerAssigner {
+    prefix: String,
+    count: usize,
+}
+
+impl VisitorMut<Node> for HydrationMarkerAssigner {
+    fn enter(&mut self, node: &mut Node) -> VisitControl {
+        node.uuid = format!("{}_{}", self.prefix, self.count);
+        self.count += 1;
+        VisitControl::Continue
+    }
+}
+
+/// Nodes the hydrating client looks up in the server-rendered markup, by their marker.
+#[derive(Debug, Default)]
+pub struct HydrationTargets {
+    /// Elements the client gets references to, which are rendered after a `<!--marker-->`
+    /// comment. The component element is not among them, the client is given it.
+    pub elements: HashSet<String>,
+    /// Text nodes the client inserts itself, which are rendered after a `<!--marker-->` comment
+    pub text_nodes: HashSet<String>,
+    /// Blocks and components the client adopts, which are rendered after a `<!--marker-->`
+    /// comment
+    pub blocks: HashSet<String>,
+    /// Blocks the client inserts an anchor after, which are rendered followed by a
+    /// `<!--/marker-->` comment
+    pub block_anchors: HashSet<String>,
+}
+
+/// Finds the nodes of a template with hydration markers that the hydrating client looks up, by
+/// analyzing the template as the client code generation does.
+pub fn hydration_targets(
+    template: &Node,
+    variable_names: &Vec<String>,
+    variable_names_to_add_value_accessor: &Vec<String>,
+    component_names: &Vec<String>,
+    func_deps: &Vec<JsFunctionDeps>,
+) -> Result<HydrationTargets, Diagnostic> {
+    let mut ref_map = vec![];
+    let mut if_blocks_info = vec![];
+    let mut for_blocks_info = vec![];
+    let mut custom_component_blocks_info = vec![];
+    let mut text_node_renderer = vec![];
+    check_html_elms(
+        variable_names,
+        variable_names_to_add_value_accessor,
+        component_names,
+        func_deps,
+        &mut template.clone(),
+        &mut ref_map,
+        &mut vec![],
+        &mut vec![],
+        None,
+        &mut vec![],
+        &mut if_blocks_info,
+        &mut for_blocks_info,
+        &mut custom_component_blocks_info,
+        &mut text_node_renderer,
+        &mut ContextCategories {
+            if_ctx: vec![],
+            for_ctx: vec![],
+        },
+        &vec![],
+        &vec![],
+        &vec![0],
+        1,
+        false,
+    )?;
+
+    Ok(HydrationTargets {
+        elements: ref_map
+            .iter()
+            .filter_map(|ref_map| match ref_map {
+                RefMap::IdBasedElementAccess(access) if access.node_id != template.uuid => {
+                    Some(access.node_id.clone())
+                }
+                _ => None,
+            })
+            .collect(),
+        text_nodes: text_node_renderer
+            .into_iter()
+            .map(|renderer| renderer.text_node_id)
+            .collect(),
+        blocks: if_blocks_info
+            .iter()
+            .map(|block| block.target_if_blk_id.clone())
+            .chain(
+                for_blocks_info
+                    .iter()
+                    .map(|block| block.target_for_blk_id.clone()),
+            )
+            .chain(
+                custom_component_blocks_info
+                    .iter()
+                    .map(|block| block.node_id.clone()),
+            )
+            .collect(),
+        block_anchors: if_blocks_info
+            .into_iter()
+            .filter(|block| block.distance_to_next_elm > 1)
+            .map(|block| block.target_if_blk_id)
+            .chain(
+                for_blocks_info
+                    .into_iter()
+                    .filter(|block| block.distance_to_next_elm > 1)
+                    .map(|block| block.target_for_blk_id),
+            )
+            .chain(
+                custom_component_blocks_info
+                    .into_iter()
+                    .filter(|block| block.distance_to_next_elm > 1)
+                    .map(|block| block.node_id),
+            )
+            .collect(),
+    })
+}
+
+/// Makes the analyzed template of a component hydratable, once its nodes carry hydration
+/// markers: the elements the client gets references to, other than the component element, and
+/// the text nodes, components and anchors of blocks it would create are adopted by their
+/// markers instead.
+pub fn use_hydration_markers(
+    ref_map: &mut [RefMap],
+    template: &Node,
+    text_node_renderer: &mut [ManualRendererForTextNode],
+    if_blocks_info: &mut [IfBlockInfo],
+    for_blocks_info: &mut [ForBlockInfo],
+    custom_component_blocks_info: &mut [CustomComponentBlockInfo],
+) {
+    for ref_map in ref_map.iter_mut() {
+        if let RefMap::IdBasedElementAccess(access) = ref_map {
+            if access.node_id != template.uuid {
+                access.hydration_marker = Some(access.node_id.clone());
+            }
+        }
+    }
+    for renderer in text_node_renderer.iter_mut() {
+        renderer.hydration_marker = Some(renderer.text_node_id.clone());
+    }
+    for block in if_blocks_info.iter_mut() {
+        block.hydration_marker = Some(block.target_if_blk_id.clone());
+    }
+    for block in for_blocks_info.iter_mut() {
+        block.hydration_marker = Some(block.target_for_blk_id.clone());
+    }
+    for block in custom_component_blocks_info.iter_mut() {
+        block.hydration_marker = Some(block.node_id.clone());
+    }
+}
@@ -1,3 +1,5 @@
+use lunas_parser::{ComponentImport, UseComponentStatement};
+
 pub fn generate_import_string(imports: &Vec<String>) -> String {
     match imports.len() == 0 {
         true => String::new(),
@@ -7,3 +9,49 @@ pub fn generate_import_string(imports: &Vec<String>) -> String {
             .collect::<String>(),
     }
 }
+
+/// Emits the JavaScript import for an `@use` directive.
+pub fn generate_component_import(use_component: &UseComponentStatement) -> String {
+    let mut default_import = None;
+    let mut namespace_import = None;
+    let mut named_imports = vec![];
+    for import in &use_component.imports {
+        match import {
+            ComponentImport::Default { local_name } => default_import = Some(local_name.clone()),
+            ComponentImport::Namespace { local_name } => {
+                namespace_import = Some(format!("* as {}", local_name))
+            }
+            ComponentImport::Named {
+                imported_name,
+                local_name,
+            } => named_imports.push(match imported_name == local_name {
+                true => imported_name.clone(),
+                false => format!("{} as {}", imported_name, local_name),
+            }),
+        }
+    }
+    let mut clauses = vec![];
+    clauses.extend(default_import);
+    clauses.extend(namespace_import);
+    if !named_imports.is_empty() {
+        clauses.push(format!("{{ {} }}", named_imports.join(", ")));
+    }
+    format!(
+        "import {} from \"{}\";",
+        clauses.join(", "),
+        use_component.component_path
+    )
+}
+
+/// Names that mark an element as a component in the template. Namespace imports are listed
+/// as a `Ui.` prefix, matching any `<Ui.Button>`-style tag.
+pub fn component_tag_names(use_component: &UseComponentStatement) -> Vec<String> {
+    use_component
+        .imports
+        .iter()
+        .map(|import| match import {
+            ComponentImport::Namespace { local_name } => format!("{}.", local_name),
+            _ => import.local_name().to_string(),
+        })
+        .collect()
+}
@@ -3,10 +3,12 @@ use num_bigint::BigUint;
 
 use crate::structs::transform_info::VariableNameAndAssignedNumber;
 
+use super::utils_swc::transform_ts_to_js;
+
 pub fn generate_input_variable_decl(
     inputs: &Vec<&PropsInput>,
     variables: &mut Vec<VariableNameAndAssignedNumber>,
-) -> Option<String> {
+) -> Result<Option<String>, String> {
     for (i, input) in inputs.iter().enumerate() {
         variables.push(VariableNameAndAssignedNumber {
             name: input.variable_name.clone(),
@@ -14,13 +16,103 @@ pub fn generate_input_variable_decl(
             to_add_value_accessor: true,
         });
     }
+    // Props arrive wrapped in value objects, so a declared default has to be wrapped the same
+    // way to be read through `.v` like any other prop.
     let prop_name = inputs
         .iter()
-        .map(|i| i.variable_name.clone())
-        .collect::<Vec<String>>()
+        .map(|i| match &i.initial_value {
+            Some(initial_value) => {
+                let js = transform_ts_to_js(initial_value).map_err(|e| {
+                    format!(
+                        "Invalid default value for @input {}: {}",
+                        i.variable_name, e
+                    )
+                })?;
+                Ok(format!(
+                    "{} = $$lunasCreateNonReactive({})",
+                    i.variable_name, js
+                ))
+            }
+            None => Ok(i.variable_name.clone()),
+        })
+        .collect::<Result<Vec<String>, String>>()?
         .join(", ");
-    match inputs.len() == 0 {
-        true => return None,
-        false => Some(format!("const {{ {} }} = args;", prop_name)),
+    match inputs.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(format!("const {{ {} }} = args;", prop_name))),
+    }
+}
+
+/// Names of the inputs the parent has to provide: neither optional nor defaulted.
+pub fn required_input_names(inputs: &Vec<&PropsInput>) -> Vec<String> {
+    inputs
+        .iter()
+        .filter(|i| !i.is_nullable && i.initial_value.is_none())
+        .map(|i| i.variable_name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lunas_parser::{parse_lunas_file, DetailedBlock, DetailedMetaData};
+
+    use super::*;
+
+    fn parse(source: &str) -> DetailedBlock {
+        parse_lunas_file(&format!("{}html:\n  <p></p>\n", source)).unwrap()
+    }
+
+    fn inputs(blocks: &DetailedBlock) -> Vec<&PropsInput> {
+        blocks
+            .detailed_meta_data
+            .iter()
+            .filter_map(|meta_data| match meta_data {
+                DetailedMetaData::PropsInput(input) => Some(input),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults_are_wrapped_in_value_objects() {
+        let blocks = parse("@input count: number = 5\n@input label: string = \"a\"\n");
+        let inputs = inputs(&blocks);
+        let mut variables = vec![];
+        let decl = generate_input_variable_decl(&inputs, &mut variables).unwrap();
+        assert_eq!(
+            decl.as_deref(),
+            Some(
+                "const { count = $$lunasCreateNonReactive(5), label = $$lunasCreateNonReactive(\"a\") } = args;"
+            )
+        );
+        assert_eq!(variables.len(), 2);
+        assert!(variables.iter().all(|v| v.to_add_value_accessor));
+        assert!(required_input_names(&inputs).is_empty());
+    }
+
+    #[test]
+    fn test_inputs_without_defaults_are_required() {
+        let blocks = parse("@input title: string\n@input subtitle?: string\n");
+        let inputs = inputs(&blocks);
+        let decl = generate_input_variable_decl(&inputs, &mut vec![]).unwrap();
+        assert_eq!(decl.as_deref(), Some("const { title, subtitle } = args;"));
+        assert_eq!(required_input_names(&inputs), vec!["title"]);
+    }
+
+    #[test]
+    fn test_required_and_defaulted_inputs_mixed() {
+        let blocks =
+            parse("@input title: string\n@input count: number = 3\n@input note?: string\n@input id: number\n");
+        let inputs = inputs(&blocks);
+        let decl = generate_input_variable_decl(&inputs, &mut vec![]).unwrap();
+        assert_eq!(
+            decl.as_deref(),
+            Some("const { title, count = $$lunasCreateNonReactive(3), note, id } = args;")
+        );
+        assert_eq!(required_input_names(&inputs), vec!["title", "id"]);
+        assert_eq!(
+            generate_input_variable_decl(&vec![], &mut vec![]).unwrap(),
+            None
+        );
     }
 }
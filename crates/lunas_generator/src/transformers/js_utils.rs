@@ -1,11 +1,12 @@
 use std::vec;
 
-use lunas_parser::DetailedBlock;
+use lunas_parser::{DetailedBlock, SourcePosition};
 use num_bigint::BigUint;
 use serde_json::Value;
 
 use crate::{
     ast_analyzer::function_analyzer::analyze_ast,
+    source_map::mapping_marker,
     structs::{
         js_analyze::{JsFunctionDeps, Tidy},
         transform_info::{
@@ -75,7 +76,11 @@ pub fn analyze_js(
         let mut functions_and_deps = analyze_ast(&js_block.ast, &variable_names);
         functions_and_deps.tidy();
 
-        // 7) Apply transformations to the script
+        // 7) Mark where the script lines come from, for the source map
+        let markers = line_origin_markers(&js_block.raw, &js_block.line_origins, &positions);
+        positions.extend(markers);
+
+        // 8) Apply transformations to the script
         let output_with_tail = add_or_remove_strings_to_script(positions.clone(), &js_block.raw);
 
         (imports, output_with_tail, functions_and_deps, lun_imports)
@@ -85,6 +90,40 @@ pub fn analyze_js(
     }
 }
 
+/// Source map markers for the first token of each script line. Lines starting inside code that
+/// is replaced or removed are left unmapped.
+fn line_origin_markers(
+    script: &str,
+    line_origins: &[Option<SourcePosition>],
+    transforms: &[TransformInfo],
+) -> Vec<TransformInfo> {
+    let rewritten_ranges = transforms
+        .iter()
+        .filter_map(|transform| match transform {
+            TransformInfo::ReplaceText(rt) => Some(rt.start_position..rt.end_position),
+            TransformInfo::RemoveStatement(rs) => Some(rs.start_position..rs.end_position),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut markers = vec![];
+    let mut line_start = 0;
+    for (line, origin) in script.split('\n').zip(line_origins) {
+        let position = (line_start + line.len() - line.trim_start().len()) as u32;
+        line_start += line.len() + 1;
+        let rewritten = rewritten_ranges
+            .iter()
+            .any(|range| range.start < position && position < range.end);
+        if let (Some(origin), false) = (origin, rewritten) {
+            markers.push(TransformInfo::AddStringToPosition(AddStringToPosition {
+                position,
+                string: mapping_marker(origin),
+                sort_order: 0,
+            }));
+        }
+    }
+    markers
+}
+
 pub fn load_lunas_script_variables(variables: &Vec<String>) -> String {
     format!("$$lunasSetImportVars([{}])", variables.join(", "))
 }
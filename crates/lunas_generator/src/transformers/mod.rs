@@ -1,7 +1,10 @@
 pub mod html_utils;
+pub mod hydration;
 pub mod imports;
 pub mod inputs;
 pub mod js_utils;
 pub mod router;
+pub mod scoped_css;
 pub mod utils;
 pub mod utils_swc;
+pub mod whitespace;
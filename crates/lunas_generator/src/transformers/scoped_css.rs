@@ -0,0 +1,551 @@
+use lunas_html_parser::{TreeNode, VisitControl, VisitorMut};
+
+use crate::orig_html_struct::structs::{Node, NodeContent};
+
+use super::{html_utils::is_component_tag, utils::fnv1a};
+
+/// At-rules whose block holds style rules, which are scoped like top-level rules.
+/// The blocks of other at-rules, such as `@keyframes` or `@font-face`, are kept as written.
+const GROUPING_AT_RULES: [&str; 5] = ["media", "supports", "container", "layer", "document"];
+
+/// Pseudo-elements that can be written with a single colon.
+const LEGACY_PSEUDO_ELEMENTS: [&str; 4] = [":before", ":after", ":first-line", ":first-letter"];
+
+/// Name of the attribute that marks the elements of a component with scoped styles, derived
+/// from the style and markup of the component so that it stays the same between builds.
+pub fn scope_attribute_name(css: &str, markup: &str) -> String {
+    format!(
+        "data-lunas-{:08x}",
+        fnv1a(css.bytes().chain(markup.bytes()))
+    )
+}
+
+/// Adds the scope attribute to every element of the template. Component tags are left out, as
+/// their attributes are passed to the component.
+pub fn add_scope_attribute(node: &mut Node, attribute: &str, component_names: &[String]) {
+    node.walk_mut(&mut ScopeAttributeAdder {
+        attribute,
+        component_names,
+    });
+}
+
+struct ScopeAttributeAdder<'a> {
+    attribute: &'a str,
+    component_names: &'a [String],
+}
+
+impl VisitorMut<Node> for ScopeAttributeAdder<'_> {
+    fn enter(&mut self, node: &mut Node) -> VisitControl {
+        if let NodeContent::Element(element) = &mut node.content {
+            if !is_component_tag(self.component_names, &element.tag_name) {
+                element
+                    .attributes
+                    .insert(self.attribute.to_string(), Some(String::new()));
+            }
+        }
+        VisitControl::Continue
+    }
+}
+
+/// Rewrites the selectors of a style sheet so that they only match elements carrying
+/// `attribute`, for `style(scoped: true):`.
+///
+/// The attribute is added to the last compound selector, e.g. `.list li:hover` becomes
+/// `.list li:hover[attribute]`. `:global(...)` keeps a part of a selector unscoped and
+/// `:deep(...)` reaches into child components: `.list :deep(.item)` becomes
+/// `.list[attribute] .item`.
+///
+/// `lang` is the language of the style block: `scss` and `less` also have `//` comments,
+/// `#{...}` and `@{...}` interpolation and rules nested inside other rules, which are scoped
+/// as well. A nested selector can't add a suffix to its parent, as in `&-title`, since the
+/// parent then ends with the attribute.
+pub fn scope_css(css: &str, attribute: &str, lang: &str) -> Result<String, String> {
+    let preprocessor = matches!(lang, "scss" | "less");
+    scope_rules(css, attribute, preprocessor, false)
+}
+
+fn scope_rules(
+    css: &str,
+    attribute: &str,
+    preprocessor: bool,
+    nested: bool,
+) -> Result<String, String> {
+    let mut scoped = String::with_capacity(css.len());
+    let mut position = 0;
+    while position < css.len() {
+        let rest = &css[position..];
+        // Whitespace and comments between rules are copied as they are
+        let skipped = skip_whitespace_and_comments(rest, preprocessor)?;
+        scoped.push_str(&rest[..skipped]);
+        position += skipped;
+        let rest = &css[position..];
+        if rest.is_empty() {
+            break;
+        }
+        let end = find_outside_of_strings(rest, &['{', ';', '}'], preprocessor)?;
+        match end.map(|end| (end, rest.as_bytes()[end])) {
+            Some((end, b'{')) => {
+                let prelude = &rest[..end];
+                let block_end = find_block_end(rest, end, preprocessor)?;
+                let block = &rest[end + 1..block_end];
+                match prelude.strip_prefix('@') {
+                    Some(at_rule) => {
+                        let name = at_rule
+                            .split(|c: char| c.is_whitespace() || c == '(')
+                            .next()
+                            .unwrap_or_default();
+                        scoped.push_str(prelude);
+                        scoped.push('{');
+                        match GROUPING_AT_RULES.contains(&name.to_ascii_lowercase().as_str()) {
+                            true => scoped.push_str(&scope_rules(
+                                block,
+                                attribute,
+                                preprocessor,
+                                nested,
+                            )?),
+                            false => scoped.push_str(block),
+                        }
+                        scoped.push('}');
+                    }
+                    // A nested property of scss, such as `font: { family: serif; }`
+                    None if preprocessor && prelude.trim_end().ends_with(':') => {
+                        scoped.push_str(&rest[..=block_end]);
+                    }
+                    None => {
+                        scoped.push_str(&scope_selector_list(prelude, attribute, nested)?);
+                        scoped.push('{');
+                        match preprocessor {
+                            true => {
+                                scoped.push_str(&scope_rules(block, attribute, preprocessor, true)?)
+                            }
+                            false => scoped.push_str(block),
+                        }
+                        scoped.push('}');
+                    }
+                }
+                position += block_end + 1;
+            }
+            // A statement at-rule such as `@import`
+            Some((end, b';')) => {
+                scoped.push_str(&rest[..=end]);
+                position += end + 1;
+            }
+            Some(_) => return Err("Unexpected `}` in style block".to_string()),
+            None => {
+                scoped.push_str(rest);
+                break;
+            }
+        }
+    }
+    Ok(scoped)
+}
+
+/// Scopes each selector of a comma separated list, keeping the whitespace around them.
+fn scope_selector_list(prelude: &str, attribute: &str, nested: bool) -> Result<String, String> {
+    let selectors = split_outside_of_parentheses(prelude, |c| c == ',')
+        .into_iter()
+        .map(|selector| {
+            let trimmed = selector.trim();
+            let leading = &selector[..selector.len() - selector.trim_start().len()];
+            let trailing = &selector[leading.len() + trimmed.len()..];
+            Ok(format!(
+                "{}{}{}",
+                leading,
+                scope_selector(trimmed, attribute, nested)?,
+                trailing
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(selectors.join(","))
+}
+
+/// A compound selector of a complex selector, with the combinator written before it.
+struct Compound {
+    combinator: String,
+    selector: String,
+    global: bool,
+    deep: bool,
+}
+
+fn scope_selector(selector: &str, attribute: &str, nested: bool) -> Result<String, String> {
+    let adds_suffix = nested
+        && selector.match_indices('&').any(|(index, _)| {
+            selector[index + 1..].starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+        });
+    if adds_suffix {
+        return Err(format!(
+            "`{}` can't be scoped, as its parent selector ends with the scope attribute",
+            selector
+        ));
+    }
+    let mut compounds = split_compounds(selector)
+        .into_iter()
+        .map(|(combinator, selector)| {
+            let (selector, global) = unwrap_pseudo_class(&selector, ":global(")?;
+            let (selector, deep) = unwrap_pseudo_class(&selector, ":deep(")?;
+            Ok(Compound {
+                combinator,
+                selector,
+                global,
+                deep,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // The attribute goes on the last scoped compound before the first `:deep()`
+    let first_deep = compounds.iter().position(|compound| compound.deep);
+    let scoped_end = first_deep.unwrap_or(compounds.len());
+    let attribute_selector = format!("[{}]", attribute);
+    match compounds[..scoped_end]
+        .iter_mut()
+        .rev()
+        .find(|compound| !compound.global)
+    {
+        Some(compound) => {
+            let at = pseudo_element_start(&compound.selector);
+            compound.selector.insert_str(at, &attribute_selector);
+        }
+        // `:deep()` at the start of a selector applies below any element of the component, a
+        // nested selector is already below its scoped parent
+        None if first_deep == Some(0) && !nested => {
+            compounds[0].combinator = " ".to_string();
+            compounds.insert(
+                0,
+                Compound {
+                    combinator: String::new(),
+                    selector: attribute_selector,
+                    global: false,
+                    deep: false,
+                },
+            );
+        }
+        None => (),
+    }
+    Ok(compounds
+        .into_iter()
+        .map(|compound| format!("{}{}", compound.combinator, compound.selector))
+        .collect())
+}
+
+/// Replaces a `:global(...)` or `:deep(...)` in a compound selector with its argument, and tells
+/// whether there was one.
+fn unwrap_pseudo_class(selector: &str, pseudo_class: &str) -> Result<(String, bool), String> {
+    let start = match selector.find(pseudo_class) {
+        Some(start) => start,
+        None => return Ok((selector.to_string(), false)),
+    };
+    let argument_start = start + pseudo_class.len();
+    let argument_end = find_closing_parenthesis(&selector[argument_start..])
+        .map(|end| argument_start + end)
+        .ok_or_else(|| format!("Unclosed `{}` in `{}`", pseudo_class, selector))?;
+    let unwrapped = format!(
+        "{}{}{}",
+        &selector[..start],
+        selector[argument_start..argument_end].trim(),
+        &selector[argument_end + 1..]
+    );
+    Ok((unwrapped, true))
+}
+
+/// Where the pseudo-element of a compound selector starts, or its end when it has none.
+fn pseudo_element_start(selector: &str) -> usize {
+    let double_colon = selector.find("::");
+    let legacy = LEGACY_PSEUDO_ELEMENTS
+        .iter()
+        .filter_map(|pseudo_element| selector.to_ascii_lowercase().find(pseudo_element))
+        .filter(|&start| !selector[..start].ends_with(':'))
+        .min();
+    double_colon
+        .into_iter()
+        .chain(legacy)
+        .min()
+        .unwrap_or(selector.len())
+}
+
+/// Splits a complex selector into its compound selectors, each with the combinator before it.
+fn split_compounds(selector: &str) -> Vec<(String, String)> {
+    let mut compounds: Vec<(String, String)> = vec![];
+    let mut combinator = String::new();
+    let mut compound = String::new();
+    let mut depth = 0;
+    let mut in_brackets = false;
+    for c in selector.chars() {
+        let is_combinator =
+            depth == 0 && !in_brackets && (c.is_whitespace() || "> + ~".contains(c));
+        if is_combinator {
+            if !compound.is_empty() {
+                compounds.push((
+                    std::mem::take(&mut combinator),
+                    std::mem::take(&mut compound),
+                ));
+            }
+            if !c.is_whitespace() {
+                combinator = format!(" {} ", c);
+            } else if combinator.is_empty() {
+                combinator = " ".to_string();
+            }
+            continue;
+        }
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            _ => (),
+        }
+        compound.push(c);
+    }
+    if !compound.is_empty() {
+        compounds.push((combinator, compound));
+    }
+    if let Some(first) = compounds.first_mut() {
+        first.0 = first.0.trim().to_string();
+        if !first.0.is_empty() {
+            first.0.push(' ');
+        }
+    }
+    compounds
+}
+
+fn split_outside_of_parentheses(text: &str, is_separator: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if depth == 0 && is_separator(c) => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn find_closing_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Length of the whitespace and comments at the start of `text`.
+fn skip_whitespace_and_comments(text: &str, preprocessor: bool) -> Result<usize, String> {
+    let mut position = 0;
+    loop {
+        let rest = &text[position..];
+        let trimmed = rest.trim_start();
+        position += rest.len() - trimmed.len();
+        if let Some(comment) = trimmed.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => position += end + 4,
+                None => return Err("Unclosed comment in style block".to_string()),
+            }
+        } else if preprocessor && trimmed.starts_with("//") {
+            position += line_comment_length(trimmed);
+        } else {
+            return Ok(position);
+        }
+    }
+}
+
+/// Length of the `//` comment at the start of `text`, up to the end of its line.
+fn line_comment_length(text: &str) -> usize {
+    text.find('\n').unwrap_or(text.len())
+}
+
+/// Index of the first of `targets` in `text` that is not inside a string or a comment.
+fn find_outside_of_strings(
+    text: &str,
+    targets: &[char],
+    preprocessor: bool,
+) -> Result<Option<usize>, String> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let mut escaped = false;
+                let closed = chars.by_ref().any(|(_, next)| {
+                    let closes = next == c && !escaped;
+                    escaped = next == '\\' && !escaped;
+                    closes
+                });
+                if !closed {
+                    return Err("Unclosed string in style block".to_string());
+                }
+            }
+            '/' if chars.peek().map(|(_, next)| *next) == Some('*') => {
+                match text[index + 2..].find("*/") {
+                    Some(end) => {
+                        let comment_end = index + 2 + end + 2;
+                        while chars.peek().is_some_and(|(next, _)| *next < comment_end) {
+                            chars.next();
+                        }
+                    }
+                    None => return Err("Unclosed comment in style block".to_string()),
+                }
+            }
+            // `url(//cdn.example.com/a.png)` is not a comment
+            'u' | 'U' if preprocessor && text[index..].to_ascii_lowercase().starts_with("url(") => {
+                let url_end = find_closing_parenthesis(&text[index + 4..])
+                    .map_or(text.len(), |end| index + 4 + end + 1);
+                while chars.peek().is_some_and(|(next, _)| *next < url_end) {
+                    chars.next();
+                }
+            }
+            '/' if preprocessor && chars.peek().map(|(_, next)| *next) == Some('/') => {
+                let comment_end = index + line_comment_length(&text[index..]);
+                while chars.peek().is_some_and(|(next, _)| *next < comment_end) {
+                    chars.next();
+                }
+            }
+            // `#{$name}` in scss and `@{name}` in less
+            '#' | '@' if preprocessor && chars.peek().map(|(_, next)| *next) == Some('{') => {
+                let interpolation_end = find_block_end(text, index + 1, preprocessor)?;
+                while chars
+                    .peek()
+                    .is_some_and(|(next, _)| *next <= interpolation_end)
+                {
+                    chars.next();
+                }
+            }
+            c if targets.contains(&c) => return Ok(Some(index)),
+            _ => (),
+        }
+    }
+    Ok(None)
+}
+
+/// Index of the `}` closing the block opened at `open`.
+fn find_block_end(text: &str, open: usize, preprocessor: bool) -> Result<usize, String> {
+    let mut depth = 0;
+    let mut position = open + 1;
+    loop {
+        let rest = &text[position..];
+        match find_outside_of_strings(rest, &['{', '}'], preprocessor)? {
+            Some(index) if rest.as_bytes()[index] == b'{' => {
+                depth += 1;
+                position += index + 1;
+            }
+            Some(index) if depth == 0 => return Ok(position + index),
+            Some(index) => {
+                depth -= 1;
+                position += index + 1;
+            }
+            None => return Err("Unclosed block in style block".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTE: &str = "data-lunas-1";
+
+    #[test]
+    fn scope_selectors() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "css").unwrap();
+        assert_eq!(
+            scope("h1, .list > li:hover {\n  color: red;\n}"),
+            "h1[data-lunas-1], .list > li:hover[data-lunas-1] {\n  color: red;\n}"
+        );
+        assert_eq!(
+            scope("a::before, a:after, input[type=\"a b\"] { content: \"}\"; }"),
+            "a[data-lunas-1]::before, a[data-lunas-1]:after, input[type=\"a b\"][data-lunas-1] { content: \"}\"; }"
+        );
+        assert_eq!(
+            scope("/* note { */\n.a .b:not(.c, .d) {}"),
+            "/* note { */\n.a .b:not(.c, .d)[data-lunas-1] {}"
+        );
+    }
+
+    #[test]
+    fn scope_global_and_deep_selectors() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "css").unwrap();
+        assert_eq!(scope(":global(body) {}"), "body {}");
+        assert_eq!(
+            scope(":global(.dark) .card {}"),
+            ".dark .card[data-lunas-1] {}"
+        );
+        assert_eq!(
+            scope(".card :global(.icon) {}"),
+            ".card[data-lunas-1] .icon {}"
+        );
+        assert_eq!(
+            scope(".list :deep(.item a) {}"),
+            ".list[data-lunas-1] .item a {}"
+        );
+        assert_eq!(scope(":deep(.item) {}"), "[data-lunas-1] .item {}");
+    }
+
+    #[test]
+    fn scope_inside_at_rules() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "css").unwrap();
+        assert_eq!(
+            scope("@import \"a.css\";\n@media (min-width: 10px) {\n  .a { color: red; }\n}\n@keyframes spin {\n  from { rotate: 0; }\n}"),
+            "@import \"a.css\";\n@media (min-width: 10px) {\n  .a[data-lunas-1] { color: red; }\n}\n@keyframes spin {\n  from { rotate: 0; }\n}"
+        );
+        assert!(scope_css(".a { color: red;", ATTRIBUTE, "css").is_err());
+        assert!(scope_css(".a :global(.b {}", ATTRIBUTE, "css").is_err());
+    }
+
+    #[test]
+    fn scope_with_line_comments() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "scss").unwrap();
+        assert_eq!(
+            scope("// Card styles\n.card {\n  color: red; // {\n}"),
+            "// Card styles\n.card[data-lunas-1] {\n  color: red; // {\n}"
+        );
+        assert_eq!(
+            scope(".a {} // don't touch\n.b { background: url(//cdn.test/b.png); }"),
+            ".a[data-lunas-1] {} // don't touch\n.b[data-lunas-1] { background: url(//cdn.test/b.png); }"
+        );
+        assert_eq!(
+            scope_css("@media print {\n  // {\n  .a {}\n}", ATTRIBUTE, "less").unwrap(),
+            "@media print {\n  // {\n  .a[data-lunas-1] {}\n}"
+        );
+        assert!(scope_css("// don't touch\n.a {}", ATTRIBUTE, "css").is_err());
+    }
+
+    #[test]
+    fn scope_interpolated_selectors() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "scss").unwrap();
+        assert_eq!(
+            scope(".icon-#{$name} .#{$a b} { width: #{$size}; }"),
+            ".icon-#{$name} .#{$a b}[data-lunas-1] { width: #{$size}; }"
+        );
+        assert_eq!(
+            scope_css(".@{name}, a { b: @{c}; }", ATTRIBUTE, "less").unwrap(),
+            ".@{name}[data-lunas-1], a[data-lunas-1] { b: @{c}; }"
+        );
+    }
+
+    #[test]
+    fn scope_nested_rules() {
+        let scope = |css: &str| scope_css(css, ATTRIBUTE, "scss").unwrap();
+        assert_eq!(
+            scope(".card {\n  color: red;\n  p { margin: 0; }\n  &:hover, .dark & { b {} }\n}"),
+            ".card[data-lunas-1] {\n  color: red;\n  p[data-lunas-1] { margin: 0; }\n  &:hover[data-lunas-1], .dark &[data-lunas-1] { b[data-lunas-1] {} }\n}"
+        );
+        assert_eq!(
+            scope(".list {\n  @media print { li {} }\n  :deep(.item) {}\n  font: { family: serif; }\n}"),
+            ".list[data-lunas-1] {\n  @media print { li[data-lunas-1] {} }\n  .item {}\n  font: { family: serif; }\n}"
+        );
+        assert!(scope_css(".btn { &-primary {} }", ATTRIBUTE, "scss").is_err());
+        assert_eq!(
+            scope_css(".a { b {} }", ATTRIBUTE, "css").unwrap(),
+            ".a[data-lunas-1] { b {} }"
+        );
+    }
+}
@@ -203,6 +203,16 @@ pub fn append_v_to_vars_in_html(
     Ok((modified_string, all_depending_values))
 }
 
+/// 32-bit FNV-1a hash, for names that have to stay the same between builds.
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
 pub fn convert_non_reactive_to_obj(input: &str, variables: &Vec<String>) -> Result<String, String> {
     let parsed = parse_module_with_swc(&input.to_string()).map_err(|e| e.to_string())?;
     let parsed_json = serde_json::to_value(&parsed).unwrap();
@@ -1,9 +1,6 @@
 use std::error::Error;
 use swc_common::{
-    comments::SingleThreadedComments,
-    errors::{ColorConfig, Handler},
-    sync::Lrc,
-    FileName, Globals, Mark, SourceMap, GLOBALS,
+    comments::SingleThreadedComments, sync::Lrc, FileName, Globals, Mark, SourceMap, GLOBALS,
 };
 use swc_ecma_codegen::to_code_default;
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsSyntax};
@@ -14,12 +11,6 @@ pub fn parse_module_with_swc(
     code: &String,
 ) -> Result<swc_ecma_ast::Module, Box<dyn std::error::Error>> {
     let cm: Lrc<swc_common::SourceMap> = Default::default();
-    let handler = swc_common::errors::Handler::with_tty_emitter(
-        swc_common::errors::ColorConfig::Auto,
-        true,
-        false,
-        Some(cm.clone()),
-    );
     let fm = cm.new_source_file(Lrc::new(swc_common::FileName::Anon), code.into());
     let lexer = swc_ecma_parser::lexer::Lexer::new(
         swc_ecma_parser::Syntax::Es(Default::default()),
@@ -29,14 +20,12 @@ pub fn parse_module_with_swc(
     );
     let mut parser = swc_ecma_parser::Parser::new_from(lexer);
 
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
-
     let module = parser.parse_module().map_err(|e| {
-        e.clone().into_diagnostic(&handler).emit();
         Box::<dyn std::error::Error>::from(format!("Failed to parse module: {}", &e.kind().msg()))
     })?;
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(format!("Failed to parse module: {}", &e.kind().msg()).into());
+    }
     Ok(module)
 }
 
@@ -44,12 +33,6 @@ pub fn parse_expr_with_swc(
     code: &String,
 ) -> Result<Box<swc_ecma_ast::Expr>, Box<dyn std::error::Error>> {
     let cm: Lrc<swc_common::SourceMap> = Default::default();
-    let handler = swc_common::errors::Handler::with_tty_emitter(
-        swc_common::errors::ColorConfig::Auto,
-        true,
-        false,
-        Some(cm.clone()),
-    );
     let fm = cm.new_source_file(Lrc::new(swc_common::FileName::Anon), code.into());
     let lexer = swc_ecma_parser::lexer::Lexer::new(
         swc_ecma_parser::Syntax::Es(Default::default()),
@@ -59,17 +42,15 @@ pub fn parse_expr_with_swc(
     );
     let mut parser = swc_ecma_parser::Parser::new_from(lexer);
 
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
-
     let expr = parser.parse_expr().map_err(|e| {
-        e.clone().into_diagnostic(&handler).emit();
         Box::<dyn std::error::Error>::from(format!(
             "Failed to parse expression: {}",
             &e.kind().msg()
         ))
     })?;
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(format!("Failed to parse expression: {}", &e.kind().msg()).into());
+    }
     Ok(expr)
 }
 
@@ -81,9 +62,6 @@ pub fn transform_ts_to_js(ts_code: &str) -> Result<String, Box<dyn Error>> {
     // Create a shared SourceMap instance (Lrc is an Arc alias)
     let cm: Lrc<SourceMap> = Default::default();
 
-    // Set up a handler for emitting errors with colored output in TTY environments
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-
     // Create a virtual source file for parsing (filename is arbitrary)
     let fm = cm.new_source_file(
         Lrc::new(FileName::Custom("input.ts".into())),
@@ -107,20 +85,17 @@ pub fn transform_ts_to_js(ts_code: &str) -> Result<String, Box<dyn Error>> {
     // Create a parser from the lexer
     let mut parser = Parser::new_from(lexer);
 
-    // Emit any lexer errors via the handler
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
-
     // TODO: Add line numbers for error positions
     // Parse the source into an AST program/module
     let module = parser.parse_program().map_err(|e| {
-        e.clone().into_diagnostic(&handler).emit();
         Box::<dyn std::error::Error>::from(format!(
             "Failed to parse TypeScript code: {}",
             &e.kind().msg()
         ))
     })?;
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(format!("Failed to parse TypeScript code: {}", &e.kind().msg()).into());
+    }
 
     // Execute transformations within a global JS context
     let globals = Globals::default();
@@ -0,0 +1,34 @@
+use lunas_html_parser::{
+    collapse_whitespace, MarkupElement, Namespace, TreeNode, VisitControl, VisitorMut,
+    RAW_TEXT_ELEMENTS,
+};
+
+use crate::orig_html_struct::structs::{Node, NodeContent};
+
+/// Collapses runs of whitespace in the text of the template, for `html(whitespace: collapse):`.
+/// Text inside elements that preserve whitespace and inside `<script>` and `<style>` is kept.
+pub fn collapse_template_whitespace(node: &mut Node) {
+    node.walk_mut(&mut WhitespaceCollapser);
+}
+
+struct WhitespaceCollapser;
+
+impl VisitorMut<Node> for WhitespaceCollapser {
+    fn enter(&mut self, node: &mut Node) -> VisitControl {
+        match &mut node.content {
+            NodeContent::Element(element)
+                if element.preserves_whitespace()
+                    || element.namespace == Namespace::Html
+                        && RAW_TEXT_ELEMENTS
+                            .contains(&element.tag_name.to_ascii_lowercase().as_str()) =>
+            {
+                VisitControl::SkipChildren
+            }
+            NodeContent::TextNode(text) => {
+                *text = collapse_whitespace(text);
+                VisitControl::Continue
+            }
+            _ => VisitControl::Continue,
+        }
+    }
+}
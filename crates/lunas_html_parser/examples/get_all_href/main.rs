@@ -1,16 +1,15 @@
-use lunas_html_parser::{Dom, Node, Result};
+use lunas_html_parser::{Dom, Result};
 
 // This example illustrates how to use the library to get all of the anchor-hrefs from a document.
 
 fn main() -> Result<()> {
     let html = include_str!("./index.html");
     let dom = Dom::parse(html)?;
-    let iter = dom.children.get(0).unwrap().into_iter();
 
-    let hrefs = iter.filter_map(|item| match item {
-        Node::Element(ref element) if element.name == "a" => element.attributes["href"].clone(),
-        _ => None,
-    });
+    let hrefs = dom
+        .select("a[href]")?
+        .into_iter()
+        .filter_map(|element| element.attributes["href"].clone());
 
     println!("\nThe following links where found:");
     for (index, href) in hrefs.enumerate() {
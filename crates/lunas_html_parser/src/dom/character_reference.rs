@@ -0,0 +1,172 @@
+//! Decoding of character references such as `&amp;`, `&#x27;` and `&nbsp;`.
+
+mod entities;
+
+use super::interpolation::map_literals;
+use entities::ENTITIES;
+
+/// Decodes the named and numeric character references of `text`, leaving interpolations
+/// untouched. In attribute values, a legacy reference without `;` that is followed by `=` or an
+/// alphanumeric character is kept as is, like browsers do.
+pub fn decode_character_references(text: &str, in_attribute: bool) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    map_literals(text, |literal| decode_literal(literal, in_attribute))
+}
+
+fn decode_literal(text: &str, in_attribute: bool) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(ampersand) = rest.find('&') {
+        decoded.push_str(&rest[..ampersand]);
+        rest = &rest[ampersand + 1..];
+        match decode_reference(rest, in_attribute) {
+            Some((value, len)) => {
+                decoded.push_str(&value);
+                rest = &rest[len..];
+            }
+            None => decoded.push('&'),
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decodes the reference at the start of `input`, which follows a `&`. Returns the decoded
+/// value and the length of the reference in `input`.
+fn decode_reference(input: &str, in_attribute: bool) -> Option<(String, usize)> {
+    match input.strip_prefix('#') {
+        Some(numeric) => decode_numeric(numeric).map(|(c, len)| (c.to_string(), len + 1)),
+        None => decode_named(input, in_attribute).map(|(value, len)| (value.to_string(), len)),
+    }
+}
+
+fn decode_numeric(input: &str) -> Option<(char, usize)> {
+    let (digits_start, radix) = match input.as_bytes().first() {
+        Some(b'x' | b'X') => (1, 16),
+        _ => (0, 10),
+    };
+    let digits = input[digits_start..]
+        .chars()
+        .take_while(|c| c.is_digit(radix))
+        .count();
+    if digits == 0 {
+        return None;
+    }
+    let end = digits_start + digits;
+    // Out of range values are replaced like any other invalid code point
+    let code = u32::from_str_radix(&input[digits_start..end], radix).unwrap_or(u32::MAX);
+    let len = match input[end..].starts_with(';') {
+        true => end + 1,
+        false => end,
+    };
+    Some((numeric_reference_char(code), len))
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+fn numeric_reference_char(code: u32) -> char {
+    let windows_1252 = match code {
+        0x80 => Some('\u{20AC}'),
+        0x82 => Some('\u{201A}'),
+        0x83 => Some('\u{0192}'),
+        0x84 => Some('\u{201E}'),
+        0x85 => Some('\u{2026}'),
+        0x86 => Some('\u{2020}'),
+        0x87 => Some('\u{2021}'),
+        0x88 => Some('\u{02C6}'),
+        0x89 => Some('\u{2030}'),
+        0x8A => Some('\u{0160}'),
+        0x8B => Some('\u{2039}'),
+        0x8C => Some('\u{0152}'),
+        0x8E => Some('\u{017D}'),
+        0x91 => Some('\u{2018}'),
+        0x92 => Some('\u{2019}'),
+        0x93 => Some('\u{201C}'),
+        0x94 => Some('\u{201D}'),
+        0x95 => Some('\u{2022}'),
+        0x96 => Some('\u{2013}'),
+        0x97 => Some('\u{2014}'),
+        0x98 => Some('\u{02DC}'),
+        0x99 => Some('\u{2122}'),
+        0x9A => Some('\u{0161}'),
+        0x9B => Some('\u{203A}'),
+        0x9C => Some('\u{0153}'),
+        0x9E => Some('\u{017E}'),
+        0x9F => Some('\u{0178}'),
+        _ => None,
+    };
+    match (code, windows_1252) {
+        (0, _) => char::REPLACEMENT_CHARACTER,
+        (_, Some(c)) => c,
+        _ => char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+fn decode_named(input: &str, in_attribute: bool) -> Option<(&'static str, usize)> {
+    let name_len = input
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .count();
+    if input[name_len..].starts_with(';') {
+        if let Some(value) = lookup(&input[..name_len + 1]) {
+            return Some((value, name_len + 1));
+        }
+    }
+    // Legacy references may omit the `;`, the longest one wins
+    let (value, len) = (1..=name_len)
+        .rev()
+        .find_map(|len| lookup(&input[..len]).map(|value| (value, len)))?;
+    let next = input[len..].chars().next();
+    match next {
+        Some(c) if in_attribute && (c == '=' || c.is_ascii_alphanumeric()) => None,
+        _ => Some((value, len)),
+    }
+}
+
+fn lookup(name: &str) -> Option<&'static str> {
+    ENTITIES
+        .binary_search_by(|(entity, _)| (*entity).cmp(name))
+        .ok()
+        .map(|i| ENTITIES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_character_references;
+
+    #[test]
+    fn decodes_named_and_numeric_references() {
+        assert_eq!(
+            decode_character_references(
+                "&lt;a&gt; &amp;&amp; &#x27;b&#39; &nbsp;&NotEqualTilde;",
+                false
+            ),
+            "<a> && 'b' \u{a0}\u{2242}\u{338}"
+        );
+        assert_eq!(
+            decode_character_references("&#0; &#x110000; &#128; &#xD800;", false),
+            "\u{FFFD} \u{FFFD} \u{20AC} \u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_and_unterminated_references() {
+        assert_eq!(
+            decode_character_references("a & b &unknown; &#; &ampx &copy2024", false),
+            "a & b &unknown; &#; &x ©2024"
+        );
+        assert_eq!(
+            decode_character_references("?a=1&copy=2&amp;b", true),
+            "?a=1&copy=2&b"
+        );
+    }
+
+    #[test]
+    fn skips_interpolations() {
+        assert_eq!(
+            decode_character_references("&lt;${a &amp;&amp; b}&gt;", false),
+            "<${a &amp;&amp; b}>"
+        );
+    }
+}
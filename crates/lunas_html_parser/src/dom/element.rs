@@ -1,21 +1,76 @@
 use super::node::Node;
-use serde::{Serialize, Serializer};
-use std::collections::{BTreeMap, HashMap};
+use super::serializer::{MarkupElement, SerializeOptions};
+use super::span::{AttributeSpan, SourceSpan};
+use indexmap::IndexMap;
+use serde::Serialize;
 use std::default::Default;
-use std::result::Result;
 
-/// Normal: `<div></div>` or Void: `<meta/>`and `<meta>`
+/// Kinds of elements, see https://html.spec.whatwg.org/multipage/syntax.html#elements-2
 #[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
-// TODO: Align with: https://html.spec.whatwg.org/multipage/syntax.html#elements-2
 pub enum ElementVariant {
     /// A normal element can have children, ex: <div></div>.
     Normal,
-    /// A void element can't have children, ex: <meta /> and <meta>
+    /// A void element can't have children, ex: <meta /> and <meta>.
+    /// Self-closed foreign elements such as <rect/> are void as well.
     Void,
+    /// Text only content that is not unescaped, ex: <script></script>
+    RawText,
+    /// Text only content in which character references are decoded, ex: <textarea></textarea>
+    EscapableRawText,
 }
 
-pub type Attributes = HashMap<String, Option<String>>;
+/// HTML elements that have no end tag
+pub const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// HTML elements whose content is raw text
+pub const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// HTML elements whose content is text with character references
+pub const ESCAPABLE_RAW_TEXT_ELEMENTS: [&str; 2] = ["textarea", "title"];
+
+/// HTML elements whose text keeps its whitespace. `<code>` is one of them, since the layout of a
+/// code sample matters even outside of a `<pre>`.
+pub const WHITESPACE_PRESERVING_ELEMENTS: [&str; 4] = ["pre", "textarea", "listing", "code"];
+
+/// HTML elements whose content does not include a newline right after the start tag
+pub const LEADING_NEWLINE_ELEMENTS: [&str; 3] = ["pre", "textarea", "listing"];
+
+/// The namespace an element belongs to. Elements are HTML unless they are inside an `<svg>` or a
+/// `<math>` element.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Namespace {
+    #[default]
+    Html,
+    Svg,
+    MathMl,
+}
+
+impl Namespace {
+    pub fn uri(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+        }
+    }
+
+    /// Namespace of an element named `name` whose parent's children are in `self`.
+    pub fn of_element(self, name: &str) -> Namespace {
+        match self {
+            Namespace::Html if name.eq_ignore_ascii_case("svg") => Namespace::Svg,
+            Namespace::Html if name.eq_ignore_ascii_case("math") => Namespace::MathMl,
+            namespace => namespace,
+        }
+    }
+}
+
+/// Attributes in the order they are declared in the source
+pub type Attributes = IndexMap<String, Option<String>>;
 
 /// Most of the parsed html nodes are elements, except for text
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -27,14 +82,23 @@ pub struct Element {
     /// The element variant, if it is of type void or not
     pub variant: ElementVariant,
 
+    /// The namespace of the element
+    pub namespace: Namespace,
+
     /// All of the elements attributes, except id and class
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
-    #[serde(serialize_with = "ordered_map")]
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
     pub attributes: Attributes,
 
     /// All of the elements child nodes
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<Node>,
+
+    /// Span of the element, from its start tag to its end tag
+    pub source_span: SourceSpan,
+
+    /// Spans of the keys and values in `attributes`
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub attribute_spans: IndexMap<String, AttributeSpan>,
 }
 
 impl Default for Element {
@@ -42,47 +106,46 @@ impl Default for Element {
         Self {
             name: "".to_string(),
             variant: ElementVariant::Void,
-            attributes: HashMap::new(),
+            namespace: Namespace::Html,
+            attributes: IndexMap::new(),
             children: vec![],
+            source_span: SourceSpan::default(),
+            attribute_spans: IndexMap::new(),
         }
     }
 }
 
-impl ToString for Element {
-    fn to_string(&self) -> String {
-        let mut string = String::new();
-        string.push_str(&format!("<{}", self.name));
-
-        // self.attributesをソートしてから出力する
-        let mut attributes: Vec<_> = self.attributes.iter().collect();
-        attributes.sort_by(|a, b| a.0.cmp(b.0));
-
-        for (key, value) in attributes {
-            if let Some(value) = value {
-                string.push_str(&format!(" {}=\"{}\"", key, value));
-            } else {
-                string.push_str(&format!(" {}", key));
-            }
+impl Element {
+    /// Namespace of the child elements, which go back to HTML inside the integration points of
+    /// SVG and MathML (`<foreignObject>`, `<mtext>`, ...).
+    pub fn children_namespace(&self) -> Namespace {
+        let is_html_integration_point = match self.namespace {
+            Namespace::Html => false,
+            Namespace::Svg => ["foreignObject", "desc", "title"]
+                .iter()
+                .any(|name| self.name.eq_ignore_ascii_case(name)),
+            Namespace::MathMl => match self.name.to_ascii_lowercase().as_str() {
+                "mi" | "mo" | "mn" | "ms" | "mtext" => true,
+                "annotation-xml" => self
+                    .attributes
+                    .get("encoding")
+                    .and_then(|encoding| encoding.as_deref())
+                    .is_some_and(|encoding| {
+                        encoding.eq_ignore_ascii_case("text/html")
+                            || encoding.eq_ignore_ascii_case("application/xhtml+xml")
+                    }),
+                _ => false,
+            },
+        };
+        match is_html_integration_point {
+            true => Namespace::Html,
+            false => self.namespace,
         }
-
-        match self.variant {
-            ElementVariant::Normal => {
-                string.push_str(">");
-                for child in &self.children {
-                    string.push_str(&child.to_string());
-                }
-                string.push_str(&format!("</{}>", self.name));
-            }
-            ElementVariant::Void => {
-                string.push_str("/>");
-            }
-        }
-
-        string
     }
 }
 
-fn ordered_map<S: Serializer>(value: &Attributes, serializer: S) -> Result<S::Ok, S::Error> {
-    let ordered: BTreeMap<_, _> = value.iter().collect();
-    ordered.serialize(serializer)
+impl ToString for Element {
+    fn to_string(&self) -> String {
+        self.to_markup(&SerializeOptions::default())
+    }
 }
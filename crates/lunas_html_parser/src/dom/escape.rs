@@ -0,0 +1,56 @@
+//! Escaping of text for the contexts markup is emitted in. Interpolations are kept as they are,
+//! since their content is code rather than text.
+
+use super::interpolation::map_literals;
+
+/// Escapes text content for HTML: `&`, `<`, `>` and no-break spaces.
+pub fn escape_text(text: &str) -> String {
+    map_literals(text, |literal| {
+        escape_with(literal, |c| match c {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '\u{a0}' => Some("&nbsp;"),
+            _ => None,
+        })
+    })
+}
+
+/// Escapes a double-quoted HTML attribute value: `&`, `"` and no-break spaces.
+pub fn escape_attribute_value(value: &str) -> String {
+    map_literals(value, |literal| {
+        escape_with(literal, |c| match c {
+            '&' => Some("&amp;"),
+            '"' => Some("&quot;"),
+            '\u{a0}' => Some("&nbsp;"),
+            _ => None,
+        })
+    })
+}
+
+/// Escapes the content of a JavaScript template literal: `\`, `` ` `` and a `${` that does not
+/// start an interpolation. Line breaks are written as escape sequences, so the value of the
+/// literal does not change when the generated code is indented.
+pub fn escape_template_literal(text: &str) -> String {
+    map_literals(text, |literal| {
+        escape_with(literal, |c| match c {
+            '\\' => Some("\\\\"),
+            '`' => Some("\\`"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            _ => None,
+        })
+        .replace("${", "\\${")
+    })
+}
+
+fn escape_with(text: &str, escape: impl Fn(char) -> Option<&'static str>) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match escape(c) {
+            Some(replacement) => escaped.push_str(replacement),
+            None => escaped.push(c),
+        }
+    }
+    escaped
+}
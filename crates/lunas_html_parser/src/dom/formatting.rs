@@ -14,15 +14,26 @@ pub fn error_msg(error: PestError<Rule>) -> Result<super::Dom> {
         Rule::el_void => "void element".to_string(),
         Rule::el_void_xml => "void element with xml ending (/>)".to_string(),
         Rule::el_process_instruct => "xml processing instruction".to_string(),
-        Rule::el_raw_text => "element with raw text (style or script)".to_string(),
+        Rule::el_raw_text => "element with raw text (script, style, textarea or title)".to_string(),
         Rule::el_normal => "normal element".to_string(),
         Rule::el_dangling => "".to_string(),
+        Rule::comment => "comment".to_string(),
         Rule::attr => "attribute (key=\"value\")".to_string(),
         Rule::attr_key => "attribute key".to_string(),
         Rule::attr_value => "attribute value".to_string(),
         Rule::el_name => "element name".to_string(),
-        Rule::el_void_name_html => "void element name".to_string(),
-        // TODO: Continue with this
+        Rule::el_void_name => "void element name".to_string(),
+        Rule::el_normal_end => "matching end tag".to_string(),
+        Rule::el_raw_text_name => "raw text element name".to_string(),
+        Rule::el_raw_text_content => "raw text".to_string(),
+        Rule::el_raw_text_end => "matching end tag of a raw text element".to_string(),
+        Rule::attr_quoted => "quoted attribute value".to_string(),
+        Rule::attr_non_quoted => "unquoted attribute value".to_string(),
+        Rule::comment_body | Rule::comment_if_body => "comment text".to_string(),
+        Rule::token_start_tag => "start tag".to_string(),
+        Rule::token_end_tag => "end tag".to_string(),
+        Rule::token_tag_close => "`>` or `/>`".to_string(),
+        Rule::token_raw_text | Rule::token_stray => "".to_string(),
         x => format!("{:?} ", x),
     });
     Err(Error::Parsing(message.to_string()))
@@ -0,0 +1,96 @@
+//! `${ ... }` interpolations in text and attribute values.
+//!
+//! The grammar keeps interpolations opaque, so escaping and character reference decoding only
+//! apply to the literal text around them.
+
+/// Part of a text or attribute value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSegment<'a> {
+    Literal(&'a str),
+    /// An interpolation, `${` and `}` included
+    Interpolation(&'a str),
+}
+
+/// Splits `text` into literal text and interpolations. An unclosed `${` is literal text.
+pub fn text_segments(text: &str) -> Vec<TextSegment<'_>> {
+    let mut segments = vec![];
+    let mut last_end = 0;
+    let mut search_from = 0;
+    while let Some(start) = text[search_from..].find("${") {
+        let start = search_from + start;
+        match find_interpolation_end(&text[start + 2..]) {
+            Some(end) => {
+                let end = start + 2 + end + 1;
+                if last_end < start {
+                    segments.push(TextSegment::Literal(&text[last_end..start]));
+                }
+                segments.push(TextSegment::Interpolation(&text[start..end]));
+                last_end = end;
+                search_from = end;
+            }
+            None => break,
+        }
+    }
+    if last_end < text.len() {
+        segments.push(TextSegment::Literal(&text[last_end..]));
+    }
+    segments
+}
+
+/// Applies `f` to the literal parts of `text`, keeping interpolations as they are.
+pub fn map_literals(text: &str, f: impl Fn(&str) -> String) -> String {
+    text_segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            TextSegment::Literal(literal) => f(literal),
+            TextSegment::Interpolation(interpolation) => interpolation.to_string(),
+        })
+        .collect()
+}
+
+/// Returns the index of the `}` closing an interpolation whose body starts `code`. Nested
+/// braces are balanced and strings and template literals are skipped, as in the html grammar.
+pub fn find_interpolation_end(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    // Open braces and template literals; `b'`'` marks a template literal, `b'{'` a brace
+    let mut stack = vec![b'{'];
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if let Some(q) = quote {
+            if c == b'\\' {
+                i += 1;
+            } else if c == q {
+                quote = None;
+            }
+        } else if stack.last() == Some(&b'`') {
+            match c {
+                b'\\' => i += 1,
+                b'`' => {
+                    stack.pop();
+                }
+                b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                    stack.push(b'{');
+                    i += 1;
+                }
+                _ => {}
+            }
+        } else {
+            match c {
+                b'"' | b'\'' => quote = Some(c),
+                b'`' => stack.push(b'`'),
+                b'{' => stack.push(b'{'),
+                b'}' => {
+                    stack.pop();
+                    if stack.is_empty() {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
@@ -3,17 +3,28 @@ use pest::{iterators::Pair, iterators::Pairs, Parser};
 use serde::Serialize;
 use std::default::Default;
 
-use crate::error::Error;
+use crate::error::{DomError, Error};
 use crate::grammar::Grammar;
 use crate::Rule;
 
+pub mod character_reference;
 pub mod element;
+pub mod escape;
 pub mod formatting;
+pub mod interpolation;
 pub mod node;
+mod recovery;
+mod selector;
+pub mod serializer;
 pub mod span;
+pub mod visitor;
+pub mod whitespace;
 
-use element::{Element, ElementVariant};
-use node::Node;
+use character_reference::decode_character_references;
+use element::{Element, ElementVariant, Namespace, ESCAPABLE_RAW_TEXT_ELEMENTS, RAW_TEXT_ELEMENTS};
+use node::{Comment, Node, Text};
+use span::{AttributeSpan, SourceSpan};
+use whitespace::{drop_leading_newline, preserves_whitespace};
 
 /// Document, DocumentFragment or Empty
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -54,7 +65,7 @@ pub struct Dom {
 
     /// A collection of all errors during parsing
     #[serde(skip_serializing)]
-    pub errors: Vec<String>,
+    pub errors: Vec<DomError>,
 }
 
 impl Default for Dom {
@@ -109,19 +120,19 @@ impl Dom {
 
                 // If we see an element, build the sub-tree and add it as a child.  If we don't
                 // have a document type yet (i.e. "empty"), select DocumentFragment
-                Rule::node_element => match Self::build_node_element(pair, &mut dom) {
-                    Ok(el) => {
-                        if let Some(node) = el {
-                            if dom.tree_type == DomVariant::Empty {
-                                dom.tree_type = DomVariant::DocumentFragment;
-                            };
-                            dom.children.push(node);
+                Rule::node_element => {
+                    match Self::build_node_element(pair.clone(), &mut dom, Namespace::Html, false) {
+                        Ok(el) => {
+                            if let Some(node) = el {
+                                if dom.tree_type == DomVariant::Empty {
+                                    dom.tree_type = DomVariant::DocumentFragment;
+                                };
+                                dom.children.push(node);
+                            }
                         }
+                        Err(error) => dom.push_error(error, &pair),
                     }
-                    Err(error) => {
-                        dom.errors.push(format!("{}", error));
-                    }
-                },
+                }
 
                 // Similar to an element, we add it as a child and select DocumentFragment if we
                 // don't already have a document type.
@@ -129,17 +140,15 @@ impl Dom {
                     if dom.tree_type == DomVariant::Empty {
                         dom.tree_type = DomVariant::DocumentFragment;
                     }
-                    let text = pair.as_str().to_string();
-                    if !text.trim().is_empty() {
-                        dom.children.push(Node::Text(text));
+                    if let Some(text) = Self::build_text(pair, false) {
+                        dom.children.push(text);
                     }
                 }
 
                 // Store comments as a child, but it doesn't affect the document type selection
                 // until the next phase (validation).
-                Rule::node_comment => {
-                    dom.children
-                        .push(Node::Comment(pair.into_inner().as_str().to_string()));
+                Rule::comment => {
+                    dom.children.push(Self::build_comment(pair));
                 }
 
                 // Ignore 'end of input', which then allows the catch-all unreachable!() arm to
@@ -231,58 +240,68 @@ impl Dom {
         Ok(dom)
     }
 
-    fn build_node_element(pair: Pair<Rule>, dom: &mut Dom) -> Result<Option<Node>> {
+    /// Builds the element of `pair`, whose parent puts its child elements in `namespace`.
+    /// Whitespace-only text is kept when the parent preserves whitespace.
+    fn build_node_element(
+        pair: Pair<Rule>,
+        dom: &mut Dom,
+        namespace: Namespace,
+        parent_preserves_whitespace: bool,
+    ) -> Result<Option<Node>> {
         let mut element = Element {
+            source_span: SourceSpan::from_pest(pair.as_span()),
             ..Element::default()
         };
 
+        // The attributes come before the children, so they are known once a child is built
+        let keeps_whitespace = |element: &Element| {
+            parent_preserves_whitespace
+                || preserves_whitespace(&element.name, element.namespace, &element.attributes)
+        };
         for pair in pair.into_inner() {
             match pair.as_rule() {
                 Rule::node_element | Rule::el_raw_text => {
-                    match Self::build_node_element(pair, dom) {
+                    let namespace = element.children_namespace();
+                    let keeps_whitespace = keeps_whitespace(&element);
+                    match Self::build_node_element(pair.clone(), dom, namespace, keeps_whitespace) {
                         Ok(el) => {
                             if let Some(child_element) = el {
                                 element.children.push(child_element)
                             }
                         }
-                        Err(error) => {
-                            dom.errors.push(format!("{}", error));
-                        }
+                        Err(error) => dom.push_error(error, &pair),
                     }
                 }
-                Rule::node_text | Rule::el_raw_text_content => {
-                    let text = pair.as_str().to_string();
-                    if !text.trim().is_empty() {
-                        element.children.push(Node::Text(text));
+                Rule::node_text => {
+                    if let Some(text) = Self::build_text(pair, keeps_whitespace(&element)) {
+                        element.children.push(text);
                     }
                 }
-                Rule::node_comment => {
-                    element
-                        .children
-                        .push(Node::Comment(pair.into_inner().as_str().to_string()));
+                Rule::el_raw_text_content => {
+                    let text = match Self::raw_text_variant(&element) {
+                        ElementVariant::RawText => Self::build_raw_text(pair),
+                        _ => Self::build_text(pair, keeps_whitespace(&element)),
+                    };
+                    if let Some(text) = text {
+                        element.children.push(text);
+                    }
+                }
+                Rule::comment => {
+                    element.children.push(Self::build_comment(pair));
                 }
-                // TODO: To enable some kind of validation we should probably align this with
-                // https://html.spec.whatwg.org/multipage/syntax.html#elements-2
-                // Also see element variants
                 Rule::el_name | Rule::el_void_name | Rule::el_raw_text_name => {
                     element.name = pair.as_str().to_string();
+                    element.namespace = namespace.of_element(&element.name);
                 }
-                Rule::attr => match Self::build_attribute(pair.into_inner()) {
-                    Ok((attr_key, attr_value)) => {
-                        match attr_key.as_str() {
-                            _ => {
-                                element.attributes.insert(attr_key, attr_value);
-                            }
-                        };
-                    }
-                    Err(error) => {
-                        dom.errors.push(format!("{}", error));
-                    }
-                },
-                Rule::el_normal_end | Rule::el_raw_text_end => {
+                Rule::attr => Self::add_attribute(&mut element, pair, dom),
+                Rule::el_normal_end => {
                     element.variant = ElementVariant::Normal;
                     break;
                 }
+                Rule::el_raw_text_end => {
+                    element.variant = Self::raw_text_variant(&element);
+                    break;
+                }
                 Rule::el_dangling => (),
                 Rule::EOI => (),
                 _ => {
@@ -293,6 +312,7 @@ impl Dom {
                 }
             }
         }
+        drop_leading_newline(&mut element);
         if element.name != "" {
             Ok(Some(Node::Element(element)))
         } else {
@@ -300,15 +320,86 @@ impl Dom {
         }
     }
 
-    fn build_attribute(pairs: Pairs<Rule>) -> Result<(String, Option<String>)> {
-        let mut attribute = ("".to_string(), None);
+    /// Adds the attribute of `pair` to `element`. The first declaration of an attribute wins,
+    /// later ones are reported.
+    fn add_attribute(element: &mut Element, pair: Pair<Rule>, dom: &mut Dom) {
+        match Self::build_attribute(pair.clone().into_inner()) {
+            Ok((attr_key, _, attr_span)) if element.attributes.contains_key(&attr_key) => {
+                dom.errors.push(DomError {
+                    message: format!("Duplicate attribute `{}` on <{}>", attr_key, element.name),
+                    source_span: attr_span.key,
+                });
+            }
+            Ok((attr_key, attr_value, attr_span)) => {
+                element.attribute_spans.insert(attr_key.clone(), attr_span);
+                element.attributes.insert(attr_key, attr_value);
+            }
+            Err(error) => dom.push_error(error, &pair),
+        }
+    }
+
+    /// Only HTML elements have raw text content, `<style>` in `<svg>` is a normal element.
+    fn raw_text_variant(element: &Element) -> ElementVariant {
+        let name = element.name.to_ascii_lowercase();
+        match element.namespace {
+            Namespace::Html if RAW_TEXT_ELEMENTS.contains(&name.as_str()) => {
+                ElementVariant::RawText
+            }
+            Namespace::Html if ESCAPABLE_RAW_TEXT_ELEMENTS.contains(&name.as_str()) => {
+                ElementVariant::EscapableRawText
+            }
+            _ => ElementVariant::Normal,
+        }
+    }
+
+    fn push_error(&mut self, error: Error, pair: &Pair<Rule>) {
+        self.errors.push(DomError {
+            message: error.to_string(),
+            source_span: SourceSpan::from_pest(pair.as_span()),
+        });
+    }
+
+    /// Text with its character references decoded
+    /// Builds a text node, unless the text is only whitespace that does not need to be kept.
+    fn build_text(pair: Pair<Rule>, keep_whitespace: bool) -> Option<Node> {
+        let text = decode_character_references(pair.as_str(), false);
+        Self::text_node(text, pair, keep_whitespace)
+    }
+
+    /// Content of `<script>` and `<style>`, kept as written
+    fn build_raw_text(pair: Pair<Rule>) -> Option<Node> {
+        Self::text_node(pair.as_str().to_string(), pair, false)
+    }
+
+    fn text_node(text: String, pair: Pair<Rule>, keep_whitespace: bool) -> Option<Node> {
+        match text.is_empty() || (!keep_whitespace && text.trim().is_empty()) {
+            true => None,
+            false => Some(Node::Text(Text {
+                text,
+                source_span: SourceSpan::from_pest(pair.as_span()),
+            })),
+        }
+    }
+
+    fn build_comment(pair: Pair<Rule>) -> Node {
+        let source_span = SourceSpan::from_pest(pair.as_span());
+        Node::Comment(Comment {
+            comment: pair.into_inner().as_str().to_string(),
+            source_span,
+        })
+    }
+
+    fn build_attribute(pairs: Pairs<Rule>) -> Result<(String, Option<String>, AttributeSpan)> {
+        let mut attribute = ("".to_string(), None, AttributeSpan::default());
         for pair in pairs {
             match pair.as_rule() {
                 Rule::attr_key => {
                     attribute.0 = pair.as_str().trim().to_string();
+                    attribute.2.key = SourceSpan::from_pest_trimmed(pair.as_span());
                 }
                 Rule::attr_non_quoted => {
-                    attribute.1 = Some(pair.as_str().trim().to_string());
+                    attribute.1 = Some(decode_character_references(pair.as_str().trim(), true));
+                    attribute.2.value = Some(SourceSpan::from_pest_trimmed(pair.as_span()));
                 }
                 Rule::attr_quoted => {
                     let inner_pair = pair
@@ -318,7 +409,11 @@ impl Dom {
                         .expect("attribute value");
 
                     match inner_pair.as_rule() {
-                        Rule::attr_value => attribute.1 = Some(inner_pair.as_str().to_string()),
+                        Rule::attr_value => {
+                            attribute.1 =
+                                Some(decode_character_references(inner_pair.as_str(), true));
+                            attribute.2.value = Some(SourceSpan::from_pest(inner_pair.as_span()));
+                        }
                         _ => {
                             return Err(Error::Parsing(format!(
                                 "Failed to parse attr value: {:?}",
@@ -348,3 +443,333 @@ impl ToString for Dom {
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_spans() {
+        let html = "<div id=\"a\">\n  héllo <!-- c -->\n  <input disabled value=x>\n</div>";
+        let dom = Dom::parse(html).unwrap();
+        let div = dom.children[0].element().unwrap();
+        assert_eq!(div.source_span.start_offset, 0);
+        assert_eq!(div.source_span.end_offset, html.len());
+        assert_eq!(
+            (div.source_span.end_line, div.source_span.end_column),
+            (4, 7)
+        );
+
+        let id = &div.attribute_spans["id"];
+        assert_eq!(div.attribute_spans.keys().collect::<Vec<_>>(), vec!["id"]);
+        assert_eq!(id.key.text, "id");
+        assert_eq!(id.value.as_ref().unwrap().start_offset, 9);
+
+        let text = &div.children[0];
+        assert!(text.source_span().text.contains("héllo"));
+        let comment = &div.children[1];
+        assert_eq!(comment.comment(), Some("c"));
+        assert_eq!(comment.source_span().text, "<!-- c -->");
+        assert_eq!(comment.source_span().start_column, 9);
+
+        let input = div.children[2].element().unwrap();
+        assert_eq!(input.source_span.start_line, 3);
+        assert!(input.attribute_spans["disabled"].value.is_none());
+        let value = input.attribute_spans["value"].value.as_ref().unwrap();
+        assert_eq!(&html[value.start_offset..value.end_offset], "x");
+    }
+
+    #[test]
+    fn interpolations_are_opaque() {
+        let html = r#"<p title="${a > b ? "x" : 'y'}" data-n=${n<3}>${a < b ? x : y} ${items.filter(i => i.n < 3).map(i => `<${i.tag}>`).length} ${ {k: "}<"}.k }</p>"#;
+        let dom = Dom::parse(html).unwrap();
+        let p = dom.children[0].element().unwrap();
+        assert_eq!(
+            p.attributes["title"].as_deref(),
+            Some(r#"${a > b ? "x" : 'y'}"#)
+        );
+        assert_eq!(p.attributes["data-n"].as_deref(), Some("${n<3}"));
+        assert_eq!(p.children.len(), 1);
+        assert_eq!(
+            p.children[0].text(),
+            Some(
+                r#"${a < b ? x : y} ${items.filter(i => i.n < 3).map(i => `<${i.tag}>`).length} ${ {k: "}<"}.k }"#
+            )
+        );
+    }
+
+    #[test]
+    fn attributes_keep_source_order() {
+        let dom = Dom::parse(r#"<a z="1" :b="x" @click="f" a/>"#).unwrap();
+        let a = dom.children[0].element().unwrap();
+        let keys = a.attributes.keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["z", ":b", "@click", "a"]);
+        assert_eq!(
+            dom.children[0].to_string(),
+            r#"<a z="1" :b="x" @click="f" a/>"#
+        );
+    }
+
+    #[test]
+    fn duplicate_attributes_are_reported() {
+        let html = "<div class=\"a\" @click=\"f\">\n  <p class=\"b\" @click=\"g\" @click=\"h\" class=\"c\"></p>\n</div>";
+        let dom = Dom::parse(html).unwrap();
+        assert_eq!(dom.errors.len(), 2);
+        assert_eq!(dom.errors[0].message, "Duplicate attribute `@click` on <p>");
+        assert_eq!(
+            (
+                dom.errors[0].source_span.start_line,
+                dom.errors[0].source_span.start_column
+            ),
+            (2, 27)
+        );
+        assert_eq!(dom.errors[1].message, "Duplicate attribute `class` on <p>");
+        let p = dom.children[0].element().unwrap().children[0]
+            .element()
+            .unwrap();
+        assert_eq!(p.attributes["class"].as_deref(), Some("b"));
+        assert_eq!(p.attributes["@click"].as_deref(), Some("g"));
+    }
+
+    #[test]
+    fn namespaces() {
+        let html = r#"<div><svg viewBox="0 0 10 10"><rect x="1"></rect><path d="M0 0"/><foreignObject><p>hi</p></foreignObject></svg><math><mi>x</mi></math></div>"#;
+        let dom = Dom::parse(html).unwrap();
+        assert!(dom.errors.is_empty());
+        let div = dom.children[0].element().unwrap();
+        assert_eq!(div.namespace, Namespace::Html);
+        let svg = div.children[0].element().unwrap();
+        assert_eq!(svg.namespace, Namespace::Svg);
+        let rect = svg.children[0].element().unwrap();
+        assert_eq!(
+            (rect.namespace, &rect.variant),
+            (Namespace::Svg, &ElementVariant::Normal)
+        );
+        let path = svg.children[1].element().unwrap();
+        assert_eq!(
+            (path.namespace, &path.variant),
+            (Namespace::Svg, &ElementVariant::Void)
+        );
+        let foreign_object = svg.children[2].element().unwrap();
+        assert_eq!(foreign_object.namespace, Namespace::Svg);
+        let p = foreign_object.children[0].element().unwrap();
+        assert_eq!(p.namespace, Namespace::Html);
+        let math = div.children[1].element().unwrap();
+        assert_eq!(math.namespace, Namespace::MathMl);
+        assert_eq!(
+            math.children[0].element().unwrap().namespace,
+            Namespace::MathMl
+        );
+    }
+
+    #[test]
+    fn element_categories() {
+        let html = "<div><colgroup><col span=2></colgroup><textarea>a <b></textarea><style>p > a {}</style><svg><style>rect {}</style></svg><br></div>";
+        let dom = Dom::parse(html).unwrap();
+        let variants = |element: &Element| {
+            element
+                .children
+                .iter()
+                .filter_map(|child| child.element())
+                .map(|child| (child.name.clone(), child.variant.clone()))
+                .collect::<Vec<_>>()
+        };
+        let div = dom.children[0].element().unwrap();
+        assert_eq!(
+            variants(div),
+            vec![
+                ("colgroup".to_string(), ElementVariant::Normal),
+                ("textarea".to_string(), ElementVariant::EscapableRawText),
+                ("style".to_string(), ElementVariant::RawText),
+                ("svg".to_string(), ElementVariant::Normal),
+                ("br".to_string(), ElementVariant::Void),
+            ]
+        );
+        let colgroup = div.children[0].element().unwrap();
+        assert_eq!(
+            variants(colgroup),
+            vec![("col".to_string(), ElementVariant::Void)]
+        );
+        let svg = div.children[3].element().unwrap();
+        assert_eq!(
+            variants(svg),
+            vec![("style".to_string(), ElementVariant::Normal)]
+        );
+    }
+
+    #[test]
+    fn character_references_round_trip() {
+        let html = r#"<p title="&quot;a&quot; &amp; b">1 &lt; 2 &amp;&amp; `${a &amp;&amp; b}`<style>a > b {}</style></p>"#;
+        let dom = Dom::parse(html).unwrap();
+        let p = dom.children[0].element().unwrap();
+        assert_eq!(p.attributes["title"].as_deref(), Some(r#""a" & b"#));
+        assert_eq!(p.children[0].text(), Some("1 < 2 && `${a &amp;&amp; b}`"));
+        assert_eq!(dom.to_string(), html);
+    }
+
+    #[test]
+    fn recovers_from_malformed_markup() {
+        let html =
+            "<div>\n  <ul><li>a<li>b</ul>\n  </span>\n  <p>1 < 2</p>\n  <section><input class=\"x";
+        assert!(Dom::parse(html).is_err());
+        let dom = Dom::parse_with_recovery(html);
+        let messages = dom
+            .errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "1:1: Unclosed element <div>: expected </div>",
+                "2:7: Unclosed element <li>: expected </li>",
+                "2:12: Unclosed element <li>: expected </li>",
+                "3:3: Unexpected end tag </span>: no <span> is open",
+                "4:8: Unexpected `<`; write `&lt;` for a literal less-than sign",
+                "5:3: Unclosed element <section>: expected </section>",
+                "5:12: Unterminated start tag <input>: expected `>`",
+            ]
+        );
+        let div = dom.children[0].element().unwrap();
+        let ul = div.children[0].element().unwrap();
+        assert_eq!(ul.children.len(), 1);
+        let li = ul.children[0].element().unwrap();
+        assert_eq!(
+            li.children[1].element().unwrap().children[0].text(),
+            Some("b")
+        );
+        let p = div.children[1].element().unwrap();
+        assert_eq!(p.to_string(), "<p>1 &lt; 2</p>");
+        let section = div.children[2].element().unwrap();
+        assert_eq!(section.children[0].element().unwrap().name, "input");
+        assert_eq!(div.source_span.end_offset, html.len());
+    }
+
+    #[test]
+    fn recovers_from_unterminated_comment() {
+        let html = "<div>a</div>\n<!-- <p>b</p>";
+        let dom = Dom::parse_with_recovery(html);
+        let messages = dom
+            .errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["2:1: Unterminated comment: expected `-->`"]);
+        assert_eq!(dom.children.len(), 2);
+        assert_eq!(dom.children[1].comment(), Some("<p>b</p>"));
+    }
+
+    #[test]
+    fn select() {
+        let html = r#"<div id="app"><ul class="menu"><li class="item active" data-id="1">a</li><li class="item" :if="x">b</li><li class="item active">c</li></ul><p lang="en-US">d</p><span>e</span></div>"#;
+        let dom = Dom::parse(html).unwrap();
+        let texts = |elements: Vec<&Element>| {
+            elements
+                .iter()
+                .map(|element| element.children[0].text().unwrap_or_default().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            texts(dom.select("ul > li.active[data-id]").unwrap()),
+            vec!["a"]
+        );
+        assert_eq!(
+            texts(dom.select("#app .item.active").unwrap()),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            texts(dom.select("li[:if], p[lang|=en]").unwrap()),
+            vec!["b", "d"]
+        );
+        assert_eq!(texts(dom.select("li.active ~ li").unwrap()), vec!["b", "c"]);
+        assert_eq!(texts(dom.select("ul + p, ul ~ *").unwrap()), vec!["d", "e"]);
+        assert_eq!(texts(dom.select("div li[data-id='1']").unwrap()), vec!["a"]);
+
+        let ul = dom.select("ul").unwrap()[0];
+        assert_eq!(
+            texts(ul.select("ul > li[class^=item]").unwrap()),
+            vec!["a", "b", "c"]
+        );
+        assert!(ul.select("div li").unwrap().is_empty());
+        assert!(dom.select("li:first-child").is_err());
+        assert!(dom.select("ul >").is_err());
+    }
+
+    #[test]
+    fn visitors() {
+        use super::visitor::{ParentNode, VisitControl, Visitor, VisitorMut};
+
+        #[derive(Default)]
+        struct Trace(Vec<String>);
+
+        impl<'a> Visitor<'a, Node> for Trace {
+            fn enter(&mut self, node: &'a Node) -> VisitControl {
+                match node {
+                    Node::Element(element) => {
+                        self.0.push(format!("<{}>", element.name));
+                        match element.name.as_str() {
+                            "svg" => VisitControl::SkipChildren,
+                            "i" => VisitControl::Stop,
+                            _ => VisitControl::Continue,
+                        }
+                    }
+                    _ => VisitControl::Continue,
+                }
+            }
+
+            fn leave(&mut self, node: &'a Node) {
+                if let Node::Element(element) = node {
+                    self.0.push(format!("</{}>", element.name));
+                }
+            }
+        }
+
+        /// Wraps every text in a `<span>` and drops comments
+        struct WrapTexts;
+
+        impl VisitorMut<Node> for WrapTexts {
+            fn leave(&mut self, node: &mut Node) {
+                if let Node::Element(element) = node {
+                    element.children.retain(|child| child.comment().is_none());
+                    for index in 0..element.children.len() {
+                        if element.children[index].is_text() && element.name != "span" {
+                            let span = Element {
+                                name: "span".to_string(),
+                                variant: ElementVariant::Normal,
+                                ..Element::default()
+                            };
+                            element.wrap_child(index, span);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut dom =
+            Dom::parse("<div><svg><rect/></svg><p>a<!-- c --></p><i></i><b></b></div>").unwrap();
+        let mut trace = Trace::default();
+        assert_eq!(dom.walk(&mut trace), VisitControl::Stop);
+        assert_eq!(
+            trace.0,
+            vec!["<div>", "<svg>", "</svg>", "<p>", "</p>", "<i>"]
+        );
+
+        dom.walk_mut(&mut WrapTexts);
+        let div = dom.children[0].element().unwrap().clone();
+        assert_eq!(
+            div.to_string(),
+            "<div><svg><rect/></svg><p><span>a</span></p><i></i><b></b></div>"
+        );
+
+        let mut div = div;
+        let b = div.detach_child(3).unwrap();
+        div.insert_child(0, b);
+        let old = div.replace_child(1, Node::Text(Text::default())).unwrap();
+        assert_eq!(old.element().unwrap().name, "svg");
+        assert!(div.detach_child(9).is_none());
+        assert_eq!(
+            div.to_string(),
+            "<div><b></b><p><span>a</span></p><i></i></div>"
+        );
+    }
+}
@@ -1,18 +1,37 @@
 use super::element::Element;
+use super::serializer::{MarkupNode, SerializeOptions};
+use super::span::SourceSpan;
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(untagged)]
 pub enum Node {
-    Text(String),
+    Text(Text),
     Element(Element),
-    Comment(String),
+    Comment(Comment),
+}
+
+/// A text node
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Text {
+    pub text: String,
+    pub source_span: SourceSpan,
+}
+
+/// A comment, without the surrounding `<!--` and `-->`
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    pub comment: String,
+    /// Span of the whole comment, delimiters included
+    pub source_span: SourceSpan,
 }
 
 impl Node {
     pub fn text(&self) -> Option<&str> {
         match self {
-            Node::Text(t) => Some(t.as_str()),
+            Node::Text(t) => Some(t.text.as_str()),
             _ => None,
         }
     }
@@ -26,7 +45,7 @@ impl Node {
 
     pub fn comment(&self) -> Option<&str> {
         match self {
-            Node::Comment(t) => Some(t.as_str()),
+            Node::Comment(t) => Some(t.comment.as_str()),
             _ => None,
         }
     }
@@ -40,12 +59,21 @@ impl Node {
 
     pub fn as_text(&self) -> String {
         match self {
-            Node::Text(txt) => txt.clone(),
+            Node::Text(txt) => txt.text.clone(),
             _ => {
                 panic!("Node is not text")
             }
         }
     }
+
+    /// Where the node was found in the parsed input
+    pub fn source_span(&self) -> &SourceSpan {
+        match self {
+            Node::Text(t) => &t.source_span,
+            Node::Element(e) => &e.source_span,
+            Node::Comment(c) => &c.source_span,
+        }
+    }
 }
 
 impl<'a> IntoIterator for &'a Node {
@@ -125,11 +153,7 @@ impl<'a> Iterator for NodeIntoIterator<'a> {
 
 impl ToString for Node {
     fn to_string(&self) -> String {
-        match self {
-            Node::Text(t) => t.clone(),
-            Node::Element(e) => e.to_string(),
-            Node::Comment(_) => "".to_string(),
-        }
+        self.to_markup(&SerializeOptions::default())
     }
 }
 
@@ -139,7 +163,10 @@ mod tests {
 
     #[test]
     fn node_utillity_functions() {
-        let node = Node::Text("test".to_string());
+        let node = Node::Text(Text {
+            text: "test".to_string(),
+            ..Text::default()
+        });
 
         assert_eq!(node.text(), Some("test"));
         assert_eq!(node.element(), None);
@@ -151,7 +178,10 @@ mod tests {
         assert_eq!(node.element(), Some(&Element::default()));
         assert_eq!(node.comment(), None);
 
-        let node = Node::Comment("test".to_string());
+        let node = Node::Comment(Comment {
+            comment: "test".to_string(),
+            ..Comment::default()
+        });
 
         assert_eq!(node.text(), None);
         assert_eq!(node.element(), None);
@@ -0,0 +1,254 @@
+//! Tolerant tree building for `Dom::parse_with_recovery`.
+//!
+//! The input is read as a flat stream of tags (see the RECOVERY section of the grammar) and the
+//! tree is built with a stack of open elements, so a missing or misplaced end tag only affects
+//! the elements around it.
+
+use pest::iterators::Pair;
+use pest::{Parser, Span};
+
+use super::element::{Element, ElementVariant, Namespace, VOID_ELEMENTS};
+use super::node::{Comment, Node, Text};
+use super::span::SourceSpan;
+use super::whitespace::{drop_leading_newline, preserves_whitespace};
+use super::{Dom, DomVariant};
+use crate::error::DomError;
+use crate::grammar::Grammar;
+use crate::Rule;
+
+impl Dom {
+    /// Parses `input` without giving up on malformed markup.
+    ///
+    /// Unclosed elements are closed at the end of their parent, end tags without a matching
+    /// start tag are dropped, a `<!--` without `-->` comments out the rest of the input and a
+    /// `<` that does not start a tag is kept as text. Every such problem is reported in
+    /// [`Dom::errors`] in source order, so editor tooling gets a tree even while a template is
+    /// being typed.
+    pub fn parse_with_recovery(input: &str) -> Self {
+        let mut builder = TreeBuilder {
+            input,
+            dom: Dom::default(),
+            open_elements: vec![],
+        };
+        match Grammar::parse(Rule::html_tokens, input) {
+            Ok(pairs) => {
+                for pair in pairs {
+                    builder.add_token(pair);
+                }
+            }
+            // The token grammar accepts any input, this is only a safeguard
+            Err(error) => builder.dom.errors.push(DomError {
+                message: error.variant.message().to_string(),
+                source_span: builder.span(0, input.len()),
+            }),
+        }
+        builder.finish()
+    }
+}
+
+struct TreeBuilder<'i> {
+    input: &'i str,
+    dom: Dom,
+    /// Elements whose end tag has not been seen yet, innermost last
+    open_elements: Vec<Element>,
+}
+
+impl<'i> TreeBuilder<'i> {
+    fn add_token(&mut self, pair: Pair<'i, Rule>) {
+        match pair.as_rule() {
+            Rule::doctype => self.dom.tree_type = DomVariant::Document,
+            Rule::comment => self.push_node(Dom::build_comment(pair)),
+            Rule::node_text => {
+                if let Some(text) = Dom::build_text(pair, self.preserves_whitespace()) {
+                    self.push_node(text);
+                }
+            }
+            Rule::token_raw_text => {
+                let namespace = self.children_namespace();
+                let keeps_whitespace = self.preserves_whitespace();
+                match Dom::build_node_element(
+                    pair.clone(),
+                    &mut self.dom,
+                    namespace,
+                    keeps_whitespace,
+                ) {
+                    Ok(Some(node)) => self.push_node(node),
+                    Ok(None) => (),
+                    Err(error) => self.dom.push_error(error, &pair),
+                }
+            }
+            Rule::token_start_tag => self.start_element(pair),
+            Rule::token_end_tag => self.end_element(pair),
+            Rule::token_comment => {
+                let span = SourceSpan::from_pest(pair.as_span());
+                self.error(
+                    "Unterminated comment: expected `-->`".to_string(),
+                    span.clone(),
+                );
+                self.push_node(Node::Comment(Comment {
+                    comment: pair.into_inner().as_str().to_string(),
+                    source_span: span,
+                }));
+            }
+            Rule::token_stray => {
+                self.error(
+                    "Unexpected `<`; write `&lt;` for a literal less-than sign".to_string(),
+                    SourceSpan::from_pest(pair.as_span()),
+                );
+                self.push_node(Node::Text(Text {
+                    text: "<".to_string(),
+                    source_span: SourceSpan::from_pest(pair.as_span()),
+                }));
+            }
+            Rule::el_process_instruct | Rule::EOI => (),
+            rule => unreachable!("[recover dom] unknown rule: {:?}", rule),
+        }
+    }
+
+    fn start_element(&mut self, pair: Pair<'i, Rule>) {
+        let span = pair.as_span();
+        let mut element = Element {
+            source_span: SourceSpan::from_pest(span),
+            variant: ElementVariant::Normal,
+            ..Element::default()
+        };
+        let mut self_closing = false;
+        let mut terminated = false;
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::el_name => {
+                    element.name = pair.as_str().to_string();
+                    element.namespace = self.children_namespace().of_element(&element.name);
+                }
+                Rule::attr => Dom::add_attribute(&mut element, pair, &mut self.dom),
+                Rule::token_tag_close => {
+                    terminated = true;
+                    self_closing = pair.as_str() == "/>";
+                }
+                rule => unreachable!("[recover dom] unknown rule in start tag: {:?}", rule),
+            }
+        }
+        if !terminated {
+            self.error(
+                format!("Unterminated start tag <{}>: expected `>`", element.name),
+                SourceSpan::from_pest(span),
+            );
+        }
+        let is_void = element.namespace == Namespace::Html
+            && VOID_ELEMENTS.contains(&element.name.to_ascii_lowercase().as_str());
+        if self_closing || is_void {
+            element.variant = ElementVariant::Void;
+            self.push_node(Node::Element(element));
+        } else {
+            self.open_elements.push(element);
+        }
+    }
+
+    fn end_element(&mut self, pair: Pair<'i, Rule>) {
+        let span = pair.as_span();
+        let name = pair
+            .into_inner()
+            .next()
+            .map(|name| name.as_str())
+            .unwrap_or_default();
+        let position = self
+            .open_elements
+            .iter()
+            .rposition(|element| same_tag_name(element, name));
+        let position = match position {
+            Some(position) => position,
+            None => {
+                self.error(
+                    format!("Unexpected end tag </{}>: no <{}> is open", name, name),
+                    SourceSpan::from_pest(span),
+                );
+                return;
+            }
+        };
+        while self.open_elements.len() > position + 1 {
+            self.close_unclosed(span.start());
+        }
+        if !span.as_str().ends_with('>') {
+            self.error(
+                format!("Unterminated end tag </{}>: expected `>`", name),
+                SourceSpan::from_pest(span),
+            );
+        }
+        self.close(span.end());
+    }
+
+    /// Closes the innermost open element, which ends at `end`.
+    fn close(&mut self, end: usize) {
+        if let Some(mut element) = self.open_elements.pop() {
+            drop_leading_newline(&mut element);
+            element.source_span = self.span(element.source_span.start_offset, end);
+            self.push_node(Node::Element(element));
+        }
+    }
+
+    /// Closes the innermost open element, reporting that its end tag is missing.
+    fn close_unclosed(&mut self, end: usize) {
+        if let Some(element) = self.open_elements.last() {
+            let message = format!(
+                "Unclosed element <{}>: expected </{}>",
+                element.name, element.name
+            );
+            let start_tag = element.source_span.clone();
+            self.error(message, start_tag);
+        }
+        self.close(end);
+    }
+
+    fn finish(mut self) -> Dom {
+        while !self.open_elements.is_empty() {
+            self.close_unclosed(self.input.len());
+        }
+        if self.dom.tree_type == DomVariant::Empty && !self.dom.children.is_empty() {
+            self.dom.tree_type = DomVariant::DocumentFragment;
+        }
+        // Unclosed elements are only noticed when their parent closes, report in source order
+        self.dom
+            .errors
+            .sort_by_key(|error| error.source_span.start_offset);
+        self.dom
+    }
+
+    fn push_node(&mut self, node: Node) {
+        match self.open_elements.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.dom.children.push(node),
+        }
+    }
+
+    fn children_namespace(&self) -> Namespace {
+        self.open_elements
+            .last()
+            .map_or(Namespace::Html, |parent| parent.children_namespace())
+    }
+
+    /// Whether text added now is inside an element that preserves whitespace
+    fn preserves_whitespace(&self) -> bool {
+        self.open_elements.iter().any(|element| {
+            preserves_whitespace(&element.name, element.namespace, &element.attributes)
+        })
+    }
+
+    fn error(&mut self, message: String, source_span: SourceSpan) {
+        self.dom.errors.push(DomError {
+            message,
+            source_span,
+        });
+    }
+
+    fn span(&self, start: usize, end: usize) -> SourceSpan {
+        SourceSpan::from_pest(Span::new(self.input, start, end).expect("span inside the input"))
+    }
+}
+
+/// Tag names of HTML elements are case-insensitive, those of SVG and MathML are not.
+fn same_tag_name(element: &Element, name: &str) -> bool {
+    match element.namespace {
+        Namespace::Html => element.name.eq_ignore_ascii_case(name),
+        _ => element.name == name,
+    }
+}
@@ -0,0 +1,248 @@
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+
+use super::element::Element;
+use super::node::Node;
+use super::Dom;
+use crate::error::Error;
+use crate::Result;
+
+#[derive(Parser)]
+#[grammar = "grammar/selector.pest"]
+struct SelectorGrammar;
+
+/// A parsed list of CSS selectors, see [`Dom::select`].
+///
+/// Names are compared as written, so `button` does not match a `<Button>` component.
+#[derive(Debug, Clone, PartialEq)]
+struct Selector {
+    alternatives: Vec<ComplexSelector>,
+}
+
+/// Compound selectors joined by combinators, e.g. `ul > li.active`
+#[derive(Debug, Clone, PartialEq)]
+struct ComplexSelector {
+    compounds: Vec<CompoundSelector>,
+    /// `combinators[i]` joins `compounds[i]` and `compounds[i + 1]`
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CompoundSelector {
+    type_name: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<AttributeSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AttributeSelector {
+    name: String,
+    /// Operator and value, `None` for `[name]`
+    condition: Option<(String, String)>,
+}
+
+/// An element and the nodes before it in its parent
+type Position<'a> = (&'a Element, &'a [Node]);
+
+impl Selector {
+    fn parse(selectors: &str) -> Result<Self> {
+        let pairs = SelectorGrammar::parse(Rule::selector_list, selectors).map_err(|error| {
+            Error::Parsing(format!("Invalid selector `{}`:\n{}", selectors, error))
+        })?;
+        let alternatives = pairs
+            .filter(|pair| pair.as_rule() == Rule::complex)
+            .map(ComplexSelector::from_pair)
+            .collect();
+        Ok(Self { alternatives })
+    }
+
+    /// Elements of `nodes` and their descendants that match, in document order.
+    fn select_in<'a>(&self, nodes: &'a [Node], path: &mut Vec<Position<'a>>) -> Vec<&'a Element> {
+        let mut selected = vec![];
+        for (index, node) in nodes.iter().enumerate() {
+            if let Node::Element(element) = node {
+                path.push((element, &nodes[..index]));
+                if self
+                    .alternatives
+                    .iter()
+                    .any(|complex| complex.matches(complex.compounds.len() - 1, path))
+                {
+                    selected.push(element);
+                }
+                selected.extend(self.select_in(&element.children, path));
+                path.pop();
+            }
+        }
+        selected
+    }
+}
+
+impl ComplexSelector {
+    fn from_pair(pair: Pair<Rule>) -> Self {
+        let mut complex = Self {
+            compounds: vec![],
+            combinators: vec![],
+        };
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::compound => complex.compounds.push(CompoundSelector::from_pair(pair)),
+                Rule::combinator => {
+                    let combinator = match pair.into_inner().next().map(|pair| pair.as_rule()) {
+                        Some(Rule::child) => Combinator::Child,
+                        Some(Rule::next_sibling) => Combinator::NextSibling,
+                        Some(Rule::subsequent_sibling) => Combinator::SubsequentSibling,
+                        _ => Combinator::Descendant,
+                    };
+                    complex.combinators.push(combinator);
+                }
+                rule => unreachable!("[selector] unknown rule: {:?}", rule),
+            }
+        }
+        complex
+    }
+
+    /// Whether the last element of `path` matches the compounds up to `compounds[index]`.
+    fn matches(&self, index: usize, path: &[Position]) -> bool {
+        let (element, previous) = match path.last() {
+            Some(position) => *position,
+            None => return false,
+        };
+        if !self.compounds[index].matches(element) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        let ancestors = &path[..path.len() - 1];
+        match self.combinators[index - 1] {
+            Combinator::Child => self.matches(index - 1, ancestors),
+            Combinator::Descendant => {
+                (1..=ancestors.len()).any(|len| self.matches(index - 1, &ancestors[..len]))
+            }
+            Combinator::NextSibling => match previous_siblings(previous).next() {
+                Some(sibling) => self.matches(index - 1, &with_last(ancestors, sibling)),
+                None => false,
+            },
+            Combinator::SubsequentSibling => previous_siblings(previous)
+                .any(|sibling| self.matches(index - 1, &with_last(ancestors, sibling))),
+        }
+    }
+}
+
+impl CompoundSelector {
+    fn from_pair(pair: Pair<Rule>) -> Self {
+        let mut compound = Self::default();
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::type_name => compound.type_name = Some(pair.as_str().to_string()),
+                Rule::universal => (),
+                Rule::id => compound.ids.push(pair.as_str()[1..].to_string()),
+                Rule::class => compound.classes.push(pair.as_str()[1..].to_string()),
+                Rule::attribute => {
+                    let mut inner = pair.into_inner();
+                    let name = inner.next().map_or("", |name| name.as_str()).to_string();
+                    let condition = inner.next().map(|op| {
+                        let value = inner.next().map_or("", |value| value.as_str());
+                        (op.as_str().to_string(), value.to_string())
+                    });
+                    compound
+                        .attributes
+                        .push(AttributeSelector { name, condition });
+                }
+                rule => unreachable!("[selector] unknown rule: {:?}", rule),
+            }
+        }
+        compound
+    }
+
+    fn matches(&self, element: &Element) -> bool {
+        let attribute = |name: &str| element.attributes.get(name).map(|value| value.as_deref());
+        let classes = || {
+            attribute("class")
+                .flatten()
+                .unwrap_or_default()
+                .split_whitespace()
+        };
+        self.type_name
+            .as_ref()
+            .is_none_or(|type_name| *type_name == element.name)
+            && self
+                .ids
+                .iter()
+                .all(|id| attribute("id") == Some(Some(id.as_str())))
+            && self
+                .classes
+                .iter()
+                .all(|class| classes().any(|c| c == class))
+            && self.attributes.iter().all(|selector| {
+                match (attribute(&selector.name), &selector.condition) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(value), Some((op, expected))) => {
+                        attribute_matches(value.unwrap_or_default(), op, expected)
+                    }
+                }
+            })
+    }
+}
+
+fn attribute_matches(value: &str, op: &str, expected: &str) -> bool {
+    match op {
+        "=" => value == expected,
+        "~=" => value.split_whitespace().any(|word| word == expected),
+        "|=" => value == expected || value.starts_with(&format!("{}-", expected)),
+        "^=" => !expected.is_empty() && value.starts_with(expected),
+        "$=" => !expected.is_empty() && value.ends_with(expected),
+        "*=" => !expected.is_empty() && value.contains(expected),
+        _ => false,
+    }
+}
+
+/// Element siblings in `previous`, the closest first, with the nodes before each of them.
+fn previous_siblings(previous: &[Node]) -> impl Iterator<Item = Position<'_>> {
+    previous
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(move |(index, node)| {
+            node.element().map(|element| (element, &previous[..index]))
+        })
+}
+
+fn with_last<'a>(ancestors: &[Position<'a>], last: Position<'a>) -> Vec<Position<'a>> {
+    let mut path = ancestors.to_vec();
+    path.push(last);
+    path
+}
+
+impl Dom {
+    /// Returns the elements matching a CSS selector list, in document order.
+    ///
+    /// Type, universal (`*`), id, class and attribute selectors (`[a]`, `[a=v]`, `[a~=v]`,
+    /// `[a|=v]`, `[a^=v]`, `[a$=v]`, `[a*=v]`) can be combined with the descendant, `>`, `+` and
+    /// `~` combinators, e.g. `ul > li.active[data-id]`. Directive attributes can be selected
+    /// without escaping, as in `[:if]` or `[@click]`.
+    pub fn select(&self, selectors: &str) -> Result<Vec<&Element>> {
+        Ok(Selector::parse(selectors)?.select_in(&self.children, &mut vec![]))
+    }
+}
+
+impl Element {
+    /// Like [`Dom::select`], for the descendants of this element. Only this element and its
+    /// descendants are considered when matching combinators.
+    pub fn select(&self, selectors: &str) -> Result<Vec<&Element>> {
+        let selector = Selector::parse(selectors)?;
+        let mut path = vec![(self, &[][..])];
+        Ok(selector.select_in(&self.children, &mut path))
+    }
+}
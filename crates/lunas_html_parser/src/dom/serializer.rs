@@ -0,0 +1,420 @@
+//! Serialization of node trees back to markup.
+//!
+//! The same serializer writes the parsed dom and trees derived from it, like the one of the
+//! generator, through the [`MarkupNode`] and [`MarkupElement`] traits.
+
+use super::element::{Attributes, Element, ElementVariant, Namespace};
+use super::element::{RAW_TEXT_ELEMENTS, VOID_ELEMENTS};
+use super::escape::{escape_attribute_value, escape_text};
+use super::node::Node;
+use super::visitor::{ParentNode, TreeNode};
+use super::whitespace::{collapse_whitespace, ignores_leading_newline, preserves_whitespace};
+use super::Dom;
+
+/// How markup is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SerializeMode {
+    /// Every attribute value quoted and self-closing tags kept as they were parsed
+    #[default]
+    Preserve,
+    /// Attribute quotes and empty values left out where HTML allows it
+    Minify,
+    /// One element or text per line, indented by depth
+    Pretty,
+}
+
+/// Options of [`MarkupNode::to_markup`] and the other serializing methods
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializeOptions {
+    pub mode: SerializeMode,
+    /// Indentation of one level in [`SerializeMode::Pretty`]
+    pub indent: String,
+    /// Whether comments are written, they are left out by default
+    pub comments: bool,
+    /// Whether runs of whitespace are written as a single space, except in the elements that
+    /// preserve whitespace.
+    pub collapse_whitespace: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            mode: SerializeMode::Preserve,
+            indent: "  ".to_string(),
+            comments: false,
+            collapse_whitespace: false,
+        }
+    }
+}
+
+impl SerializeOptions {
+    pub fn minify() -> Self {
+        Self {
+            mode: SerializeMode::Minify,
+            ..Self::default()
+        }
+    }
+
+    pub fn pretty() -> Self {
+        Self {
+            mode: SerializeMode::Pretty,
+            ..Self::default()
+        }
+    }
+}
+
+/// What a node is, as far as serializing it is concerned
+pub enum Markup<'a, E> {
+    Element(&'a E),
+    Text(&'a str),
+    Comment(&'a str),
+}
+
+/// A node that can be written as markup.
+pub trait MarkupNode: TreeNode<Element: MarkupElement> {
+    fn markup(&self) -> Markup<'_, Self::Element>;
+
+    fn to_markup(&self, options: &SerializeOptions) -> String {
+        let mut writer = Writer::new(options);
+        writer.node(self, Context::Flow, 0);
+        writer.out
+    }
+}
+
+/// An element that can be written as markup.
+pub trait MarkupElement: ParentNode {
+    fn tag_name(&self) -> &str;
+
+    fn attributes(&self) -> &Attributes;
+
+    fn namespace(&self) -> Namespace;
+
+    /// Whether the element was written as `<name/>`
+    fn is_self_closing(&self) -> bool {
+        false
+    }
+
+    /// Whether the text of the element keeps its whitespace, see
+    /// [`preserves_whitespace`](super::whitespace::preserves_whitespace).
+    fn preserves_whitespace(&self) -> bool {
+        preserves_whitespace(self.tag_name(), self.namespace(), self.attributes())
+    }
+
+    fn to_markup(&self, options: &SerializeOptions) -> String
+    where
+        Self::Node: MarkupNode<Element = Self>,
+    {
+        let mut writer = Writer::new(options);
+        writer.element::<Self::Node>(self, Context::Flow, 0);
+        writer.out
+    }
+
+    /// Markup of the children of the element, laid out as they are inside it.
+    fn children_to_markup(&self, options: &SerializeOptions) -> String
+    where
+        Self::Node: MarkupNode<Element = Self>,
+    {
+        let mut writer = Writer::new(options);
+        writer.nodes(self.child_nodes(), Context::of(self), 0);
+        writer.out
+    }
+}
+
+/// Writes nodes one after the other, as the children of a fragment.
+pub fn nodes_to_markup<N: MarkupNode>(nodes: &[N], options: &SerializeOptions) -> String {
+    let mut writer = Writer::new(options);
+    writer.nodes(nodes, Context::Flow, 0);
+    writer.out
+}
+
+/// How the text inside an element is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Flow,
+    /// Whitespace is rendered, so text is kept as it is
+    Preformatted,
+    /// The content of `<script>` and `<style>`, which is not escaped
+    RawText,
+}
+
+impl Context {
+    fn of<E: MarkupElement>(element: &E) -> Self {
+        let name = element.tag_name().to_ascii_lowercase();
+        match element.namespace() {
+            Namespace::Html if RAW_TEXT_ELEMENTS.contains(&name.as_str()) => Context::RawText,
+            _ if element.preserves_whitespace() => Context::Preformatted,
+            _ => Context::Flow,
+        }
+    }
+}
+
+struct Writer<'o> {
+    options: &'o SerializeOptions,
+    out: String,
+}
+
+impl<'o> Writer<'o> {
+    fn new(options: &'o SerializeOptions) -> Self {
+        Self {
+            options,
+            out: String::new(),
+        }
+    }
+
+    fn pretty(&self, context: Context) -> bool {
+        self.options.mode == SerializeMode::Pretty && context == Context::Flow
+    }
+
+    fn nodes<N: MarkupNode>(&mut self, nodes: &[N], context: Context, depth: usize) {
+        if !self.pretty(context) {
+            for node in nodes {
+                self.node(node, context, depth);
+            }
+            return;
+        }
+        let mut first = true;
+        for node in nodes {
+            if !self.is_written(node) {
+                continue;
+            }
+            if !first {
+                self.newline(depth);
+            }
+            first = false;
+            self.node(node, context, depth);
+        }
+    }
+
+    fn node<N: MarkupNode>(&mut self, node: &N, context: Context, depth: usize) {
+        match node.markup() {
+            Markup::Element(element) => self.element::<N>(element, context, depth),
+            Markup::Text(text) => self.text(text, context),
+            Markup::Comment(comment) => {
+                if self.options.comments {
+                    self.out.push_str(&format!("<!--{}-->", comment));
+                }
+            }
+        }
+    }
+
+    fn element<N: MarkupNode>(&mut self, element: &N::Element, context: Context, depth: usize) {
+        let name = element.tag_name();
+        self.out.push('<');
+        self.out.push_str(name);
+        let mut ends_unquoted = false;
+        for (key, value) in element.attributes() {
+            ends_unquoted = self.attribute(key, value.as_deref());
+        }
+
+        let is_html = element.namespace() == Namespace::Html;
+        if is_html && VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str()) {
+            self.out.push('>');
+            return;
+        }
+        let children = element.child_nodes();
+        let self_closing = match self.options.mode {
+            SerializeMode::Preserve => element.is_self_closing(),
+            // `<name/>` only closes foreign elements in HTML
+            _ => !is_html,
+        };
+        if self_closing && children.is_empty() {
+            // `<rect x=1/>` would read as `x="1/"`
+            self.out.push_str(if ends_unquoted { " />" } else { "/>" });
+            return;
+        }
+        self.out.push('>');
+        // Parsers drop a newline right after the start tag, so a newline of the text is written
+        // after it
+        if ignores_leading_newline(name, element.namespace()) {
+            if let Some(Markup::Text(text)) = children.first().map(|child| child.markup()) {
+                if text.starts_with('\n') {
+                    self.out.push('\n');
+                }
+            }
+        }
+
+        let inner = match context {
+            Context::Flow => Context::of(element),
+            _ => context,
+        };
+        let has_element_child = children
+            .iter()
+            .any(|child| matches!(child.markup(), Markup::Element(_)));
+        if self.pretty(inner) && has_element_child {
+            self.newline(depth + 1);
+            self.nodes(children, inner, depth + 1);
+            self.newline(depth);
+        } else {
+            self.nodes(children, inner, depth + 1);
+        }
+        self.out.push_str(&format!("</{}>", name));
+    }
+
+    /// Writes an attribute, returning whether its value was left unquoted.
+    fn attribute(&mut self, key: &str, value: Option<&str>) -> bool {
+        self.out.push(' ');
+        self.out.push_str(key);
+        match value {
+            None => false,
+            Some("") if self.options.mode == SerializeMode::Minify => false,
+            Some(value) if self.options.mode == SerializeMode::Minify && can_be_unquoted(value) => {
+                self.out.push('=');
+                self.out.push_str(&escape_attribute_value(value));
+                true
+            }
+            Some(value) => {
+                self.out
+                    .push_str(&format!("=\"{}\"", escape_attribute_value(value)));
+                false
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str, context: Context) {
+        match (context, self.options.mode) {
+            (Context::RawText, _) => self.out.push_str(text),
+            (Context::Preformatted, _) => self.out.push_str(&escape_text(text)),
+            (_, SerializeMode::Pretty) => self
+                .out
+                .push_str(&escape_text(collapse_whitespace(text).trim())),
+            _ if self.options.collapse_whitespace => {
+                self.out.push_str(&escape_text(&collapse_whitespace(text)))
+            }
+            _ => self.out.push_str(&escape_text(text)),
+        }
+    }
+
+    /// Whether `node` adds anything to the output
+    fn is_written<N: MarkupNode>(&self, node: &N) -> bool {
+        match node.markup() {
+            Markup::Element(_) => true,
+            Markup::Text(text) => !text.trim().is_empty(),
+            Markup::Comment(_) => self.options.comments,
+        }
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(&self.options.indent.repeat(depth));
+    }
+}
+
+/// Whether an attribute value means the same without quotes. Interpolations are always quoted,
+/// since their value is only known at runtime.
+fn can_be_unquoted(value: &str) -> bool {
+    !value.contains("${")
+        && !value.ends_with('/')
+        && !value
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || "\"'=<>`".contains(c))
+}
+
+impl MarkupNode for Node {
+    fn markup(&self) -> Markup<'_, Element> {
+        match self {
+            Node::Element(element) => Markup::Element(element),
+            Node::Text(text) => Markup::Text(&text.text),
+            Node::Comment(comment) => Markup::Comment(&comment.comment),
+        }
+    }
+}
+
+impl MarkupElement for Element {
+    fn tag_name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    fn is_self_closing(&self) -> bool {
+        self.variant == ElementVariant::Void
+    }
+}
+
+impl Dom {
+    /// Writes the root nodes as markup.
+    pub fn to_markup(&self, options: &SerializeOptions) -> String {
+        nodes_to_markup(&self.children, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(html: &str, options: &SerializeOptions) -> String {
+        Dom::parse(html).unwrap().to_markup(options)
+    }
+
+    /// Asserts that `markup` parses back to the tree of `html`
+    fn assert_same_tree(markup: &str, html: &str) {
+        let options = SerializeOptions::default();
+        assert_eq!(serialize(markup, &options), serialize(html, &options));
+    }
+
+    #[test]
+    fn serialize_modes() {
+        let html = "<div class=\"a b\" id=\"x\">\n  <p title=\"\">Hello\n    <b>world</b></p>\n  <br/>\n  <pre>  keep\n  this </pre>\n</div>";
+
+        assert_eq!(
+            serialize(html, &SerializeOptions::default()),
+            "<div class=\"a b\" id=\"x\"><p title=\"\">Hello\n    <b>world</b></p><br><pre>  keep\n  this </pre></div>"
+        );
+        assert_eq!(
+            serialize(html, &SerializeOptions::minify()),
+            "<div class=\"a b\" id=x><p title>Hello\n    <b>world</b></p><br><pre>  keep\n  this </pre></div>"
+        );
+        assert_eq!(
+            serialize(html, &SerializeOptions::pretty()),
+            "<div class=\"a b\" id=\"x\">\n  <p title=\"\">\n    Hello\n    <b>world</b>\n  </p>\n  <br>\n  <pre>  keep\n  this </pre>\n</div>"
+        );
+    }
+
+    #[test]
+    fn serialize_special_content() {
+        let minify = SerializeOptions::minify();
+        assert_eq!(
+            serialize("<script>if (a < b) {}</script>", &minify),
+            "<script>if (a < b) {}</script>"
+        );
+        assert_eq!(
+            serialize("<svg><rect x=\"1\"></rect><path d=\"M0/\"/></svg>", &minify),
+            "<svg><rect x=1 /><path d=\"M0/\"/></svg>"
+        );
+        let svg = "<svg viewBox=\"0 0 10 10\"><circle r=\"5\"/><rect width=\"2\"/></svg>";
+        let minified = serialize(svg, &minify);
+        assert_eq!(
+            minified,
+            "<svg viewBox=\"0 0 10 10\"><circle r=5 /><rect width=2 /></svg>"
+        );
+        assert_same_tree(&minified, svg);
+        // An unquoted value still ends before `/>` when parsing
+        assert_same_tree("<img src=a.png/>", "<img src=\"a.png\"/>");
+        let collapse = SerializeOptions {
+            collapse_whitespace: true,
+            ..SerializeOptions::minify()
+        };
+        assert_eq!(
+            serialize("<p class=\"${ a  b }\">${ x  }  &amp;  y</p>", &collapse),
+            "<p class=\"${ a  b }\">${ x  } &amp; y</p>"
+        );
+        assert_eq!(
+            serialize("<p>a<!-- note --></p>", &SerializeOptions::default()),
+            "<p>a</p>"
+        );
+        let comments = SerializeOptions {
+            comments: true,
+            ..SerializeOptions::default()
+        };
+        assert_eq!(
+            serialize("<p>a<!-- note --></p>", &comments),
+            "<p>a<!--note--></p>"
+        );
+    }
+}
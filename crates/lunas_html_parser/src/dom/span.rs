@@ -1,6 +1,9 @@
 use serde::Serialize;
 
 /// Span of the information in the parsed source.
+///
+/// Lines and columns are 1-based, columns are counted in characters and offsets are byte
+/// offsets into the parsed input. `end_*` points just past the last character.
 #[derive(Debug, Default, Clone, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceSpan {
@@ -9,4 +12,39 @@ pub struct SourceSpan {
     pub end_line: usize,
     pub start_column: usize,
     pub end_column: usize,
+    pub start_offset: usize,
+    pub end_offset: usize,
+}
+
+impl SourceSpan {
+    pub(crate) fn from_pest(span: pest::Span) -> Self {
+        let (start_line, start_column) = span.start_pos().line_col();
+        let (end_line, end_column) = span.end_pos().line_col();
+        Self {
+            text: span.as_str().to_string(),
+            start_line,
+            end_line,
+            start_column,
+            end_column,
+            start_offset: span.start(),
+            end_offset: span.end(),
+        }
+    }
+
+    /// Like [`SourceSpan::from_pest`], without the whitespace around the spanned text.
+    pub(crate) fn from_pest_trimmed(span: pest::Span) -> Self {
+        let text = span.as_str();
+        let start = text.len() - text.trim_start().len();
+        let end = start + text.trim().len();
+        Self::from_pest(span.get(start..end).unwrap_or(span))
+    }
+}
+
+/// Spans of an attribute's key and, if it has one, its value.
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributeSpan {
+    pub key: SourceSpan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<SourceSpan>,
 }
@@ -0,0 +1,191 @@
+//! Traversal and editing of node trees.
+//!
+//! The traits are generic over the node type so that trees derived from the parsed dom, like
+//! the one of the generator, can be walked and edited the same way.
+
+use super::element::Element;
+use super::node::Node;
+use super::Dom;
+
+/// What a walk does after a visitor entered a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitControl {
+    /// Visit the children of the node
+    Continue,
+    /// Go on with the next sibling, the node is still left
+    SkipChildren,
+    /// End the walk, no more node is entered or left
+    Stop,
+}
+
+/// Read-only pass over a tree. `enter` is called before the children of a node are visited and
+/// `leave` after them.
+pub trait Visitor<'a, N> {
+    fn enter(&mut self, _node: &'a N) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave(&mut self, _node: &'a N) {}
+}
+
+/// Pass that may edit the tree while walking it.
+///
+/// Changes made in `enter` to the children of a node are seen by the walk, which visits the
+/// children as they are once `enter` returns.
+pub trait VisitorMut<N> {
+    fn enter(&mut self, _node: &mut N) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave(&mut self, _node: &mut N) {}
+}
+
+/// A node of a tree, whose element nodes have children.
+pub trait TreeNode: Sized {
+    type Element: ParentNode<Node = Self>;
+
+    fn as_element(&self) -> Option<&Self::Element>;
+
+    fn as_element_mut(&mut self) -> Option<&mut Self::Element>;
+
+    /// Walks this node and its descendants in document order.
+    fn walk<'a, V: Visitor<'a, Self>>(&'a self, visitor: &mut V) -> VisitControl {
+        match visitor.enter(self) {
+            VisitControl::Stop => return VisitControl::Stop,
+            VisitControl::SkipChildren => (),
+            VisitControl::Continue => {
+                let children = self.as_element().map_or(&[][..], |e| e.child_nodes());
+                for child in children {
+                    if child.walk(visitor) == VisitControl::Stop {
+                        return VisitControl::Stop;
+                    }
+                }
+            }
+        }
+        visitor.leave(self);
+        VisitControl::Continue
+    }
+
+    /// Like [`TreeNode::walk`], with a visitor that can edit the nodes.
+    fn walk_mut<V: VisitorMut<Self>>(&mut self, visitor: &mut V) -> VisitControl {
+        match visitor.enter(self) {
+            VisitControl::Stop => return VisitControl::Stop,
+            VisitControl::SkipChildren => (),
+            VisitControl::Continue => {
+                if let Some(element) = self.as_element_mut() {
+                    for child in element.child_nodes_mut() {
+                        if child.walk_mut(visitor) == VisitControl::Stop {
+                            return VisitControl::Stop;
+                        }
+                    }
+                }
+            }
+        }
+        visitor.leave(self);
+        VisitControl::Continue
+    }
+}
+
+/// An element of a tree, with helpers to edit its children without index arithmetic.
+pub trait ParentNode: Sized {
+    type Node: TreeNode<Element = Self>;
+
+    fn child_nodes(&self) -> &[Self::Node];
+
+    fn child_nodes_mut(&mut self) -> &mut Vec<Self::Node>;
+
+    /// Turns the element into a node that can be added to a tree.
+    fn into_node(self) -> Self::Node;
+
+    /// Inserts `node` before the child at `index`, or after the last child when `index` is
+    /// past the end.
+    fn insert_child(&mut self, index: usize, node: Self::Node) {
+        let children = self.child_nodes_mut();
+        children.insert(index.min(children.len()), node);
+    }
+
+    /// Removes the child at `index` and returns it.
+    fn detach_child(&mut self, index: usize) -> Option<Self::Node> {
+        let children = self.child_nodes_mut();
+        match index < children.len() {
+            true => Some(children.remove(index)),
+            false => None,
+        }
+    }
+
+    /// Puts `node` in place of the child at `index` and returns the replaced child. Nothing
+    /// changes when there is no child at `index`.
+    fn replace_child(&mut self, index: usize, node: Self::Node) -> Option<Self::Node> {
+        self.child_nodes_mut()
+            .get_mut(index)
+            .map(|child| std::mem::replace(child, node))
+    }
+
+    /// Moves the child at `index` into `wrapper`, after its existing children, and puts the
+    /// wrapper in its place. Returns the wrapper node, or `None` when there is no child at
+    /// `index`.
+    fn wrap_child(&mut self, index: usize, wrapper: Self) -> Option<&mut Self::Node> {
+        let child = self.child_nodes_mut().get_mut(index)?;
+        let wrapped = std::mem::replace(child, wrapper.into_node());
+        if let Some(wrapper) = child.as_element_mut() {
+            wrapper.child_nodes_mut().push(wrapped);
+        }
+        Some(child)
+    }
+}
+
+impl TreeNode for Node {
+    type Element = Element;
+
+    fn as_element(&self) -> Option<&Element> {
+        match self {
+            Node::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match self {
+            Node::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+}
+
+impl ParentNode for Element {
+    type Node = Node;
+
+    fn child_nodes(&self) -> &[Node] {
+        &self.children
+    }
+
+    fn child_nodes_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.children
+    }
+
+    fn into_node(self) -> Node {
+        Node::Element(self)
+    }
+}
+
+impl Dom {
+    /// Walks the root nodes and their descendants in document order.
+    pub fn walk<'a, V: Visitor<'a, Node>>(&'a self, visitor: &mut V) -> VisitControl {
+        for node in &self.children {
+            if node.walk(visitor) == VisitControl::Stop {
+                return VisitControl::Stop;
+            }
+        }
+        VisitControl::Continue
+    }
+
+    /// Like [`Dom::walk`], with a visitor that can edit the nodes.
+    pub fn walk_mut<V: VisitorMut<Node>>(&mut self, visitor: &mut V) -> VisitControl {
+        for node in &mut self.children {
+            if node.walk_mut(visitor) == VisitControl::Stop {
+                return VisitControl::Stop;
+            }
+        }
+        VisitControl::Continue
+    }
+}
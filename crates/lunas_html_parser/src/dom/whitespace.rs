@@ -0,0 +1,138 @@
+//! Whitespace significance of text.
+//!
+//! Text inside `<pre>`, `<textarea>`, `<listing>` and `<code>`, or inside an element styled with
+//! a preserving `white-space` value, is kept exactly as written. Elsewhere whitespace only
+//! separates words, so it may be collapsed.
+
+use super::element::{Attributes, Element, Namespace};
+use super::element::{LEADING_NEWLINE_ELEMENTS, WHITESPACE_PRESERVING_ELEMENTS};
+use super::interpolation::map_literals;
+use super::node::Node;
+
+/// Whether the text of an element keeps its whitespace. The descendants of such an element keep
+/// it as well.
+pub fn preserves_whitespace(name: &str, namespace: Namespace, attributes: &Attributes) -> bool {
+    let is_preserving_element = namespace == Namespace::Html
+        && WHITESPACE_PRESERVING_ELEMENTS.contains(&name.to_ascii_lowercase().as_str());
+    is_preserving_element
+        || attributes
+            .get("style")
+            .and_then(|style| style.as_deref())
+            .is_some_and(has_preserving_white_space)
+}
+
+/// Whether an inline style sets `white-space` to a value that keeps spaces or line breaks.
+fn has_preserving_white_space(style: &str) -> bool {
+    style.split(';').any(|declaration| {
+        let (property, value) = match declaration.split_once(':') {
+            Some(declaration) => declaration,
+            None => return false,
+        };
+        let value = value.trim().to_ascii_lowercase();
+        match property.trim().to_ascii_lowercase().as_str() {
+            "white-space" => ["pre", "pre-wrap", "pre-line", "break-spaces"]
+                .iter()
+                .any(|preserving| value.starts_with(preserving)),
+            "white-space-collapse" => value != "collapse",
+            _ => false,
+        }
+    })
+}
+
+/// Replaces runs of HTML whitespace with a single space, outside of interpolations.
+pub fn collapse_whitespace(text: &str) -> String {
+    map_literals(text, |literal| {
+        let mut collapsed = String::with_capacity(literal.len());
+        let mut in_whitespace = false;
+        for c in literal.chars() {
+            match c.is_ascii_whitespace() {
+                true if in_whitespace => (),
+                true => {
+                    in_whitespace = true;
+                    collapsed.push(' ');
+                }
+                false => {
+                    in_whitespace = false;
+                    collapsed.push(c);
+                }
+            }
+        }
+        collapsed
+    })
+}
+
+/// Whether a newline right after the start tag of an element is dropped by HTML parsers
+pub fn ignores_leading_newline(name: &str, namespace: Namespace) -> bool {
+    namespace == Namespace::Html
+        && LEADING_NEWLINE_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Drops the newline that follows the start tag of `<pre>`, `<textarea>` and `<listing>`, as
+/// browsers do.
+pub(crate) fn drop_leading_newline(element: &mut Element) {
+    if !ignores_leading_newline(&element.name, element.namespace) {
+        return;
+    }
+    if let Some(Node::Text(text)) = element.children.first_mut() {
+        let content = text.text.as_str();
+        let content = content
+            .strip_prefix("\r\n")
+            .or_else(|| content.strip_prefix('\n'))
+            .unwrap_or(content);
+        text.text = content.to_string();
+        if text.text.is_empty() {
+            element.children.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::serializer::SerializeOptions;
+    use crate::dom::Dom;
+
+    fn texts(element: &Element) -> Vec<&str> {
+        element
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn whitespace_significant_elements() {
+        let html = "<div>\n  <pre>\n\n  fn main() {}\n</pre>\n  <textarea>\n  a \n</textarea><code> <b> </b> </code><p style=\"color: red; white-space: pre-wrap\"> </p><p> </p></div>";
+        for dom in [Dom::parse(html).unwrap(), Dom::parse_with_recovery(html)] {
+            let div = dom.children[0].element().unwrap();
+            let element = |index: usize| div.children[index].element().unwrap();
+            assert_eq!(div.children.len(), 5);
+            assert_eq!(texts(element(0)), vec!["\n  fn main() {}\n"]);
+            assert_eq!(texts(element(1)), vec!["  a \n"]);
+            assert_eq!(texts(element(2)), vec![" ", " "]);
+            assert_eq!(texts(element(2).children[1].element().unwrap()), vec![" "]);
+            assert_eq!(texts(element(3)), vec![" "]);
+            assert!(element(4).children.is_empty());
+
+            let collapse = SerializeOptions {
+                collapse_whitespace: true,
+                ..SerializeOptions::minify()
+            };
+            assert_eq!(
+                dom.to_markup(&collapse),
+                "<div><pre>\n\n  fn main() {}\n</pre><textarea>  a \n</textarea><code> <b> </b> </code><p style=\"color: red; white-space: pre-wrap\"> </p><p></p></div>"
+            );
+        }
+    }
+
+    #[test]
+    fn collapse_outside_of_interpolations() {
+        assert_eq!(
+            collapse_whitespace("a \n\t b ${ x  +  y }  c"),
+            "a b ${ x  +  y } c"
+        );
+    }
+}
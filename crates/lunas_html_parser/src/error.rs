@@ -1,3 +1,5 @@
+use crate::dom::span::SourceSpan;
+use serde::Serialize;
 use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
@@ -13,3 +15,22 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// A problem found while building the dom that did not stop the parsing, e.g. a duplicate
+/// attribute. The offending part of the source is dropped from the tree.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DomError {
+    pub message: String,
+    pub source_span: SourceSpan,
+}
+
+impl std::fmt::Display for DomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.source_span.start_line, self.source_span.start_column, self.message
+        )
+    }
+}
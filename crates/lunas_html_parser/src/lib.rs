@@ -16,12 +16,17 @@
 //! - Removes comments
 //! - Removes dangling elements
 //! - Iterate over all nodes in the dom tree
+//! - Walk and edit the tree with `Visitor` and `VisitorMut` passes
+//! - Recover from unclosed and mismatched tags with `Dom::parse_with_recovery`
+//! - Select elements with CSS selectors: `dom.select("ul > li.active[data-id]")`
+//! - Write the tree back as minified, pretty or source-preserving markup with `Dom::to_markup`
+//! - Keep the exact text of `<pre>`, `<textarea>`, `<code>` and `white-space: pre` elements
 //!
 //! ## What is it not
 //!
 //! - It's not a high-performance browser-grade parser
 //! - It's not suitable for html validation
-//! - It's not a parser that includes element selection or dom manipulation
+//! - It's not a library for dom manipulation beyond simple tree edits
 //!
 //! If your requirements matches any of the above, then you're most likely looking for one of the crates below:
 //!
@@ -102,9 +107,24 @@ mod grammar;
 
 use grammar::Rule;
 
-pub use crate::dom::element::{Element, ElementVariant};
-pub use crate::dom::node::Node;
+pub use crate::dom::character_reference::decode_character_references;
+pub use crate::dom::element::{
+    Attributes, Element, ElementVariant, Namespace, ESCAPABLE_RAW_TEXT_ELEMENTS,
+    LEADING_NEWLINE_ELEMENTS, RAW_TEXT_ELEMENTS, VOID_ELEMENTS, WHITESPACE_PRESERVING_ELEMENTS,
+};
+pub use crate::dom::escape::{escape_attribute_value, escape_template_literal, escape_text};
+pub use crate::dom::interpolation::{find_interpolation_end, text_segments, TextSegment};
+pub use crate::dom::node::{Comment, Node, Text};
+pub use crate::dom::serializer::{
+    nodes_to_markup, Markup, MarkupElement, MarkupNode, SerializeMode, SerializeOptions,
+};
+pub use crate::dom::span::{AttributeSpan, SourceSpan};
+pub use crate::dom::visitor::{ParentNode, TreeNode, VisitControl, Visitor, VisitorMut};
+pub use crate::dom::whitespace::{
+    collapse_whitespace, ignores_leading_newline, preserves_whitespace,
+};
 pub use crate::dom::Dom;
 pub use crate::dom::DomVariant;
+pub use crate::error::DomError;
 pub use crate::error::Error;
 pub use crate::error::Result;
@@ -0,0 +1,285 @@
+use serde::Serialize;
+
+/// Stable identifiers for every kind of diagnostic the compiler can emit.
+pub mod codes {
+    /// The block structure of the file could not be parsed.
+    pub const SYNTAX: &str = "syntax";
+    /// A custom block was rejected by the plugin handling it.
+    pub const INVALID_CUSTOM_BLOCK: &str = "invalid-custom-block";
+    /// A language block appears more than once.
+    pub const DUPLICATE_BLOCK: &str = "duplicate-block";
+    /// The mandatory `html` block is missing.
+    pub const MISSING_HTML_BLOCK: &str = "missing-html-block";
+    /// A block attribute has a value the compiler does not support.
+    pub const INVALID_BLOCK_ATTRIBUTE: &str = "invalid-block-attribute";
+    /// A `@directive` line could not be understood.
+    pub const INVALID_META_DATA: &str = "invalid-meta-data";
+    /// The `html` block is not valid markup.
+    pub const INVALID_HTML: &str = "invalid-html";
+    /// The `script` block is not valid TypeScript.
+    pub const INVALID_SCRIPT: &str = "invalid-script";
+    /// The template uses a directive or binding that cannot be compiled.
+    pub const INVALID_TEMPLATE: &str = "invalid-template";
+    /// The `style` block cannot be scoped to the component.
+    pub const INVALID_STYLE: &str = "invalid-style";
+    /// The compile target is not one the compiler can build for.
+    pub const INVALID_TARGET: &str = "invalid-target";
+    /// Code generation failed for a reason not covered by the codes above.
+    pub const CODEGEN: &str = "codegen";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A position in the original `.lun` source.
+///
+/// `offset` is a byte offset, `line` and `column` are 1-based and `column` is counted in
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourcePosition {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for SourcePosition {
+    /// The start of the file.
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+/// A half-open range (`start` inclusive, `end` exclusive) in the original `.lun` source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SourceRange {
+    pub start: SourcePosition,
+    pub end: SourcePosition,
+}
+
+impl From<&lunas_html_parser::SourceSpan> for SourceRange {
+    fn from(span: &lunas_html_parser::SourceSpan) -> Self {
+        Self {
+            start: SourcePosition {
+                offset: span.start_offset,
+                line: span.start_line,
+                column: span.start_column,
+            },
+            end: SourcePosition {
+                offset: span.end_offset,
+                line: span.end_line,
+                column: span.end_column,
+            },
+        }
+    }
+}
+
+/// Where the content of a block or directive sits in the original `.lun` source.
+///
+/// Block contents are de-indented and trimmed, so offsets into them do not match the file;
+/// [`BlockLocation::position`] translates them back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BlockLocation {
+    /// Byte offset of the first content character.
+    pub offset: usize,
+    /// 1-based line of the first content character.
+    pub line: usize,
+    /// Width in bytes of the indentation stripped from every content line.
+    pub indent: usize,
+    /// Position at which each line of the content starts.
+    pub line_starts: Vec<SourcePosition>,
+}
+
+impl BlockLocation {
+    /// Creates a location from the offsets of each content line relative to the text the item
+    /// was parsed from. [`BlockLocation::relocate`] turns it into a location in the file.
+    pub(crate) fn from_line_offsets(indent: usize, line_offsets: Vec<usize>) -> Self {
+        Self {
+            offset: line_offsets.first().copied().unwrap_or_default(),
+            line: 1,
+            indent,
+            line_starts: line_offsets
+                .into_iter()
+                .map(|offset| SourcePosition {
+                    offset,
+                    ..SourcePosition::default()
+                })
+                .collect(),
+        }
+    }
+
+    /// Shifts a location built by [`BlockLocation::from_line_offsets`] by `base`, the offset
+    /// of the parsed item in the file indexed by `index`.
+    pub(crate) fn relocate(&mut self, base: usize, index: &LineIndex) {
+        for start in &mut self.line_starts {
+            *start = index.position(base + start.offset);
+        }
+        let first = match self.line_starts.first() {
+            Some(first) => *first,
+            None => index.position(base + self.offset),
+        };
+        self.offset = first.offset;
+        self.line = first.line;
+    }
+
+    /// Maps a byte offset in `content`, the text this location describes, to the file.
+    pub fn position(&self, content: &str, offset: usize) -> SourcePosition {
+        let offset = offset.min(content.len());
+        let line = content[..offset].matches('\n').count();
+        let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+        match self.line_starts.get(line) {
+            Some(start) => SourcePosition {
+                offset: start.offset + offset - line_start,
+                line: start.line,
+                column: start.column + content[line_start..offset].chars().count(),
+            },
+            None => SourcePosition {
+                offset: self.offset,
+                line: self.line,
+                column: 1,
+            },
+        }
+    }
+
+    pub fn range(&self, content: &str, start: usize, end: usize) -> SourceRange {
+        SourceRange {
+            start: self.position(content, start),
+            end: self.position(content, end),
+        }
+    }
+}
+
+/// A structured compiler message that tooling can point at a location in the `.lun` file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<SourceRange>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: code.to_string(),
+            message: message.into(),
+            range: None,
+        }
+    }
+
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: code.to_string(),
+            message: message.into(),
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: SourceRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Attaches `range` only when the diagnostic does not carry a more precise one already.
+    pub fn or_range(mut self, range: SourceRange) -> Self {
+        if self.range.is_none() {
+            self.range = Some(range);
+        }
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.range {
+            Some(range) => write!(
+                f,
+                "{}:{}: {} [{}]",
+                range.start.line, range.start.column, self.message, self.code
+            ),
+            None => write!(f, "{} [{}]", self.message, self.code),
+        }
+    }
+}
+
+/// Converts byte offsets of a source text into line/column positions.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> SourcePosition {
+        let offset = offset.min(self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = self.source[line_start..offset].chars().count();
+        SourcePosition {
+            offset,
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> SourceRange {
+        SourceRange {
+            start: self.position(start),
+            end: self.position(end),
+        }
+    }
+
+    /// Returns the range from `start` up to (but excluding) the end of its line.
+    pub fn line_range(&self, start: usize) -> SourceRange {
+        let end = self.source[start.min(self.source.len())..]
+            .find(['\r', '\n'])
+            .map(|i| start + i)
+            .unwrap_or(self.source.len());
+        self.range(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index_position() {
+        let index = LineIndex::new("html:\n  <div></div>\n");
+        assert_eq!(
+            index.position(8),
+            SourcePosition {
+                offset: 8,
+                line: 2,
+                column: 3
+            }
+        );
+        assert_eq!(index.line_range(0).end.column, 6);
+    }
+}
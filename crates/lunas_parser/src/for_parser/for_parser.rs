@@ -9,7 +9,8 @@ use std::result::Result;
 
 use swc_common::{sync::Lrc, FileName, SourceMap, SourceMapper, Span, Spanned};
 use swc_ecma_ast::{
-    CallExpr, Callee, Expr, ForHead, ForInStmt, ForOfStmt, MemberProp, ModuleItem, Stmt,
+    CallExpr, Callee, Expr, ForHead, ForInStmt, ForOfStmt, MemberProp, Module, ModuleItem,
+    ObjectPatProp, Pat, Stmt,
 };
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
 
@@ -34,26 +35,8 @@ impl ParsedFor {
     /// Parse strings like `"const [idx, val] of data.entries()"` or `"let key in mapObj"`.
     pub fn parse(input: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
         let src = input.trim();
-        let wrapped = format!("for({}){{}}", src);
-
         let cm: Lrc<SourceMap> = Default::default();
-        let fm = cm.new_source_file(
-            FileName::Custom("for_stmt.js".into()).into(),
-            wrapped.clone(),
-        );
-        let lexer = Lexer::new(
-            Syntax::Es(Default::default()),
-            Default::default(),
-            StringInput::from(&*fm),
-            None,
-        );
-        let mut parser = Parser::new_from(lexer);
-        let module = parser.parse_module().map_err(|e| {
-            Box::<dyn Error + Send + Sync>::from(format!(
-                "SWC parse error for input '{}': {:?}",
-                src, e
-            ))
-        })?;
+        let module = parse_for_statement(&cm, src)?;
 
         let module_item = module.body.into_iter().next().unwrap_or_else(|| {
             panic!("Empty input, no statements: '{}'", src);
@@ -161,6 +144,23 @@ impl ParsedFor {
         })
     }
 
+    /// Names the pattern binds for each item, e.g. `["i", "row"]` for `[i, row]`.
+    pub fn bindings(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let cm: Lrc<SourceMap> = Default::default();
+        let module = parse_for_statement(&cm, &format!("const {} of []", self.raw))?;
+        let mut names = vec![];
+        if let Some(ModuleItem::Stmt(Stmt::ForOf(ForOfStmt {
+            left: ForHead::VarDecl(var_decl),
+            ..
+        }))) = module.body.first()
+        {
+            for decl in &var_decl.decls {
+                collect_bindings(&decl.name, &mut names);
+            }
+        }
+        Ok(names)
+    }
+
     pub fn clone_with_new_iterable(&self, new_iterable: &str) -> Self {
         ParsedFor {
             kind: self.kind.clone(),
@@ -182,6 +182,52 @@ impl ParsedFor {
     }
 }
 
+fn parse_for_statement(
+    cm: &Lrc<SourceMap>,
+    src: &str,
+) -> Result<Module, Box<dyn Error + Send + Sync>> {
+    let fm = cm.new_source_file(
+        FileName::Custom("for_stmt.js".into()).into(),
+        format!("for({}){{}}", src),
+    );
+    let lexer = Lexer::new(
+        Syntax::Es(Default::default()),
+        Default::default(),
+        StringInput::from(&*fm),
+        None,
+    );
+    let mut parser = Parser::new_from(lexer);
+    parser.parse_module().map_err(|e| {
+        Box::<dyn Error + Send + Sync>::from(format!(
+            "SWC parse error for input '{}': {:?}",
+            src, e
+        ))
+    })
+}
+
+fn collect_bindings(pat: &Pat, names: &mut Vec<String>) {
+    match pat {
+        Pat::Ident(ident) => names.push(ident.sym.to_string()),
+        Pat::Array(array) => {
+            for elem in array.elems.iter().flatten() {
+                collect_bindings(elem, names);
+            }
+        }
+        Pat::Object(object) => {
+            for prop in &object.props {
+                match prop {
+                    ObjectPatProp::KeyValue(key_value) => collect_bindings(&key_value.value, names),
+                    ObjectPatProp::Assign(assign) => names.push(assign.key.sym.to_string()),
+                    ObjectPatProp::Rest(rest) => collect_bindings(&rest.arg, names),
+                }
+            }
+        }
+        Pat::Rest(rest) => collect_bindings(&rest.arg, names),
+        Pat::Assign(assign) => collect_bindings(&assign.left, names),
+        Pat::Invalid(_) | Pat::Expr(_) => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{ForKind, ParsedFor};
@@ -238,6 +284,17 @@ mod tests {
         valid_for_in_destructuring: "const [i, v] in data.entries()" => ParsedFor { kind: ForKind::In, iterable: "data.entries()".into(), raw: "[i, v]".into() },
     }
 
+    #[test]
+    fn bindings_of_patterns() {
+        let bindings = |input: &str| ParsedFor::parse(input).unwrap().bindings().unwrap();
+        assert_eq!(bindings("row of rows"), vec!["row"]);
+        assert_eq!(bindings("[i, row] of rows.entries()"), vec!["i", "row"]);
+        assert_eq!(
+            bindings("{ id, name: label, tags: [first] = [], ...rest } of rows"),
+            vec!["id", "label", "first", "rest"]
+        );
+    }
+
     generate_for_error_tests! {
         invalid_1: "for foo bar",
         invalid_2: "let [a] of",
@@ -1,3 +1,4 @@
+pub mod diagnostics;
 mod for_parser;
 mod parse2;
 mod parser1;
@@ -6,27 +7,190 @@ pub mod structs;
 mod swc_parser;
 mod ts_to_js;
 
+pub use diagnostics::{BlockLocation, Diagnostic, Severity, SourcePosition, SourceRange};
 use parse2::parse2;
 use parser1::parse1;
 pub use structs::detailed_blocks::DetailedBlock;
-pub use structs::detailed_meta_data::{DetailedMetaData, PropsInput, UseComponentStatement};
+pub use structs::detailed_language_blocks::CustomBlock;
+pub use structs::detailed_meta_data::{
+    ComponentImport, DetailedMetaData, InputType, PropsInput, UseComponentStatement,
+};
 
-pub fn parse_lunas_file(input: &str) -> Result<DetailedBlock, String> {
+/// Parses a `.lun` file. On failure, every problem found in the file is returned at once.
+pub fn parse_lunas_file(input: &str) -> Result<DetailedBlock, Vec<Diagnostic>> {
     let new_input = format!("{}\n", input);
-    let parsed_items = match parse1(&new_input) {
-        Ok(r) => {
-            let (_, parsed_items) = r;
-            Ok(parsed_items)
-        }
-        Err(e) => Err(e.to_string()),
-    }?;
-
-    let detailed_block = match parse2(parsed_items) {
-        Ok(r) => r,
-        Err(e) => return Err(format!("{:?}", e)),
-    };
+    let (parsed_items, mut diagnostics) = parse1(&new_input);
 
-    Ok(detailed_block)
+    match parse2(parsed_items) {
+        Ok(detailed_block) if diagnostics.is_empty() => Ok(detailed_block),
+        Ok(_) => Err(diagnostics),
+        Err(e) => {
+            diagnostics.extend(e);
+            diagnostics.sort_by_key(|d| d.range.map(|r| r.start.offset));
+            Err(diagnostics)
+        }
+    }
 }
 
 pub use for_parser::for_parser::ParsedFor;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::codes;
+
+    #[test]
+    fn test_duplicate_block_diagnostic_has_range() {
+        let input = "html:\n  <div></div>\n\nstyle:\n  div {}\nstyle:\n  p {}\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, codes::DUPLICATE_BLOCK);
+        let range = diagnostics[0].range.unwrap();
+        assert_eq!((range.start.line, range.start.column), (6, 1));
+    }
+
+    #[test]
+    fn test_reports_every_problem_in_one_pass() {
+        let input = "@foo bar\n@input 1x\nhtml:\n  <div></div>\n stray\nstyle:\n  a {}\nstyle:\n  b {}\n}\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        let lines = diagnostics
+            .iter()
+            .map(|d| (d.code.as_str(), d.range.unwrap().start.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (codes::INVALID_META_DATA, 1),
+                (codes::INVALID_META_DATA, 2),
+                (codes::SYNTAX, 5),
+                (codes::DUPLICATE_BLOCK, 8),
+                (codes::SYNTAX, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_attributes() {
+        let input = "html:\n  <div></div>\nstyle(scoped: true, lang: \"scss\"):\n  div {}\nscript(lang: js):\n  let a = 1\n";
+        let blocks = parse_lunas_file(input).unwrap().detailed_language_blocks;
+        assert_eq!(blocks.css_attributes.get("scoped").unwrap(), "true");
+        assert_eq!(blocks.css_lang(), "scss");
+        assert_eq!(blocks.js_attributes.get("lang").unwrap(), "js");
+        assert!(!blocks.collapses_whitespace());
+        assert!(blocks.scopes_css());
+        assert_eq!(blocks.js.unwrap().raw, "let a = 1");
+
+        let input = "html(whitespace: collapse):\n  <div></div>\n";
+        let blocks = parse_lunas_file(input).unwrap().detailed_language_blocks;
+        assert!(blocks.collapses_whitespace());
+
+        let input = "html(whitespace: trim):\n  <div></div>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics[0].code, codes::INVALID_BLOCK_ATTRIBUTE);
+
+        let input = "html:\n  <div></div>\nstyle(scoped: yes):\n  div {}\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics[0].code, codes::INVALID_BLOCK_ATTRIBUTE);
+    }
+
+    #[test]
+    fn test_custom_blocks_are_kept() {
+        let input = "docs(lang: md):\n  # Button\nhtml:\n  <div></div>\n";
+        let blocks = parse_lunas_file(input).unwrap().detailed_language_blocks;
+        assert_eq!(blocks.custom_blocks.len(), 1);
+        assert_eq!(blocks.custom_blocks[0].name, "docs");
+        assert_eq!(blocks.custom_blocks[0].content, "# Button");
+        assert_eq!(
+            blocks.custom_blocks[0].attributes.get("lang").unwrap(),
+            "md"
+        );
+    }
+
+    #[test]
+    fn test_unknown_directive_diagnostic() {
+        let input = "@foo bar\nhtml:\n  <div></div>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics[0].code, codes::INVALID_META_DATA);
+        assert_eq!(diagnostics[0].range.unwrap().start.line, 1);
+    }
+
+    #[test]
+    fn test_script_syntax_error_diagnostic() {
+        let input = "html:\n  <div></div>\nscript:\n  let a = 1;\n  let b = ;\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, codes::INVALID_SCRIPT);
+        let range = diagnostics[0].range.unwrap();
+        assert_eq!((range.start.line, range.start.column), (5, 11));
+    }
+
+    #[test]
+    fn test_block_locations() {
+        let input = "@input name: string\nhtml:\n\n    <div>\n\n      {name}\n    </div>\nstyle:\n  div {}\n";
+        let block = parse_lunas_file(input).unwrap();
+        let blocks = &block.detailed_language_blocks;
+        let html = &blocks.html_location;
+        assert_eq!((html.offset, html.line, html.indent), (31, 4, 4));
+        let content = "<div>\n\n  {name}\n</div>";
+        let name = content.find('{').unwrap();
+        let position = html.position(content, name);
+        assert_eq!(&input[position.offset..position.offset + 6], "{name}");
+        assert_eq!((position.line, position.column), (6, 7));
+        let css = blocks.css_location.as_ref().unwrap();
+        assert_eq!((css.line, css.indent), (9, 2));
+    }
+
+    #[test]
+    fn test_crlf_and_whitespace_only_lines() {
+        let input = "html:\r\n  <div>\r\n \r\n      \r\n    <p>Hi</p>\r\n  </div>\r\nscript:\r\n\tlet a = 1;\r\n\r\n\tlet b = 2;\r\n";
+        let block = parse_lunas_file(input).unwrap();
+        let blocks = &block.detailed_language_blocks;
+        assert_eq!(blocks.js.as_ref().unwrap().raw, "let a = 1;\nlet b = 2;");
+        assert_eq!(blocks.js_location.as_ref().unwrap().indent, 1);
+        let p = blocks
+            .html_location
+            .position("<div>\n\n\n  <p>Hi</p>\n</div>", 10);
+        assert_eq!(&input[p.offset..p.offset + 3], "<p>");
+    }
+
+    #[test]
+    fn test_inconsistent_indentation_diagnostic() {
+        let input = "html:\n    <div></div>\n  <p></p>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, codes::SYNTAX);
+        assert_eq!(diagnostics[0].range.unwrap().start.line, 3);
+        assert!(diagnostics[0]
+            .message
+            .contains("indented with 4 spaces, but this line is indented with 2 spaces"));
+
+        let input = "html:\n\t<div></div>\n    <p></p>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert!(diagnostics[0]
+            .message
+            .contains("indented with 1 tab, but this line is indented with 4 spaces"));
+    }
+
+    #[test]
+    fn test_duplicate_attribute_diagnostic() {
+        let input = "html:\n  <div class=\"a\" class=\"b\"></div>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics[0].code, codes::INVALID_HTML);
+        let range = diagnostics[0].range.unwrap();
+        assert_eq!((range.start.line, range.start.column), (2, 18));
+    }
+
+    #[test]
+    fn test_unclosed_element_diagnostic() {
+        let input = "html:\n  <div>\n    <p>Hello\n  </div>\n";
+        let diagnostics = parse_lunas_file(input).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, codes::INVALID_HTML);
+        assert_eq!(
+            diagnostics[0].message,
+            "Unclosed element <p>: expected </p>"
+        );
+        let range = diagnostics[0].range.unwrap();
+        assert_eq!((range.start.line, range.start.column), (3, 5));
+    }
+}
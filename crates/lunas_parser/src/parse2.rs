@@ -1,16 +1,20 @@
 use std::collections::HashMap;
 
+use crate::diagnostics::{codes, BlockLocation, Diagnostic, SourceRange};
 use crate::structs::blocks::{LanguageBlock, ParsedItem};
 
 use crate::structs::detailed_blocks::DetailedBlock;
-use crate::structs::detailed_language_blocks::{DetailedLanguageBlocks, JsBlock};
+use crate::structs::detailed_language_blocks::{CustomBlock, DetailedLanguageBlocks, JsBlock};
 use crate::structs::detailed_meta_data::DetailedMetaData;
-use crate::swc_parser::parse_with_swc;
+use crate::swc_parser::{parse_with_swc, SwcError};
 use crate::ts_to_js::transform_ts_to_js;
 
-use lunas_html_parser::Dom;
+use lunas_html_parser::{Dom, Node, SourceSpan};
 
-pub fn parse2(input: Vec<ParsedItem>) -> Result<DetailedBlock, String> {
+/// Validates the items found by `parse1`. Every block and directive is checked even after a
+/// failure, so the returned diagnostics cover the whole file.
+pub fn parse2(input: Vec<ParsedItem>) -> Result<DetailedBlock, Vec<Diagnostic>> {
+    let mut diagnostics = vec![];
     let variant_a_values: Vec<LanguageBlock> = input
         .clone()
         .into_iter()
@@ -19,70 +23,262 @@ pub fn parse2(input: Vec<ParsedItem>) -> Result<DetailedBlock, String> {
             _ => None,
         })
         .collect();
-    let lang_blocks = parse_language_blocks(variant_a_values)?;
+    let lang_blocks = parse_language_blocks(variant_a_values, &mut diagnostics);
 
-    let detailed_meta_data = input
-        .into_iter()
-        .filter_map(|e| match e {
-            ParsedItem::MetaData(meta) => Some(meta),
-            _ => None,
-        })
-        .map(|e| DetailedMetaData::from_simple_meta_data(e))
-        .collect::<Result<Vec<_>, _>>()?;
+    let mut detailed_meta_data = vec![];
+    for meta in input.into_iter().filter_map(|e| match e {
+        ParsedItem::MetaData(meta) => Some(meta),
+        _ => None,
+    }) {
+        match DetailedMetaData::from_simple_meta_data(meta) {
+            Ok(meta) => detailed_meta_data.push(meta),
+            Err(e) => diagnostics.push(e),
+        }
+    }
 
-    Ok(DetailedBlock {
-        detailed_meta_data: detailed_meta_data,
-        detailed_language_blocks: lang_blocks,
-    })
+    match lang_blocks {
+        Some(lang_blocks) if diagnostics.is_empty() => Ok(DetailedBlock {
+            detailed_meta_data,
+            detailed_language_blocks: lang_blocks,
+        }),
+        _ => Err(diagnostics),
+    }
 }
 
-fn parse_language_blocks<'a>(blks: Vec<LanguageBlock>) -> Result<DetailedLanguageBlocks, String> {
-    let mut hm = HashMap::new();
+fn parse_language_blocks(
+    blks: Vec<LanguageBlock>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<DetailedLanguageBlocks> {
+    let mut hm: HashMap<&str, &LanguageBlock> = HashMap::new();
+    let mut custom_blocks = vec![];
     for block in &blks {
-        let language_name: &str = &block.language_name.as_str();
-        // if language_name is not one of 'html', 'style', 'script'
+        let language_name: &str = block.language_name.as_str();
+        // blocks other than 'html', 'style', 'script' are handed over to compiler plugins
         if language_name != "html" && language_name != "style" && language_name != "script" {
-            return Err("Invalid language name".to_string());
+            custom_blocks.push(CustomBlock {
+                name: block.language_name.clone(),
+                attributes: block.attributes.clone(),
+                content: block.content.clone(),
+                range: block.range,
+                location: block.location.clone(),
+            });
+            continue;
         }
         if hm.contains_key(language_name) {
-            return Err("Duplicate language name".to_string());
+            diagnostics.push(
+                Diagnostic::error(
+                    codes::DUPLICATE_BLOCK,
+                    format!("Duplicate language name `{}`", language_name),
+                )
+                .with_range(block.range),
+            );
+            continue;
         }
-        let content = block.content.clone();
 
-        hm.insert(language_name, content);
+        hm.insert(language_name, block);
     }
 
-    let html = hm.get("html");
-    if html == None {
-        return Err("Missing html block".to_string());
+    let parsed_js = match hm.get("script") {
+        Some(ts) => match script_to_js(ts).and_then(|(js, origins)| {
+            parse_with_swc(&js)
+                .map(|parsed| (js, origins, parsed))
+                .map_err(|e| script_error(ts, &e, false))
+        }) {
+            Ok((js, origins, parsed)) => {
+                let parsed_json = serde_json::to_value(&parsed).unwrap();
+                // `raw` starts at the first line with code
+                let skipped_lines = js[..js.len() - js.trim_start().len()].matches('\n').count();
+                let raw = js.trim();
+                let line_origins = origins
+                    .into_iter()
+                    .skip(skipped_lines)
+                    .take(raw.split('\n').count())
+                    .map(|offset| offset.map(|offset| ts.location.position(&ts.content, offset)))
+                    .collect();
+                Some(JsBlock {
+                    ast: parsed_json,
+                    raw: raw.into(),
+                    line_origins,
+                })
+            }
+            Err(e) => {
+                diagnostics.push(e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let html = match hm.get("html") {
+        Some(html) => html,
+        None => {
+            diagnostics.push(
+                Diagnostic::error(codes::MISSING_HTML_BLOCK, "Missing html block")
+                    .with_range(SourceRange::default()),
+            );
+            return None;
+        }
+    };
+    match html
+        .attributes
+        .get("whitespace")
+        .map(|value| value.as_str())
+    {
+        None | Some("preserve") | Some("collapse") => (),
+        Some(value) => diagnostics.push(
+            Diagnostic::error(
+                codes::INVALID_BLOCK_ATTRIBUTE,
+                format!(
+                    "Unsupported whitespace mode `{}`; expected `preserve` or `collapse`",
+                    value
+                ),
+            )
+            .with_range(html.range),
+        ),
     }
-    let parsed_html_dom_result = Dom::parse(html.unwrap());
-    match parsed_html_dom_result {
-        Ok(parsed_html) => {
-            let css = hm.get("style");
-            let ts = hm.get("script");
-            let parsed_js = match ts {
-                Some(ts) => {
-                    let js = transform_ts_to_js(ts).map_err(|e| e.to_string())?;
-                    let parsed = parse_with_swc(&js);
-                    let parsed_json = serde_json::to_value(&parsed).unwrap();
-                    Some(JsBlock {
-                        ast: parsed_json,
-                        raw: js.trim().into(),
-                    })
+    let parsed_html = match Dom::parse(&html.content) {
+        Ok(mut parsed_html) => {
+            relocate_spans(&mut parsed_html.children, &html.location, &html.content);
+            if !parsed_html.errors.is_empty() {
+                for error in &mut parsed_html.errors {
+                    relocate_span(&mut error.source_span, &html.location, &html.content);
+                    diagnostics.push(
+                        Diagnostic::error(codes::INVALID_HTML, error.message.clone())
+                            .with_range(SourceRange::from(&error.source_span)),
+                    );
                 }
-                None => None,
-            };
-            let str_css = match css {
-                Some(css) => Some(css.to_string()),
-                None => None,
-            };
-            Ok(DetailedLanguageBlocks {
-                dom: parsed_html,
-                css: str_css,
-                js: parsed_js,
-            })
+                return None;
+            }
+            parsed_html
+        }
+        Err(e) => {
+            // The recovering parser locates what is wrong, e.g. the element left unclosed
+            let mut recovered = Dom::parse_with_recovery(&html.content);
+            if recovered.errors.is_empty() {
+                diagnostics.push(
+                    Diagnostic::error(codes::INVALID_HTML, format!("Invalid html block: {}", e))
+                        .with_range(html.range),
+                );
+            }
+            for error in &mut recovered.errors {
+                relocate_span(&mut error.source_span, &html.location, &html.content);
+                diagnostics.push(
+                    Diagnostic::error(codes::INVALID_HTML, error.message.clone())
+                        .with_range(SourceRange::from(&error.source_span)),
+                );
+            }
+            return None;
+        }
+    };
+
+    if let Some(style) = hm.get("style") {
+        match style.attributes.get("scoped").map(|value| value.as_str()) {
+            None | Some("true") | Some("false") => (),
+            Some(value) => diagnostics.push(
+                Diagnostic::error(
+                    codes::INVALID_BLOCK_ATTRIBUTE,
+                    format!(
+                        "Unsupported scoped value `{}`; expected `true` or `false`",
+                        value
+                    ),
+                )
+                .with_range(style.range),
+            ),
         }
-        Err(_) => return Err("Invalid html block".to_string()),
     }
+
+    let str_css = hm.get("style").map(|css| css.content.to_string());
+    let attributes_of = |name: &str| {
+        hm.get(name)
+            .map(|block| block.attributes.clone())
+            .unwrap_or_default()
+    };
+    Some(DetailedLanguageBlocks {
+        dom: parsed_html,
+        css: str_css,
+        js: parsed_js,
+        html_attributes: attributes_of("html"),
+        css_attributes: attributes_of("style"),
+        js_attributes: attributes_of("script"),
+        custom_blocks,
+        html_range: html.range,
+        html_location: html.location.clone(),
+        css_location: hm.get("style").map(|css| css.location.clone()),
+        js_location: hm.get("script").map(|js| js.location.clone()),
+    })
+}
+
+/// Turns the spans of `nodes`, which are relative to the html block content, into positions in
+/// the `.lun` file.
+fn relocate_spans(nodes: &mut [Node], location: &BlockLocation, content: &str) {
+    let relocate = |span: &mut SourceSpan| relocate_span(span, location, content);
+    for node in nodes {
+        match node {
+            Node::Element(element) => {
+                relocate(&mut element.source_span);
+                for span in element.attribute_spans.values_mut() {
+                    relocate(&mut span.key);
+                    if let Some(value) = &mut span.value {
+                        relocate(value);
+                    }
+                }
+                relocate_spans(&mut element.children, location, content);
+            }
+            Node::Text(text) => relocate(&mut text.source_span),
+            Node::Comment(comment) => relocate(&mut comment.source_span),
+        }
+    }
+}
+
+fn relocate_span(span: &mut SourceSpan, location: &BlockLocation, content: &str) {
+    let range = location.range(content, span.start_offset, span.end_offset);
+    span.start_offset = range.start.offset;
+    span.start_line = range.start.line;
+    span.start_column = range.start.column;
+    span.end_offset = range.end.offset;
+    span.end_line = range.end.line;
+    span.end_column = range.end.column;
+}
+
+/// Returns the script block as JavaScript, transpiling it unless it is `script(lang: js):`,
+/// with the offset in the block content of the first token of each JavaScript line.
+fn script_to_js(block: &LanguageBlock) -> Result<(String, Vec<Option<usize>>), Diagnostic> {
+    match block.attributes.get("lang").map(|lang| lang.as_str()) {
+        None | Some("ts") => {
+            transform_ts_to_js(&block.content).map_err(|e| script_error(block, &e, true))
+        }
+        Some("js") => {
+            let mut line_start = 0;
+            let origins = block
+                .content
+                .split('\n')
+                .map(|line| {
+                    let code_start = line_start + line.len() - line.trim_start().len();
+                    line_start += line.len() + 1;
+                    (!line.trim().is_empty()).then_some(code_start)
+                })
+                .collect();
+            Ok((block.content.clone(), origins))
+        }
+        Some(lang) => Err(Diagnostic::error(
+            codes::INVALID_BLOCK_ATTRIBUTE,
+            format!(
+                "Unsupported script language `{}`; expected `ts` or `js`",
+                lang
+            ),
+        )
+        .with_range(block.range)),
+    }
+}
+
+/// Reports a syntax error of the script block, at its position in the file when the error span
+/// refers to the block content rather than to generated code.
+fn script_error(block: &LanguageBlock, error: &SwcError, in_content: bool) -> Diagnostic {
+    let range = match in_content {
+        true => block
+            .location
+            .range(&block.content, error.span.start, error.span.end),
+        false => block.range,
+    };
+    Diagnostic::error(codes::INVALID_SCRIPT, error.message.clone()).with_range(range)
 }
@@ -1,12 +1,133 @@
+use crate::diagnostics::{codes, Diagnostic, LineIndex};
 use crate::structs::blocks::ParsedItem;
 extern crate nom;
 
 use crate::parsers::{language_block::parse_language_block, metadata::parse_meta_data};
 
-use nom::{branch::alt, multi::many0, IResult};
+use nom::branch::alt;
 
-pub fn parse1(input: &str) -> IResult<&str, Vec<ParsedItem>> {
-    let (input, items) = many0(alt((parse_language_block, parse_meta_data)))(input)?;
+/// Splits the file into language blocks and `@` directives.
+///
+/// Parsing does not stop at the first malformed item: the offending lines are reported and
+/// skipped up to the next block boundary (the next non-indented line), so every problem in the
+/// file is collected in a single pass.
+pub fn parse1(input: &str) -> (Vec<ParsedItem>, Vec<Diagnostic>) {
+    let line_index = LineIndex::new(input);
+    let mut items = vec![];
+    let mut diagnostics = vec![];
+    let mut rest = input;
+    // Name and indentation of the block parsed just before `rest`, if any
+    let mut previous_block: Option<(String, &str)> = None;
 
-    Ok((input, items))
+    loop {
+        rest = skip_blank_lines(rest);
+        if rest.is_empty() {
+            break;
+        }
+        let offset = input.len() - rest.len();
+        match alt((parse_language_block, parse_meta_data))(rest) {
+            Ok((remaining, mut item)) if remaining.len() < rest.len() => {
+                item.set_origin(offset, &line_index);
+                previous_block = match &item {
+                    ParsedItem::LanguageBlock(block) => Some((
+                        block.language_name.clone(),
+                        block_indentation(input, block.location.offset, block.location.indent),
+                    )),
+                    ParsedItem::MetaData(_) => None,
+                };
+                items.push(item);
+                rest = remaining;
+            }
+            _ => {
+                let message = match &previous_block {
+                    Some((name, indentation)) if rest.starts_with([' ', '\t']) => {
+                        inconsistent_indentation_message(name, indentation, rest)
+                    }
+                    _ => unparsable_line_message(rest).to_string(),
+                };
+                diagnostics.push(
+                    Diagnostic::error(codes::SYNTAX, message)
+                        .with_range(line_index.line_range(offset)),
+                );
+                previous_block = None;
+                rest = skip_to_next_block(rest);
+            }
+        }
+    }
+
+    (items, diagnostics)
+}
+
+fn unparsable_line_message(line: &str) -> &'static str {
+    if line.starts_with([' ', '\t']) {
+        "Unexpected indentation: this line does not belong to any block"
+    } else if line.starts_with('@') {
+        "Malformed directive: expected `@name` optionally followed by `(key: value)` and content"
+    } else {
+        "Expected a language block (`name:`) or a `@` directive"
+    }
+}
+
+/// Explains why an indented `line` right after a block is not part of it.
+fn inconsistent_indentation_message(block_name: &str, indentation: &str, line: &str) -> String {
+    let line_indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    format!(
+        "Inconsistent indentation: lines of the `{}` block are indented with {}, but this line is indented with {}",
+        block_name,
+        describe_indentation(indentation),
+        describe_indentation(line_indentation)
+    )
+}
+
+/// Returns the indentation of the line containing `offset`, limited to `width` bytes.
+fn block_indentation(input: &str, offset: usize, width: usize) -> &str {
+    let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &input[line_start..];
+    let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    &indentation[..width.min(indentation.len())]
+}
+
+/// Describes indentation for error messages, e.g. "2 spaces" or "1 tab and 2 spaces".
+fn describe_indentation(indentation: &str) -> String {
+    let count = |c: char, name: &str| match indentation.matches(c).count() {
+        0 => None,
+        1 => Some(format!("1 {}", name)),
+        n => Some(format!("{} {}s", n, name)),
+    };
+    match (count('\t', "tab"), count(' ', "space")) {
+        (Some(tabs), Some(spaces)) => format!("{} and {}", tabs, spaces),
+        (Some(tabs), None) => tabs,
+        (None, Some(spaces)) => spaces,
+        (None, None) => "no indentation".to_string(),
+    }
+}
+
+fn skip_blank_lines(mut input: &str) -> &str {
+    while let Some(line_end) = input.find('\n') {
+        if !input[..line_end].trim().is_empty() {
+            break;
+        }
+        input = &input[line_end + 1..];
+    }
+    if input.trim().is_empty() {
+        ""
+    } else {
+        input
+    }
+}
+
+/// Skips the current line and every following line that cannot start a new item, i.e. blank
+/// or indented lines.
+fn skip_to_next_block(input: &str) -> &str {
+    let mut rest = match input.find('\n') {
+        Some(line_end) => &input[line_end + 1..],
+        None => "",
+    };
+    while !rest.is_empty() && rest.starts_with([' ', '\t', '\r', '\n']) {
+        rest = match rest.find('\n') {
+            Some(line_end) => &rest[line_end + 1..],
+            None => "",
+        };
+    }
+    rest
 }
@@ -1,82 +1,96 @@
-use crate::parsers::utils::{empty_lines, parse_language_name};
+use crate::diagnostics::{BlockLocation, SourceRange};
+use crate::parsers::utils::{empty_lines, parse_language_name, parse_params};
 use crate::structs::blocks::{LanguageBlock, ParsedItem};
 extern crate nom;
 
-use nom::bytes::complete::take_while;
-use nom::character::complete::line_ending;
-use nom::combinator::{not, opt, peek};
-use nom::multi::many_till;
+use nom::character::complete::{line_ending, space0};
 
-use nom::{
-    branch::alt, bytes::complete::tag, character::complete::multispace0, sequence::tuple, IResult,
-};
+use nom::{bytes::complete::tag, character::complete::multispace0, sequence::tuple, IResult};
 
 pub fn parse_language_block(input: &str) -> IResult<&str, ParsedItem> {
+    let origin = input;
     let (input, _) = empty_lines(input)?;
     let (input, language_name) = parse_language_name(input)?;
-    let (input, _) = tuple((multispace0, tag(":")))(input)?;
-    let (input, _) = tag("\n")(input)?;
+    let (input, attributes) = parse_params(input)?;
+    let (input, _) = tuple((multispace0, tag(":"), space0, line_ending))(input)?;
 
-    let (input, content) = indented_content(input)?;
+    let (input, (indentation, lines)) = indented_content(input)?;
+    let lines = trim_lines(lines);
+    let line_offsets = lines
+        .iter()
+        .map(|line| line.as_ptr() as usize - origin.as_ptr() as usize)
+        .collect();
 
     Ok((
         input,
         ParsedItem::LanguageBlock(LanguageBlock {
             language_name,
-            content: content.trim().to_string(),
+            attributes,
+            content: lines.join("\n"),
+            range: SourceRange::default(),
+            location: BlockLocation::from_line_offsets(indentation.len(), line_offsets),
         }),
     ))
 }
 
-fn is_space_or_tab(c: char) -> bool {
-    c == ' ' || c == '\t'
+/// Drops the blank lines around the content and trims the first and last line, keeping every
+/// line a slice of the source so its offset can still be recovered.
+fn trim_lines(lines: Vec<&str>) -> Vec<&str> {
+    let (first, last) = match (
+        lines.iter().position(|line| !line.trim().is_empty()),
+        lines.iter().rposition(|line| !line.trim().is_empty()),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return vec![],
+    };
+    let mut lines = lines[first..=last].to_vec();
+    lines[0] = lines[0].trim_start();
+    let last = lines.len() - 1;
+    lines[last] = lines[last].trim_end();
+    lines
 }
 
-fn is_not_line_ending(c: char) -> bool {
-    c != '\n' && c != '\r'
+fn is_space_or_tab(c: char) -> bool {
+    c == ' ' || c == '\t'
 }
 
-fn indent(input: &str) -> IResult<&str, &str> {
-    take_while(is_space_or_tab)(input)
+/// Splits off the first line of `input`, accepting both `\n` and `\r\n` endings. The returned
+/// line never contains the line ending.
+fn split_line(input: &str) -> (&str, &str) {
+    match input.find('\n') {
+        Some(end) => (input[..end].trim_end_matches('\r'), &input[end + 1..]),
+        None => (input, ""),
+    }
 }
 
-fn indented_line<'a>(indentation: &'a str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
-    move |input: &'a str| {
-        let (input, ret) = opt(tag("\n"))(input)?;
-        if ret != None {
-            return Ok((input, ""));
+/// Returns the indentation of the block and its lines with that indentation removed.
+///
+/// Every non-blank line has to start with the indentation of the first one, tabs and spaces
+/// alike. Blank lines, including lines holding nothing but whitespace, are kept as empty lines.
+/// The block ends at the first non-blank line that does not carry the indentation.
+fn indented_content(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
+    let mut rest = input;
+    let mut indentation: Option<&str> = None;
+    let mut lines = vec![];
+    while !rest.is_empty() {
+        let (line, next) = split_line(rest);
+        let text = line.trim_start_matches(is_space_or_tab);
+        if text.is_empty() {
+            lines.push(&line[..0]);
+        } else {
+            match indentation {
+                Some(indentation) => match line.strip_prefix(indentation) {
+                    Some(line) => lines.push(line),
+                    None => break,
+                },
+                None if text.len() < line.len() => {
+                    indentation = Some(&line[..line.len() - text.len()]);
+                    lines.push(text);
+                }
+                None => break,
+            }
         }
-        let (input, _) = tag(indentation)(input)?;
-        let (input, line) = take_while(is_not_line_ending)(input)?;
-        let (input, _) = line_ending(input)?;
-        Ok((input, line))
+        rest = next;
     }
-}
-
-fn take_empty_lines(input: &str) -> IResult<&str, &str> {
-    take_while(|c| c == '\n' || c == '\r')(input)
-}
-
-fn content_of_first_line(input: &str) -> IResult<&str, &str> {
-    let (input, content) = take_while(is_not_line_ending)(input)?;
-    Ok((input, content))
-}
-
-fn indented_content(input: &str) -> IResult<&str, String> {
-    let (input, _) = take_empty_lines(input)?;
-    let (input, initial_indentation) = indent(input)?;
-    let (input, content_of_first_line) = content_of_first_line(input)?;
-
-    let (input, _) = line_ending(input)?;
-
-    let (input, (mut lines, _)) = many_till(
-        indented_line(initial_indentation),
-        peek(not(alt((tag(initial_indentation), tag("\n"))))),
-    )(input)?;
-
-    lines.insert(0, content_of_first_line);
-
-    let joined = lines.join("\n");
-
-    Ok((input, joined))
+    Ok((rest, (indentation.unwrap_or_default(), lines)))
 }
@@ -1,11 +1,15 @@
 extern crate nom;
 
+use std::collections::HashMap;
+
 use nom::{
     bytes::complete::tag,
-    character::complete::{alphanumeric1, not_line_ending},
-    combinator::{map, recognize},
-    multi::many0,
-    IResult,
+    character::complete::{alphanumeric1, line_ending, not_line_ending, space0},
+    combinator::{map, opt, recognize},
+    error::{ErrorKind, ParseError},
+    multi::{many0, separated_list0},
+    sequence::tuple,
+    AsChar, IResult, InputTakeAtPosition,
 };
 
 pub fn parse_language_name(input: &str) -> IResult<&str, String> {
@@ -13,9 +17,56 @@ pub fn parse_language_name(input: &str) -> IResult<&str, String> {
 }
 
 pub fn empty_lines(input: &str) -> IResult<&str, &str> {
-    recognize(many0(tag("\n")))(input)
+    recognize(many0(line_ending))(input)
 }
 
 pub fn parse_content(input: &str) -> IResult<&str, String> {
     map(recognize(not_line_ending), String::from)(input)
 }
+
+/// Parses an optional `(key: value, ...)` list as used by `@directive(...)` and `block(...):`.
+/// Quotes around values are removed.
+pub fn parse_params(input: &str) -> IResult<&str, HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let (input, open) = opt(tag("("))(input)?;
+    if open.is_none() {
+        return Ok((input, params));
+    }
+    let (input, result) = separated_list0(
+        tag(","),
+        tuple((
+            space0,
+            alphanumeric1,
+            space0,
+            tag(":"),
+            space0,
+            alphanumeric_or_quotes,
+            space0,
+        )),
+    )(input)?;
+
+    for (_, key, _, _, _, value, _) in result {
+        params.insert(
+            key.to_string(),
+            value.trim_matches(|c| c == '\'' || c == '"').to_string(),
+        );
+    }
+
+    let (input, _) = tag(")")(input)?;
+    Ok((input, params))
+}
+
+fn alphanumeric_or_quotes<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
+where
+    T: InputTakeAtPosition,
+    T::Item: AsChar + Clone,
+    <T as InputTakeAtPosition>::Item: AsChar,
+{
+    input.split_at_position1_complete(
+        |item| {
+            let c = item.as_char();
+            !(c.is_alphanumeric() || c == '\'' || c == '\"')
+        },
+        ErrorKind::AlphaNumeric,
+    )
+}
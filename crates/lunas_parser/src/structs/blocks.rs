@@ -1,9 +1,17 @@
 use std::collections::HashMap;
 
+use crate::diagnostics::{BlockLocation, LineIndex, SourceRange};
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct LanguageBlock {
     pub language_name: String,
+    /// Attributes given in parentheses after the name, e.g. `style(scoped: true, lang: scss):`.
+    pub attributes: HashMap<String, String>,
     pub content: String,
+    /// Range of the `name:` header line in the original source.
+    pub range: SourceRange,
+    /// Position of `content` in the original source.
+    pub location: BlockLocation,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -11,6 +19,10 @@ pub struct MetaData {
     pub kind: String,
     pub params: HashMap<String, String>,
     pub content: String,
+    /// Range of the `@kind` line in the original source.
+    pub range: SourceRange,
+    /// Position of `content` in the original source.
+    pub location: BlockLocation,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -18,3 +30,16 @@ pub enum ParsedItem {
     LanguageBlock(LanguageBlock),
     MetaData(MetaData),
 }
+
+impl ParsedItem {
+    /// Records where the item starts in the file. Items are parsed from a slice starting at
+    /// `offset`, so their content locations are relative to it until this is called.
+    pub fn set_origin(&mut self, offset: usize, index: &LineIndex) {
+        let (range, location) = match self {
+            ParsedItem::LanguageBlock(block) => (&mut block.range, &mut block.location),
+            ParsedItem::MetaData(meta) => (&mut meta.range, &mut meta.location),
+        };
+        *range = index.line_range(offset);
+        location.relocate(offset, index);
+    }
+}
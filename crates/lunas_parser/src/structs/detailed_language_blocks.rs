@@ -1,15 +1,74 @@
+use std::collections::HashMap;
+
 use lunas_html_parser::Dom;
+
+use crate::diagnostics::{BlockLocation, SourcePosition, SourceRange};
 use serde_json::Value;
 
 #[derive(Debug)]
 pub struct DetailedLanguageBlocks {
+    /// The parsed html block. Its source spans are positions in the `.lun` file.
     pub dom: Dom,
     pub css: Option<String>,
     pub js: Option<JsBlock>,
+    /// Attributes of the `html`, `style` and `script` blocks (`style(scoped: true):`).
+    pub html_attributes: HashMap<String, String>,
+    pub css_attributes: HashMap<String, String>,
+    pub js_attributes: HashMap<String, String>,
+    /// Blocks other than `html`, `style` and `script`, left for compiler plugins.
+    pub custom_blocks: Vec<CustomBlock>,
+    /// Range of the `html:` header, used to locate errors found while compiling the template.
+    pub html_range: SourceRange,
+    /// Positions of the block contents in the original source, for mapping offsets in `dom`,
+    /// `css` and the script block back to the `.lun` file.
+    pub html_location: BlockLocation,
+    pub css_location: Option<BlockLocation>,
+    pub js_location: Option<BlockLocation>,
+}
+
+impl DetailedLanguageBlocks {
+    /// Language of the style block as given by its `lang` attribute, `css` by default.
+    pub fn css_lang(&self) -> &str {
+        self.css_attributes
+            .get("lang")
+            .map(|lang| lang.as_str())
+            .unwrap_or("css")
+    }
+
+    /// Whether whitespace in the markup is collapsed, as asked for with
+    /// `html(whitespace: collapse):`. Elements that preserve whitespace keep it either way.
+    pub fn collapses_whitespace(&self) -> bool {
+        self.html_attributes
+            .get("whitespace")
+            .is_some_and(|whitespace| whitespace == "collapse")
+    }
+
+    /// Whether the style block only applies to the elements of this component, as asked for with
+    /// `style(scoped: true):`.
+    pub fn scopes_css(&self) -> bool {
+        self.css_attributes
+            .get("scoped")
+            .is_some_and(|scoped| scoped == "true")
+    }
+}
+
+/// A top-level block with a name the compiler does not know, such as `docs:` or `i18n:`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomBlock {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    pub content: String,
+    /// Range of the block header in the original source.
+    pub range: SourceRange,
+    /// Position of `content` in the original source.
+    pub location: BlockLocation,
 }
 
 #[derive(Debug)]
 pub struct JsBlock {
     pub ast: Value,
     pub raw: String,
+    /// For each line of `raw`, the position in the `.lun` file of the code its first token
+    /// comes from. Lines holding only generated code have none.
+    pub line_origins: Vec<Option<SourcePosition>>,
 }
@@ -1,12 +1,13 @@
 use nom::{
-    branch::permutation,
-    bytes::complete::{is_not, tag, take_while1},
-    character::complete::{alphanumeric0, alphanumeric1, space0},
-    combinator::{all_consuming, opt},
+    bytes::complete::{tag, take_while1},
+    combinator::all_consuming,
     IResult,
 };
+use swc_ecma_ast::TsType;
 
 use super::blocks::MetaData;
+use crate::diagnostics::{codes, Diagnostic};
+use crate::swc_parser::parse_ts_type;
 
 #[derive(Debug)]
 pub enum DetailedMetaData {
@@ -19,88 +20,194 @@ pub enum DetailedMetaData {
 #[derive(Debug)]
 pub struct PropsInput {
     pub variable_name: String,
-    pub type_of_value: String,
+    pub type_of_value: InputType,
     pub initial_value: Option<String>,
     pub is_nullable: bool,
 }
 
+/// The declared type of an `@input`, kept both as written and as a parsed TypeScript type.
+#[derive(Debug)]
+pub struct InputType {
+    pub raw: String,
+    pub ts_type: Box<TsType>,
+}
+
+/// An `@use ... from "path"` directive.
 #[derive(Debug)]
 pub struct UseComponentStatement {
-    pub component_name: String,
+    pub imports: Vec<ComponentImport>,
     pub component_path: String,
 }
 
-impl<'a> DetailedMetaData {
-    pub fn from_simple_meta_data(simple_meta_data: MetaData) -> Result<Self, &'a str> {
+/// One binding introduced by an `@use` directive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentImport {
+    /// `@use Card from "..."`, or `@use Card as MyCard from "..."` to use the default export
+    /// under another name.
+    Default { local_name: String },
+    /// `@use { Card, CardHeader as Header } from "..."`.
+    Named {
+        imported_name: String,
+        local_name: String,
+    },
+    /// `@use * as Ui from "..."`; its components are used as `<Ui.Button>`.
+    Namespace { local_name: String },
+}
+
+impl ComponentImport {
+    /// The name the import is bound to in the component.
+    pub fn local_name(&self) -> &str {
+        match self {
+            ComponentImport::Default { local_name }
+            | ComponentImport::Named { local_name, .. }
+            | ComponentImport::Namespace { local_name } => local_name,
+        }
+    }
+}
+
+impl DetailedMetaData {
+    pub fn from_simple_meta_data(simple_meta_data: MetaData) -> Result<Self, Diagnostic> {
+        let range = simple_meta_data.range;
+        let error = |message: String| {
+            Err(Diagnostic::error(codes::INVALID_META_DATA, message).with_range(range))
+        };
         if simple_meta_data.kind == "input" {
             match parse_input_content(&simple_meta_data.content) {
-                Ok((_, (variable_name, type_of_value, initial_value, is_nullable))) => {
+                Ok((variable_name, type_of_value, initial_value, is_nullable)) => {
                     Ok(Self::PropsInput(PropsInput {
-                        variable_name: variable_name.to_string(),
-                        type_of_value: type_of_value.to_string(),
-                        initial_value: match initial_value {
-                            Some(initial_value) => Some(initial_value.to_string()),
-                            None => None,
-                        },
-                        is_nullable: is_nullable,
+                        variable_name,
+                        type_of_value,
+                        initial_value,
+                        is_nullable,
                     }))
                 }
-                Err(_) => Err("error parsing input content"),
+                Err(e) => error(format!(
+                    "Invalid @input declaration `{}`: {}",
+                    simple_meta_data.content, e
+                )),
             }
         } else if simple_meta_data.kind == "use" {
-            parse_component_use_statement(&simple_meta_data.content)
-                .map(|(_, (component_name, _, component_path))| {
-                    Self::UseComponentStatement(UseComponentStatement {
-                        component_name: component_name.to_string(),
+            match parse_component_use_statement(&simple_meta_data.content) {
+                Ok((_, (imports, _, component_path))) => {
+                    Ok(Self::UseComponentStatement(UseComponentStatement {
+                        imports,
                         component_path: component_path.to_string(),
-                    })
-                })
-                .map_err(|_| "error parsing use statement")
+                    }))
+                }
+                Err(_) => error(format!(
+                    "Invalid @use statement `{}`",
+                    simple_meta_data.content
+                )),
+            }
         } else if simple_meta_data.kind == "useAutoRouting" {
             Ok(Self::UseAutoRoutingStatement)
         } else if simple_meta_data.kind == "useRouting" {
             Ok(Self::UseRoutingStatement)
         } else {
-            Err("unknown kind of meta data")
+            error(format!("Unknown directive `@{}`", simple_meta_data.kind))
         }
     }
 }
 
-fn parse_input_content(input: &str) -> IResult<&str, (&str, &str, Option<&str>, bool)> {
-    let (input, (_, variable_name, _, _, _, type_name, optional_question_mark, content)) =
-        permutation((
-            space0,
-            alphanumeric1,
-            space0,
-            tag(":"),
-            space0,
-            alphanumeric0,
-            opt(tag("?")),
-            opt(permutation((
-                space0,
-                tag("="),
-                space0,
-                all_consuming(is_not("\n")),
-            ))),
-        ))(input)?;
-
-    let is_optional = optional_question_mark != None;
-    let initial_value = match content {
-        Some((_, _, _, value)) => Some(value),
-        None => None,
+/// Splits an `@input` declaration (`name?: Type = default`) into its parts and parses the type
+/// with SWC. A trailing `?` after the type (`name: Type?`) is accepted as well.
+fn parse_input_content(input: &str) -> Result<(String, InputType, Option<String>, bool), String> {
+    let (name_part, rest) = input
+        .split_once(':')
+        .ok_or_else(|| "expected `name: Type`".to_string())?;
+    let name_part = name_part.trim();
+    let (variable_name, mut is_optional) = match name_part.strip_suffix('?') {
+        Some(name) => (name.trim_end(), true),
+        None => (name_part, false),
     };
+    if variable_name.is_empty()
+        || variable_name.starts_with(|c: char| c.is_ascii_digit())
+        || !variable_name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+    {
+        return Err(format!("`{}` is not a valid variable name", variable_name));
+    }
+
+    let (type_part, initial_value) = match find_top_level_assignment(rest) {
+        Some(index) => (&rest[..index], Some(rest[index + 1..].trim().to_string())),
+        None => (rest, None),
+    };
+    if initial_value.as_deref() == Some("") {
+        return Err("expected a default value after `=`".to_string());
+    }
+    let mut type_part = type_part.trim();
+    if let Some(stripped) = type_part.strip_suffix('?') {
+        type_part = stripped.trim_end();
+        is_optional = true;
+    }
+    if type_part.is_empty() {
+        return Err("expected a type after `:`".to_string());
+    }
+    let ts_type = parse_ts_type(type_part)?;
 
     Ok((
-        &input,
-        (variable_name, type_name, initial_value, is_optional),
+        variable_name.to_string(),
+        InputType {
+            raw: type_part.to_string(),
+            ts_type,
+        },
+        initial_value,
+        is_optional,
     ))
 }
 
+/// Returns the index of the `=` separating the type from the default value, skipping `=`
+/// characters nested in brackets or strings and those belonging to `=>`, `==`, `<=`, etc.
+fn find_top_level_assignment(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut depth = 0usize;
+    let mut quote: Option<u8> = None;
+    // Whether the last `>` closed a generic argument list, so that a `=` after it is not `>=`
+    let mut closed_generic = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if let Some(q) = quote {
+            if c == b'\\' {
+                i += 1;
+            } else if c == q {
+                quote = None;
+            }
+        } else {
+            match c {
+                b'\'' | b'"' | b'`' => quote = Some(c),
+                b'(' | b'[' | b'{' | b'<' => depth += 1,
+                b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+                // `>` closes a generic argument list unless it is the arrow of `=>`
+                b'>' if i == 0 || bytes[i - 1] != b'=' => {
+                    closed_generic = depth > 0;
+                    depth = depth.saturating_sub(1);
+                }
+                b'=' if depth == 0 => {
+                    let prev = if i > 0 { bytes[i - 1] } else { b' ' };
+                    let next = bytes.get(i + 1).copied().unwrap_or(b' ');
+                    let is_comparison =
+                        matches!(prev, b'=' | b'!' | b'<') || (prev == b'>' && !closed_generic);
+                    if next != b'>' && next != b'=' && !is_comparison {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
 use nom::{
     branch::alt,
     bytes::complete::take_while,
-    character::complete::{char, multispace1},
-    sequence::{preceded, tuple},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{map, opt},
+    multi::separated_list1,
+    sequence::{delimited, pair, preceded, tuple},
 };
 
 fn is_alphanumeric_underscore(c: char) -> bool {
@@ -114,10 +221,179 @@ fn parse_string(input: &str) -> IResult<&str, &str> {
     Ok((input, str_contents))
 }
 
-fn parse_component_use_statement(input: &str) -> IResult<&str, (&str, &str, &str)> {
+fn parse_identifier(input: &str) -> IResult<&str, &str> {
+    take_while1(is_alphanumeric_underscore)(input)
+}
+
+/// Parses `name` optionally followed by `as alias`, returning the name and the alias.
+fn parse_aliased_identifier(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+    pair(
+        parse_identifier,
+        opt(preceded(
+            tuple((multispace1, tag("as"), multispace1)),
+            parse_identifier,
+        )),
+    )(input)
+}
+
+fn parse_named_imports(input: &str) -> IResult<&str, Vec<ComponentImport>> {
+    delimited(
+        pair(char('{'), multispace0),
+        separated_list1(
+            tuple((multispace0, char(','), multispace0)),
+            map(parse_aliased_identifier, |(name, alias)| {
+                ComponentImport::Named {
+                    imported_name: name.to_string(),
+                    local_name: alias.unwrap_or(name).to_string(),
+                }
+            }),
+        ),
+        tuple((multispace0, opt(char(',')), multispace0, char('}'))),
+    )(input)
+}
+
+fn parse_namespace_import(input: &str) -> IResult<&str, Vec<ComponentImport>> {
+    map(
+        preceded(
+            tuple((char('*'), multispace0, tag("as"), multispace1)),
+            parse_identifier,
+        ),
+        |name| {
+            vec![ComponentImport::Namespace {
+                local_name: name.to_string(),
+            }]
+        },
+    )(input)
+}
+
+fn parse_default_import(input: &str) -> IResult<&str, Vec<ComponentImport>> {
+    map(parse_aliased_identifier, |(name, alias)| {
+        vec![ComponentImport::Default {
+            local_name: alias.unwrap_or(name).to_string(),
+        }]
+    })(input)
+}
+
+fn parse_component_use_statement(input: &str) -> IResult<&str, (Vec<ComponentImport>, &str, &str)> {
     all_consuming(tuple((
-        take_while1(is_alphanumeric_underscore),
-        preceded(multispace1, tag("from")),
+        alt((
+            parse_named_imports,
+            parse_namespace_import,
+            parse_default_import,
+        )),
+        preceded(multispace0, tag("from")),
         preceded(multispace1, parse_string),
     )))(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swc_ecma_ast::TsKeywordTypeKind;
+
+    fn parse(content: &str) -> (String, InputType, Option<String>, bool) {
+        parse_input_content(content).unwrap()
+    }
+
+    #[test]
+    fn test_parse_input_keyword_type() {
+        let (name, ty, initial_value, is_optional) = parse("count: number = 5");
+        assert_eq!(name, "count");
+        assert_eq!(ty.raw, "number");
+        assert!(matches!(
+            *ty.ts_type,
+            TsType::TsKeywordType(ref k) if k.kind == TsKeywordTypeKind::TsNumberKeyword
+        ));
+        assert_eq!(initial_value.as_deref(), Some("5"));
+        assert!(!is_optional);
+    }
+
+    #[test]
+    fn test_parse_input_complex_types() {
+        let (_, ty, _, _) = parse("items: string[]");
+        assert!(matches!(*ty.ts_type, TsType::TsArrayType(_)));
+
+        let (_, ty, initial_value, _) = parse(r#"mode: "a" | "b" = "a""#);
+        assert_eq!(ty.raw, r#""a" | "b""#);
+        assert!(matches!(*ty.ts_type, TsType::TsUnionOrIntersectionType(_)));
+        assert_eq!(initial_value.as_deref(), Some(r#""a""#));
+
+        let (_, ty, _, _) = parse("user: Record<string, User>");
+        assert!(matches!(*ty.ts_type, TsType::TsTypeRef(_)));
+
+        let (_, ty, initial_value, _) = parse("cb: (x: number) => void = () => {}");
+        assert_eq!(ty.raw, "(x: number) => void");
+        assert!(matches!(*ty.ts_type, TsType::TsFnOrConstructorType(_)));
+        assert_eq!(initial_value.as_deref(), Some("() => {}"));
+
+        let (_, ty, initial_value, _) = parse("items: Array<string>=[]");
+        assert_eq!(ty.raw, "Array<string>");
+        assert!(matches!(*ty.ts_type, TsType::TsTypeRef(_)));
+        assert_eq!(initial_value.as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn test_parse_input_optional_forms() {
+        assert!(parse("label?: string").3);
+        assert!(parse("label: string?").3);
+    }
+
+    #[test]
+    fn test_parse_input_invalid_type() {
+        assert!(parse_input_content("count: number[").is_err());
+        assert!(parse_input_content("count number").is_err());
+    }
+
+    fn parse_use(content: &str) -> Vec<ComponentImport> {
+        parse_component_use_statement(content).unwrap().1 .0
+    }
+
+    #[test]
+    fn test_parse_use_default_import() {
+        assert_eq!(
+            parse_use("Card from \"./card.lun\""),
+            vec![ComponentImport::Default {
+                local_name: "Card".to_string()
+            }]
+        );
+        assert_eq!(
+            parse_use("Foo as Bar from './foo.lun'"),
+            vec![ComponentImport::Default {
+                local_name: "Bar".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_use_named_imports() {
+        assert_eq!(
+            parse_use("{ Card, CardHeader as Header, } from \"./cards\""),
+            vec![
+                ComponentImport::Named {
+                    imported_name: "Card".to_string(),
+                    local_name: "Card".to_string()
+                },
+                ComponentImport::Named {
+                    imported_name: "CardHeader".to_string(),
+                    local_name: "Header".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_use_namespace_import() {
+        let (_, (imports, _, path)) =
+            parse_component_use_statement("* as Ui from \"./ui\"").unwrap();
+        assert_eq!(imports[0].local_name(), "Ui");
+        assert!(matches!(imports[0], ComponentImport::Namespace { .. }));
+        assert_eq!(path, "./ui");
+    }
+
+    #[test]
+    fn test_parse_use_invalid() {
+        assert!(parse_component_use_statement("{ } from \"./cards\"").is_err());
+        assert!(parse_component_use_statement("* from \"./ui\"").is_err());
+        assert!(parse_component_use_statement("Foo as from \"./foo\"").is_err());
+    }
+}
@@ -1,19 +1,40 @@
+use std::fmt;
+use std::ops::Range;
+
 use swc_common::sync::Lrc;
-use swc_common::{
-    errors::{ColorConfig, Handler},
-    FileName, SourceMap,
-};
-use swc_ecma_ast::Module;
-use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
-
-pub fn parse_with_swc(code: &String) -> Module {
-    let cm: Lrc<SourceMap> = Default::default();
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+use swc_common::{FileName, SourceFile, SourceMap, Spanned};
+use swc_ecma_ast::{Decl, Module, ModuleItem, Stmt, TsType};
+use swc_ecma_parser::{error::Error, lexer::Lexer, Parser, StringInput, Syntax, TsSyntax};
+
+/// A syntax error reported by SWC. `span` holds byte offsets into the parsed code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwcError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl SwcError {
+    pub fn from_parser_error(error: Error, fm: &SourceFile) -> Self {
+        let span = error.span();
+        let start = (span.lo.0.saturating_sub(fm.start_pos.0)) as usize;
+        let end = (span.hi.0.saturating_sub(fm.start_pos.0)) as usize;
+        Self {
+            message: error.kind().msg().to_string(),
+            span: start..end.max(start),
+        }
+    }
+}
 
-    // Real usage
-    // let fm = cm
-    //     .load_file(Path::new("test.js"))
-    //     .expect("failed to load test.js");
+impl fmt::Display for SwcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SwcError {}
+
+pub fn parse_with_swc(code: &String) -> Result<Module, SwcError> {
+    let cm: Lrc<SourceMap> = Default::default();
     let fm = cm.new_source_file(Lrc::new(FileName::Anon), code.into());
     let lexer = Lexer::new(
         // We want to parse ecmascript
@@ -26,17 +47,40 @@ pub fn parse_with_swc(code: &String) -> Module {
 
     let mut parser = Parser::new_from(lexer);
 
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
+    let module = parser
+        .parse_module()
+        .map_err(|e| SwcError::from_parser_error(e, &fm))?;
+
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(SwcError::from_parser_error(e, &fm));
     }
 
+    Ok(module)
+}
+
+/// Parses a standalone TypeScript type expression such as `string[]` or `(x: number) => void`.
+pub fn parse_ts_type(code: &str) -> Result<Box<TsType>, String> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(
+        Lrc::new(FileName::Anon),
+        format!("type __LunasInputType = {};", code),
+    );
+    let lexer = Lexer::new(
+        Syntax::Typescript(TsSyntax::default()),
+        Default::default(),
+        StringInput::from(&*fm),
+        None,
+    );
+    let mut parser = Parser::new_from(lexer);
     let module = parser
         .parse_module()
-        .map_err(|e| {
-            // Unrecoverable fatal error occurred
-            e.into_diagnostic(&handler).emit()
-        })
-        .expect("failed to parser module");
+        .map_err(|e| e.kind().msg().to_string())?;
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(e.kind().msg().to_string());
+    }
 
-    module
+    match module.body.as_slice() {
+        [ModuleItem::Stmt(Stmt::Decl(Decl::TsTypeAlias(alias)))] => Ok(alias.type_ann.clone()),
+        _ => Err(format!("`{}` is not a single type expression", code)),
+    }
 }
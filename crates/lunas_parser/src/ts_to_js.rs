@@ -1,15 +1,14 @@
-use std::error::Error;
 use swc_common::{
-    comments::SingleThreadedComments,
-    errors::{ColorConfig, Handler},
-    sync::Lrc,
-    FileName, Globals, Mark, SourceMap, GLOBALS,
+    comments::SingleThreadedComments, sync::Lrc, BytePos, FileName, Globals, LineCol, Mark,
+    SourceMap, GLOBALS,
 };
-use swc_ecma_codegen::to_code_default;
+use swc_ecma_codegen::{text_writer::JsWriter, Emitter, Node};
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsSyntax};
 use swc_ecma_transforms_base::{fixer::fixer, hygiene::hygiene, resolver};
 use swc_ecma_transforms_typescript::{typescript, Config};
 
+use crate::swc_parser::SwcError;
+
 /// Transforms a TypeScript code string into JavaScript by stripping type annotations and TS-specific syntax.
 ///
 /// # Arguments
@@ -18,14 +17,13 @@ use swc_ecma_transforms_typescript::{typescript, Config};
 ///
 /// # Returns
 ///
-/// * `Ok(String)` containing the transformed JavaScript code, or an error if parsing fails.
-pub fn transform_ts_to_js(ts_code: &str) -> Result<String, Box<dyn Error>> {
+/// * `Ok((js, line_origins))` with the transformed JavaScript code and, for each of its lines,
+///   the byte offset in `ts_code` of the first token written on it (`None` for lines made only
+///   of generated code), or the first syntax error with its span in `ts_code` if parsing fails.
+pub fn transform_ts_to_js(ts_code: &str) -> Result<(String, Vec<Option<usize>>), SwcError> {
     // Create a shared SourceMap instance (Lrc is an Arc alias)
     let cm: Lrc<SourceMap> = Default::default();
 
-    // Set up an error handler with colored output for TTY environments
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-
     // Create a virtual source file for the parser (filename can be arbitrary)
     let fm = cm.new_source_file(
         Lrc::new(FileName::Custom("input.ts".into())),
@@ -49,20 +47,19 @@ pub fn transform_ts_to_js(ts_code: &str) -> Result<String, Box<dyn Error>> {
     // Create a parser from the lexer
     let mut parser = Parser::new_from(lexer);
 
-    // Emit any lexer errors through the handler
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
-
     // Parse the source into an AST program/module
     let module = parser
         .parse_program()
-        .map_err(|e| e.into_diagnostic(&handler).emit())
-        .expect("Failed to parse TypeScript module.");
+        .map_err(|e| SwcError::from_parser_error(e, &fm))?;
+
+    // Errors the parser recovered from are still syntax errors in the user's code
+    if let Some(e) = parser.take_errors().into_iter().next() {
+        return Err(SwcError::from_parser_error(e, &fm));
+    }
 
     // Execute transformations within a global JS context
     let globals = Globals::default();
-    let code = GLOBALS.set(&globals, || {
+    let output = GLOBALS.set(&globals, || {
         // Create unique marks for symbol resolution and hygiene
         let unresolved_mark = Mark::new();
         let top_level_mark = Mark::new();
@@ -88,11 +85,36 @@ pub fn transform_ts_to_js(ts_code: &str) -> Result<String, Box<dyn Error>> {
         // 4. fixer: insert missing tokens (e.g., semicolons, parentheses)
         let program = module.apply(fixer(Some(&comments)));
 
-        // Generate JavaScript code from the transformed AST
-        to_code_default(cm, Some(&comments), &program)
+        // Generate JavaScript code from the transformed AST, recording the original position
+        // of the tokens
+        let mut buf = vec![];
+        let mut srcmap: Vec<(BytePos, LineCol)> = vec![];
+        {
+            let mut emitter = Emitter {
+                cfg: Default::default(),
+                cm: cm.clone(),
+                comments: Some(&comments),
+                wr: JsWriter::new(cm.clone(), "\n", &mut buf, Some(&mut srcmap)),
+            };
+            program
+                .emit_with(&mut emitter)
+                .expect("writing to a buffer does not fail");
+        }
+        let code = String::from_utf8(buf).expect("codegen generated non-utf8 output");
+        let mut line_origins = vec![None; code.split('\n').count()];
+        let mut first_columns = vec![u32::MAX; line_origins.len()];
+        for (pos, LineCol { line, col }) in srcmap {
+            let line = line as usize;
+            let in_source = pos.0 >= fm.start_pos.0 && pos.0 < fm.end_pos.0;
+            if in_source && line < line_origins.len() && col < first_columns[line] {
+                first_columns[line] = col;
+                line_origins[line] = Some((pos.0 - fm.start_pos.0) as usize);
+            }
+        }
+        (code, line_origins)
     });
 
-    Ok(code)
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -112,7 +134,7 @@ mod tests {
         "#;
 
         // Perform the TS -> JS transformation
-        let js = transform_ts_to_js(ts).expect("Transformation failed");
+        let (js, _) = transform_ts_to_js(ts).expect("Transformation failed");
 
         // Verify that type annotations are removed and imports remain
         assert!(
@@ -128,4 +150,24 @@ mod tests {
             "Import statement should be preserved"
         );
     }
+
+    #[test]
+    fn test_transform_line_origins() {
+        let ts = "let count: number = 1;\n\ninterface A {}\nfunction inc(by: number) {\n  count += by;\n}\n";
+        let (js, origins) = transform_ts_to_js(ts).unwrap();
+        let lines = js.lines().collect::<Vec<_>>();
+        let origin_of = |code: &str| {
+            let line = lines.iter().position(|line| line.contains(code)).unwrap();
+            origins[line].map(|offset| &ts[offset..offset + code.len()])
+        };
+        assert_eq!(origin_of("let count"), Some("let count"));
+        assert_eq!(origin_of("count += by"), Some("count += by"));
+    }
+
+    #[test]
+    fn test_transform_reports_syntax_error() {
+        let ts = "let a = 1;\nlet b = ;\n";
+        let error = transform_ts_to_js(ts).unwrap_err();
+        assert_eq!(&ts[error.span.start..error.span.start + 1], ";");
+    }
 }
@@ -9,6 +9,7 @@ export type ComponentDeclaration = (args?: {
 export type LunasModuleExports = {
   mount: (elm: HTMLElement) => LunasComponentState;
   insert: (elm: HTMLElement, anchor: HTMLElement | null) => LunasComponentState;
+  hydrate: (elm: HTMLElement, componentElm?: HTMLElement) => LunasComponentState;
   __unmount: () => void;
 };
 
@@ -44,6 +45,8 @@ export type LunasComponentState = {
   updateComponentFuncs: ((() => void) | undefined)[][];
   updateBlockFuncs: UpdateBlockFuncs;
   isMounted: boolean;
+  // Whether the component is adopting server-rendered markup instead of creating its nodes
+  isHydrating: boolean;
   componentElm: HTMLElement;
   compSymbol: symbol;
   resetDependecies: (() => void)[];
@@ -74,12 +77,27 @@ type LunasInternalElement = {
   innerHtml: string;
   topElmTag: string;
   topElmAttr: { [key: string]: string };
+  // Namespace URI of the top element, when it is not an HTML element (SVG or MathML)
+  namespace?: string;
 };
 
 type NestedArray<T> = (T | NestedArray<T>)[];
 
 type FragmentFunc = (item?: unknown, indices?: number[]) => Fragment[];
 
+// An item rendered by a `:for` block with a `:key`. Its nodes stay under the same
+// index of the ref map while it is moved around the list.
+type KeyedForItem = {
+  key: unknown;
+  index: number;
+  // A copy of the item when it was last rendered, to tell whether it changed since
+  data: unknown;
+  elm: HTMLElement;
+  // Runs the bindings of the texts and attributes of the item again with a changed item
+  patch: (item: unknown) => void;
+  cleanUp: () => void;
+};
+
 export class valueObj<T> {
   private _v: T;
   private proxy: T;
@@ -217,16 +235,29 @@ export class valueObj<T> {
   }
 }
 
+declare const process: { env: { NODE_ENV?: string } };
+
+// Bundlers replace `process.env.NODE_ENV` at build time; without one, treat it as production.
+export const isDevMode = (): boolean => {
+  try {
+    return process.env.NODE_ENV !== "production";
+  } catch {
+    return false;
+  }
+};
+
 export const $$lunasInitComponent = function (
   this: LunasComponentState,
   args: { [key: string]: any } = {},
-  inputs: string[] = []
+  inputs: string[] = [],
+  requiredInputs: string[] = []
 ) {
   this.updatedFlag = false;
   this.valUpdateMap = [0];
   this.blkUpdateMap = {};
   this.currentVarBitGen = bitArrayGenerator();
   this.isMounted = false;
+  this.isHydrating = false;
   this.ifBlocks = {};
   this.ifBlockStates = {};
   this.compSymbol = Symbol();
@@ -238,6 +269,14 @@ export const $$lunasInitComponent = function (
   this.__lunas_after_mount = () => {};
   this.__lunas_destroy = () => {};
 
+  if (isDevMode()) {
+    for (const key of requiredInputs) {
+      if (!(key in args)) {
+        console.warn(`[Lunas] Missing required prop "${key}".`);
+      }
+    }
+  }
+
   for (const key of inputs) {
     const arg = args[key];
     if (arg instanceof valueObj) {
@@ -282,12 +321,14 @@ export const $$lunasInitComponent = function (
     this: LunasComponentState,
     innerHtml: string,
     topElmTag: string,
-    topElmAttr: { [key: string]: string } = {}
+    topElmAttr: { [key: string]: string } = {},
+    namespace?: string
   ) {
     this.internalElement = {
       innerHtml,
       topElmTag,
       topElmAttr,
+      namespace,
     };
   }.bind(this);
 
@@ -347,6 +388,26 @@ export const $$lunasInitComponent = function (
     return this;
   }.bind(this);
 
+  // Adopts the markup rendered by the server-side rendering module of the component,
+  // `componentElm` or the first element of `elm`, instead of building it again.
+  // Only components compiled with the `hydrate` target can be hydrated.
+  const hydrate = function (
+    this: LunasComponentState,
+    elm: HTMLElement,
+    componentElm: HTMLElement = elm.firstElementChild as HTMLElement
+  ): LunasComponentState {
+    if (this.isMounted) throw new Error("Component is already mounted");
+    if (!componentElm) throw new Error("No server-rendered markup to hydrate");
+    this.componentElm = componentElm;
+    this.isHydrating = true;
+    this.__lunas_apply_enhancement();
+    this.isHydrating = false;
+    this.__lunas_after_mount();
+    this.isMounted = true;
+    _updateComponent(() => {});
+    return this;
+  }.bind(this);
+
   const __unmount = function (this: LunasComponentState) {
     if (!this.isMounted) throw new Error("Component is not mounted");
     this.componentElm!.remove();
@@ -436,15 +497,19 @@ export const $$lunasInitComponent = function (
           mapOffset: number | number[],
           _mapLength: number | number[]
         ) => {
-          const componentElm = _createDomElementFromLunasElement(
-            lunasElement()
-          );
           const parentElement = getNestedArrayValue(
             this.refMap,
             parentElementIndex
           ) as HTMLElement;
           const refElement = getNestedArrayValue(this.refMap, refElementIndex);
-          parentElement!.insertBefore(componentElm, refElement ?? null);
+          const claimedElm = this.isHydrating
+            ? claimHydrationElement(parentElement, hydrationMarkerOf(ifBlockId))
+            : null;
+          const componentElm =
+            claimedElm ?? _createDomElementFromLunasElement(lunasElement());
+          if (!claimedElm) {
+            parentElement!.insertBefore(componentElm, refElement ?? null);
+          }
           setNestedArrayValue(this.refMap, mapOffset, componentElm);
           postRender();
           if (fragments) {
@@ -576,16 +641,24 @@ export const $$lunasInitComponent = function (
     this.ifBlocks[name].renderer();
   }.bind(this);
 
+  // `hydrationMarkers` name the server-rendered elements in hydratable output, `null`
+  // standing for the component element
   const getElmRefs = function (
     this: LunasComponentState,
     ids: string[],
     preserveId: number | number[],
-    refLocation: number | number[] = 0
+    refLocation: number | number[] = 0,
+    hydrationMarkers?: (string | null)[]
   ): void {
     const boolMap = bitMapToBoolArr(preserveId);
     ids.forEach(
       function (this: LunasComponentState, id: string, index: number) {
-        const e = document.getElementById(id)!;
+        const marker = hydrationMarkers?.[index] ?? null;
+        const e = !this.isHydrating
+          ? document.getElementById(id)!
+          : marker === null
+            ? this.componentElm
+            : claimHydrationTarget(this.componentElm, marker);
         if (boolMap[index]) {
           e.removeAttribute("id");
         }
@@ -622,7 +695,9 @@ export const $$lunasInitComponent = function (
         parentElementIndex: number | number[],
         refElementIndex?: number | number[]
       ],
-      fragment?: FragmentFunc
+      fragment?: FragmentFunc | null,
+      getKey?: (item: unknown) => unknown,
+      patchItems?: boolean
     ][],
     indices?: number[]
   ): void {
@@ -640,6 +715,8 @@ export const $$lunasInitComponent = function (
         [mapOffset, mapLength],
         [parentElementIndex, refElementIndex],
         fragmentFunc,
+        getKey,
+        patchItems,
       ] = config;
       const forBlockId = typeof getName === "function" ? getName() : getName;
       const blkName = indices ? `${prevIfCtx}-${indices}` : prevIfCtx;
@@ -667,6 +744,124 @@ export const $$lunasInitComponent = function (
 
       let oldItems = deepCopy(getDataArray());
 
+      // Renders an item before `insertionPointElm`, with its nodes under `index` of the ref map
+      const renderForItem = (
+        item: unknown,
+        index: number,
+        containerElm: HTMLElement,
+        insertionPointElm: Node | null,
+        fragmentsOf: FragmentFunc | null | undefined = fragmentFunc
+      ): HTMLElement => {
+        const fullIndices = [...parentIndices, index];
+        const lunasElm = renderItem(item, fullIndices);
+        const claimedElm = this.isHydrating
+          ? claimHydrationElement(containerElm, hydrationMarkerOf(forBlockId))
+          : null;
+        const domElm =
+          claimedElm ?? _createDomElementFromLunasElement(lunasElm);
+        setNestedArrayValue(this.refMap, [mapOffset, ...fullIndices], domElm);
+        if (!claimedElm) {
+          containerElm.insertBefore(domElm, insertionPointElm);
+        }
+        afterRenderHook?.(item, fullIndices);
+        if (fragmentsOf) {
+          const fragments = fragmentsOf(item, fullIndices);
+          createFragments(fragments, ifCtxUnderFor, forBlockId);
+        }
+        if (forCtx.length > 0) {
+          const lastFor = forCtx[forCtx.length - 1]!;
+          const lastForWithIndices = indices!.slice(0, -1).length
+            ? `${lastFor}-${indices!.slice(0, -1)}`
+            : lastFor;
+          this.forBlocks[lastForWithIndices]!.childs.push(forBlockId);
+        }
+        return domElm;
+      };
+
+      // Items of a keyed block in the order they are rendered in
+      let keyedItems: KeyedForItem[] = [];
+
+      // Renders an item of a keyed block, keeping apart what cleans it up so that it can be
+      // removed on its own
+      const renderKeyedForItem = (
+        item: unknown,
+        index: number,
+        containerElm: HTMLElement,
+        insertionPointElm: Node | null
+      ): KeyedForItem => {
+        const { cleanUp, childs } = this.forBlocks[forBlockId];
+        const [cleanUpStart, childsStart] = [cleanUp.length, childs.length];
+        // The registered fragments read the ones made from the latest item
+        let fragments: Fragment[] = [];
+        const elm = renderForItem(
+          item,
+          index,
+          containerElm,
+          insertionPointElm,
+          fragmentFunc &&
+            ((item, indices) => {
+              fragments = fragmentFunc(item, indices);
+              return fragments.map(
+                (
+                  [[, attributeName, defaultValue], nodeIdx, depBit, type],
+                  i
+                ): Fragment => [
+                  [() => fragments[i]![0][0](), attributeName, defaultValue],
+                  nodeIdx,
+                  depBit,
+                  type,
+                ]
+              );
+            })
+        );
+        const itemCleanUp = cleanUp.splice(cleanUpStart);
+        const itemChilds = childs.slice(childsStart);
+        const keyedItem: KeyedForItem = {
+          key: getKey!(item),
+          index,
+          data: deepCopy(item),
+          elm,
+          patch: (item) => {
+            if (fragmentFunc) {
+              fragments = fragmentFunc(item, [...parentIndices, index]);
+              fragments.forEach(renderFragment);
+            }
+            keyedItem.data = deepCopy(item);
+          },
+          // Runs once, whether the item or the whole block is removed first
+          cleanUp: () => {
+            itemCleanUp.splice(0).forEach((f) => f());
+            itemChilds.forEach((child) => {
+              if (this.forBlocks[child]) {
+                this.forBlocks[child].cleanUp.forEach((f) => f());
+                this.forBlocks[child].cleanUp = [];
+              }
+            });
+          },
+        };
+        cleanUp.push(keyedItem.cleanUp);
+        return keyedItem;
+      };
+
+      const removeKeyedForItem = (keyedItem: KeyedForItem) => {
+        const rs = resetMap(
+          this.refMap,
+          [mapOffset, ...parentIndices, keyedItem.index],
+          mapLength
+        );
+        for (const r of rs) {
+          if (r instanceof HTMLElement) {
+            r.remove();
+          }
+        }
+        keyedItem.cleanUp();
+        const { cleanUp } = this.forBlocks[forBlockId];
+        const idx = cleanUp.indexOf(keyedItem.cleanUp);
+        if (idx >= 0) {
+          cleanUp.splice(idx, 1);
+        }
+      };
+
       const renderForBlock = ((items: unknown[]) => {
         const containerElm = getNestedArrayValue(
           this.refMap,
@@ -679,25 +874,96 @@ export const $$lunasInitComponent = function (
         if (!(items != null && typeof items[Symbol.iterator] === "function")) {
           throw new Error(`Items should be an iterable object`);
         }
+        keyedItems = [];
         Array.from(items).forEach((item, index) => {
-          const fullIndices = [...parentIndices, index];
-          const lunasElm = renderItem(item, fullIndices);
-          const domElm = _createDomElementFromLunasElement(lunasElm);
-          setNestedArrayValue(this.refMap, [mapOffset, ...fullIndices], domElm);
-          containerElm.insertBefore(domElm, insertionPointElm);
-          afterRenderHook?.(item, fullIndices);
-          if (fragmentFunc) {
-            const fragments = fragmentFunc(item, fullIndices);
-            createFragments(fragments, ifCtxUnderFor, forBlockId);
+          if (getKey) {
+            keyedItems.push(
+              renderKeyedForItem(item, index, containerElm, insertionPointElm)
+            );
+          } else {
+            renderForItem(item, index, containerElm, insertionPointElm);
           }
-          if (forCtx.length > 0) {
-            const lastFor = forCtx[forCtx.length - 1]!;
-            const lastForWithIndices = indices!.slice(0, -1).length
-              ? `${lastFor}-${indices!.slice(0, -1)}`
-              : lastFor;
-            this.forBlocks[lastForWithIndices]!.childs.push(forBlockId);
+        });
+        oldItems = deepCopy(getDataArray());
+      }).bind(this);
+
+      // Updates a keyed block by its keys: items keep their nodes and are moved where they
+      // belong, those that changed being patched when the block allows it and rendered again
+      // otherwise
+      const updateKeyedForBlock = ((items: unknown[]) => {
+        const containerElm = getNestedArrayValue(
+          this.refMap,
+          parentElementIndex
+        ) as HTMLElement;
+        const insertionPointElm = getNestedArrayValue(
+          this.refMap,
+          refElementIndex
+        ) as HTMLElement;
+        const positions = new Map(keyedItems.map((item, i) => [item, i]));
+        const leftovers = new Set(keyedItems);
+        const itemsByKey = new Map<unknown, KeyedForItem>();
+        for (const keyedItem of keyedItems) {
+          if (!itemsByKey.has(keyedItem.key)) {
+            itemsByKey.set(keyedItem.key, keyedItem);
           }
+        }
+        const seenKeys = new Set<unknown>();
+        const reused = items.map((item) => {
+          const key = getKey!(item);
+          if (seenKeys.has(key) && isDevMode()) {
+            console.warn(`[Lunas] Duplicate key "${String(key)}" in a :for block.`);
+          }
+          seenKeys.add(key);
+          const keyedItem = itemsByKey.get(key);
+          if (!keyedItem) {
+            return null;
+          }
+          const changed = !deepEqual(keyedItem.data, item);
+          if (changed && !patchItems) {
+            return null;
+          }
+          itemsByKey.delete(key);
+          leftovers.delete(keyedItem);
+          if (changed) {
+            keyedItem.patch(item);
+          }
+          return keyedItem;
         });
+        // What is left of the old items is gone or could not be patched
+        leftovers.forEach(removeKeyedForItem);
+
+        const usedIndices = new Set(reused.map((keyedItem) => keyedItem?.index));
+        let freeIndex = 0;
+        const itemIndices = reused.map((keyedItem) => {
+          if (keyedItem) return keyedItem.index;
+          while (usedIndices.has(freeIndex)) freeIndex++;
+          return freeIndex++;
+        });
+
+        // The longest run of items that kept their order stays where it is
+        const staying = longestIncreasingSubsequence(
+          reused.map((keyedItem) => (keyedItem ? positions.get(keyedItem)! : -1))
+        );
+        const newKeyedItems: KeyedForItem[] = new Array(items.length);
+        let nextElm: Node | null = insertionPointElm;
+        for (let i = items.length - 1; i >= 0; i--) {
+          const keyedItem = reused[i];
+          if (keyedItem) {
+            if (!staying.has(i)) {
+              containerElm.insertBefore(keyedItem.elm, nextElm);
+            }
+            newKeyedItems[i] = keyedItem;
+          } else {
+            newKeyedItems[i] = renderKeyedForItem(
+              items[i],
+              itemIndices[i],
+              containerElm,
+              nextElm
+            );
+          }
+          nextElm = newKeyedItems[i].elm;
+        }
+        keyedItems = newKeyedItems;
         oldItems = deepCopy(getDataArray());
       }).bind(this);
 
@@ -723,7 +989,9 @@ export const $$lunasInitComponent = function (
 
         if (bitAnd(this.valUpdateMap, updateFlag)) {
           const newItems = Array.from(getDataArray());
-          if (diffDetected(oldItems, newItems)) {
+          if (getKey && diffDetected(oldItems, newItems)) {
+            updateKeyedForBlock(newItems);
+          } else if (diffDetected(oldItems, newItems)) {
             oldItems.forEach((_item, i) => {
               const rs = resetMap(
                 this.refMap,
@@ -811,7 +1079,8 @@ export const $$lunasInitComponent = function (
       amount: number,
       parent: number | number[],
       anchor?: number | number[],
-      text?: string
+      text?: string | null,
+      hydrationMarkers?: string[]
     ][],
     _assignmentLocation: number[] | number = 0
   ) {
@@ -819,9 +1088,8 @@ export const $$lunasInitComponent = function (
       typeof _assignmentLocation === "number"
         ? [_assignmentLocation]
         : _assignmentLocation;
-    for (const [amount, parentIdx, anchorIdx, text] of args) {
+    for (const [amount, parentIdx, anchorIdx, text, hydrationMarkers] of args) {
       for (let i = 0; i < amount; i++) {
-        const txtNode = document.createTextNode(text ?? " ");
         const parentElm = getNestedArrayValue(
           this.refMap,
           parentIdx
@@ -830,26 +1098,53 @@ export const $$lunasInitComponent = function (
           this.refMap,
           anchorIdx
         ) as HTMLElement;
-        parentElm.insertBefore(txtNode, anchorElm);
+        const marker =
+          this.isHydrating && hydrationMarkers
+            ? findHydrationMarker(parentElm, hydrationMarkers[i])
+            : null;
+        let txtNode: Node;
+        // Anchors take the place of their marker, text follows it
+        if (text != null && marker?.nextSibling?.nodeType === Node.TEXT_NODE) {
+          txtNode = marker.nextSibling;
+        } else {
+          // Empty text is not rendered on the server
+          txtNode = document.createTextNode(text ?? " ");
+          parentElm.insertBefore(txtNode, marker ?? anchorElm);
+        }
+        marker?.remove();
         setNestedArrayValue(this.refMap, assignmentLocation, txtNode);
         assignmentLocation[0]++;
       }
     }
   }.bind(this);
 
+  // Writes the current value of a fragment to its node
+  const renderFragment = function (
+    this: LunasComponentState,
+    [[textContent, attributeName, defaultValue], _nodeIdx, , fragmentType]: Fragment
+  ) {
+    const nodeIdx = typeof _nodeIdx === "number" ? [_nodeIdx] : _nodeIdx;
+    const target = getNestedArrayValue(this.refMap, nodeIdx) as Node;
+    if (fragmentType === FragmentType.ATTRIBUTE) {
+      $$lunasReplaceAttr(
+        attributeName!,
+        textContent(),
+        defaultValue,
+        target as HTMLElement
+      );
+    } else {
+      $$lunasReplaceText(textContent(), target);
+    }
+  }.bind(this);
+
   const createFragments = function (
     this: LunasComponentState,
     fragments: Fragment[],
     ifCtx?: string[],
     latestForName?: string
   ) {
-    for (const [
-      [textContent, attributeName, defaultValue],
-      _nodeIdx,
-      depBit,
-      fragmentType,
-    ] of fragments) {
-      const nodeIdx = typeof _nodeIdx === "number" ? [_nodeIdx] : _nodeIdx;
+    for (const fragment of fragments) {
+      const [, , depBit, fragmentType] = fragment;
       const fragmentUpdateFunc = (() => {
         if (ifCtx?.length) {
           const blockRendered = ifCtx.every(
@@ -866,28 +1161,12 @@ export const $$lunasInitComponent = function (
         if (!valueUpdated) {
           return;
         }
-        const target = getNestedArrayValue(this.refMap, nodeIdx) as Node;
-        if (fragmentType === FragmentType.ATTRIBUTE) {
-          $$lunasReplaceAttr(
-            attributeName!,
-            textContent(),
-            defaultValue,
-            target as HTMLElement
-          );
-        } else {
-          $$lunasReplaceText(textContent(), target);
-        }
+        renderFragment(fragment);
       }).bind(this);
       if (fragmentType === FragmentType.ATTRIBUTE) {
         // Because the determination of the arribute types depends on dynamic values,
         // it is necessary to update the attributes after the initial rendering
-        const target = getNestedArrayValue(this.refMap, nodeIdx) as Node;
-        $$lunasReplaceAttr(
-          attributeName!,
-          textContent(),
-          defaultValue,
-          target as HTMLElement
-        );
+        renderFragment(fragment);
       }
       this.updateComponentFuncs[1].push(fragmentUpdateFunc);
       if (latestForName) {
@@ -907,7 +1186,8 @@ export const $$lunasInitComponent = function (
     anchorIdx: number | number[] | null,
     refIdx: number | number[],
     latestCtx: string | null,
-    indices: number[] | null
+    indices: number[] | null,
+    hydrationMarker?: string
   ) {
     const parentElement = getNestedArrayValue(
       this.refMap,
@@ -917,10 +1197,13 @@ export const $$lunasInitComponent = function (
       this.refMap,
       anchorIdx
     ) as HTMLElement;
-    const { componentElm } = componentExport.insert(
-      parentElement,
-      anchorElement
-    );
+    const claimedElm =
+      this.isHydrating && hydrationMarker
+        ? claimHydrationElement(parentElement, hydrationMarker)
+        : null;
+    const { componentElm } = claimedElm
+      ? componentExport.hydrate(parentElement, claimedElm)
+      : componentExport.insert(parentElement, anchorElement);
     setNestedArrayValue(this.refMap, refIdx, componentElm);
     if (latestCtx) {
       const forIndices = indices ? indices.slice(0, -1) : null;
@@ -946,13 +1229,20 @@ export const $$lunasInitComponent = function (
     parentIdx: number | number[],
     refIdx: number | number[],
     latestCtx: string | null,
-    indices: number[] | null
+    indices: number[] | null,
+    hydrationMarker?: string
   ) {
     const parentElement = getNestedArrayValue(
       this.refMap,
       parentIdx
     ) as HTMLElement;
-    const { componentElm } = componentExport.mount(parentElement);
+    const claimedElm =
+      this.isHydrating && hydrationMarker
+        ? claimHydrationElement(parentElement, hydrationMarker)
+        : null;
+    const { componentElm } = claimedElm
+      ? componentExport.hydrate(parentElement, claimedElm)
+      : componentExport.mount(parentElement);
     setNestedArrayValue(this.refMap, refIdx, componentElm);
     if (latestCtx) {
       const forIndices = indices ? indices.slice(0, -1) : null;
@@ -1016,6 +1306,7 @@ export const $$lunasInitComponent = function (
     $$lunasComponentReturn: {
       mount,
       insert,
+      hydrate,
       __unmount,
     } as LunasModuleExports,
   };
@@ -1043,6 +1334,8 @@ export function $$lunasReplaceText(content: any, elm: Node) {
 //   elm.innerHTML = $$lunasEscapeHtml(content);
 // }
 
+const HTML_NAMESPACE = "http://www.w3.org/1999/xhtml";
+
 export function $$lunasReplaceAttr(
   key: string,
   content: any,
@@ -1067,26 +1360,37 @@ export function $$lunasReplaceAttr(
       elm.removeAttribute(key);
       return;
     }
-    (elm as any)[key] = String(content);
+    // Properties of SVG and MathML elements are read-only or missing, e.g. `rect.width`
+    if (elm.namespaceURI === HTML_NAMESPACE) {
+      (elm as any)[key] = String(content);
+    } else {
+      elm.setAttribute(key, String(content));
+    }
   }
 }
 
 export function $$createLunasElement(
   innerHtml: string,
   topElmTag: string,
-  topElmAttr: { [key: string]: string } = {}
+  topElmAttr: { [key: string]: string } = {},
+  namespace?: string
 ): LunasInternalElement {
   return {
     innerHtml,
     topElmTag,
     topElmAttr,
+    namespace,
   };
 }
 
 const _createDomElementFromLunasElement = (
   lunasElement: LunasInternalElement
 ): HTMLElement => {
-  const componentElm = document.createElement(lunasElement.topElmTag);
+  const componentElm = (
+    lunasElement.namespace
+      ? document.createElementNS(lunasElement.namespace, lunasElement.topElmTag)
+      : document.createElement(lunasElement.topElmTag)
+  ) as HTMLElement;
   Object.keys(lunasElement.topElmAttr).forEach((key) => {
     componentElm.setAttribute(key, lunasElement.topElmAttr[key]);
   });
@@ -1094,6 +1398,74 @@ const _createDomElementFromLunasElement = (
   return componentElm;
 };
 
+// Server-rendered markup carries hydration markers: a `<!--marker-->` comment
+// before the elements the client gets references to and the text nodes, blocks
+// and components it would insert, and a `<!--/marker-->` comment where it would
+// insert the anchor of a block. Markers repeat in the items of `:for` blocks and
+// in instances of a component, so each comment is removed once it is claimed.
+
+// The marker of a block, whose name under a `:for` block is followed by the indices
+const hydrationMarkerOf = (blockName: string): string => blockName.split("-")[0];
+
+// The first `<!--marker-->` comment under `parent`, in document order
+const findNestedHydrationMarker = (
+  parent: Node,
+  marker: string
+): Comment | null => {
+  for (const child of Array.from(parent.childNodes)) {
+    if (
+      child.nodeType === Node.COMMENT_NODE &&
+      (child as Comment).data === marker
+    ) {
+      return child as Comment;
+    }
+    const nested = findNestedHydrationMarker(child, marker);
+    if (nested) return nested;
+  }
+  return null;
+};
+
+// Takes the element rendered after the first `<!--marker-->` comment under `root`.
+const claimHydrationTarget = (
+  root: HTMLElement,
+  marker: string
+): HTMLElement => {
+  const comment = findNestedHydrationMarker(root, marker);
+  const target = comment?.nextSibling;
+  if (!comment || target?.nodeType !== Node.ELEMENT_NODE) {
+    throw new Error(
+      `[Lunas] Hydration failed: the server-rendered markup has no element "${marker}". Compile components with the \`hydratable-server\` and \`hydrate\` targets from the same source.`
+    );
+  }
+  comment.remove();
+  return target as HTMLElement;
+};
+
+const findHydrationMarker = (parent: Node, marker: string): Comment | null => {
+  for (const child of Array.from(parent.childNodes)) {
+    if (
+      child.nodeType === Node.COMMENT_NODE &&
+      (child as Comment).data === marker
+    ) {
+      return child as Comment;
+    }
+  }
+  return null;
+};
+
+// Takes the element rendered after the first `<!--marker-->` comment in `parent`, or
+// returns null when the server did not render it.
+const claimHydrationElement = (
+  parent: Node,
+  marker: string
+): HTMLElement | null => {
+  const comment = findHydrationMarker(parent, marker);
+  if (!comment) return null;
+  const elm = comment.nextSibling;
+  comment.remove();
+  return elm?.nodeType === Node.ELEMENT_NODE ? (elm as HTMLElement) : null;
+};
+
 export const $$lunasCreateNonReactive = function <T>(
   this: LunasComponentState,
   v: T
@@ -1142,6 +1514,47 @@ function diffDetected<T>(_oldArray: T[], _newArray: T[]): boolean {
   return true;
 }
 
+function deepEqual(a: unknown, b: unknown): boolean {
+  if (a === b) return true;
+  if (a == null || b == null || typeof a !== "object" || typeof b !== "object") {
+    return false;
+  }
+  if (Array.isArray(a) !== Array.isArray(b)) return false;
+  const keysOfA = Object.keys(a);
+  const keysOfB = Object.keys(b);
+  return (
+    keysOfA.length === keysOfB.length &&
+    keysOfA.every((key) =>
+      deepEqual((a as any)[key], (b as any)[key])
+    )
+  );
+}
+
+// Returns the positions of a longest strictly increasing subsequence of `sequence`,
+// skipping negative values
+function longestIncreasingSubsequence(sequence: number[]): Set<number> {
+  // Position of the last element of the best subsequence of each length
+  const tails: number[] = [];
+  const previous: number[] = new Array(sequence.length).fill(-1);
+  sequence.forEach((value, i) => {
+    if (value < 0) return;
+    let low = 0;
+    let high = tails.length;
+    while (low < high) {
+      const mid = (low + high) >> 1;
+      if (sequence[tails[mid]] < value) low = mid + 1;
+      else high = mid;
+    }
+    if (low > 0) previous[i] = tails[low - 1];
+    tails[low] = i;
+  });
+  const result = new Set<number>();
+  for (let i = tails[tails.length - 1] ?? -1; i >= 0; i = previous[i]) {
+    result.add(i);
+  }
+  return result;
+}
+
 function setNestedArrayValue<T>(
   arr: NestedArray<T>,
   location: number | number[],
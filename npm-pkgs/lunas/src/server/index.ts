@@ -0,0 +1,92 @@
+/** biome-ignore-all lint/suspicious/noExplicitAny: user inputs are unpredictable, so accepting `any` is necessary to handle arbitrary data. */
+
+import { $$lunasCreateNonReactive, $$lunasEscapeHtml, isDevMode, valueObj } from "../engine";
+
+export { $$lunasCreateNonReactive, $$lunasEscapeHtml };
+
+/**
+ * A component compiled with the `server` target: renders the markup of the
+ * component for the given props.
+ */
+export type SsrComponentDeclaration = (props?: { [key: string]: any }) => string;
+
+/**
+ * Sets up a component compiled for server-side rendering.
+ *
+ * Props may be plain values, as passed by the code rendering a page, or the
+ * reactive values passed between components. Nothing is updated after the
+ * markup is rendered, so watchers and lifecycle hooks are never called.
+ */
+export function $$lunasInitSsrComponent(
+  props: { [key: string]: any } = {},
+  inputs: string[] = [],
+  requiredInputs: string[] = []
+) {
+  if (isDevMode()) {
+    for (const key of requiredInputs) {
+      if (!(key in props)) {
+        console.warn(`[Lunas] Missing required prop "${key}".`);
+      }
+    }
+  }
+
+  const args: { [key: string]: valueObj<unknown> } = {};
+  for (const key of inputs) {
+    const prop = props[key];
+    // Missing props keep their default value
+    if (prop === undefined) continue;
+    args[key] = prop instanceof valueObj ? prop : new valueObj(prop);
+  }
+
+  return {
+    args,
+    $$lunasSetImportVars: (_items: unknown[]) => {},
+    $$lunasAfterMount: (_afterMount: () => void) => {},
+    $$lunasAfterUnmount: (_afterUnmount: () => void) => {},
+    $$lunasReactive: <T>(v: T) => new valueObj<T>(v),
+    $$lunasWatch: (_dependingVars: unknown[], _func: () => void) => {},
+  };
+}
+
+/**
+ * Renders a bound attribute as `$$lunasReplaceAttr` sets it in the browser:
+ * `true` gives an empty attribute, `false` and `undefined` leave it out and an
+ * object lists its truthy keys after the static value of the attribute.
+ */
+export function $$lunasRenderAttr(
+  key: string,
+  content: any,
+  defaultValue?: string | null
+): string {
+  if (typeof content === "boolean") {
+    return content ? key : "";
+  }
+  if (content === undefined || content === null) {
+    return "";
+  }
+  let value: string;
+  if (typeof content === "object") {
+    value = defaultValue ? `${defaultValue} ` : "";
+    value += Object.keys(content)
+      .filter((k) => content[k])
+      .join(" ");
+  } else {
+    value = String(content);
+  }
+  return `${key}="${$$lunasEscapeHtml(value)}"`;
+}
+
+/**
+ * Renders the elements of a `:for` block, one for each item of `items`.
+ */
+export function $$lunasRenderList(
+  items: any,
+  renderItem: (item: any) => string
+): string {
+  if (!(items != null && typeof items[Symbol.iterator] === "function")) {
+    throw new Error(`Items should be an iterable object`);
+  }
+  return Array.from(items)
+    .map((item) => renderItem(item))
+    .join("");
+}
@@ -1,51 +1,123 @@
 import { compile } from "lunas/compiler";
 import { Plugin } from "vite";
 
+/**
+ * A diagnostic reported by the Lunas compiler. `line` and `column` are 1-based.
+ */
+interface LunasDiagnostic {
+  severity: "error" | "warning";
+  code: string;
+  message: string;
+  range?: {
+    start: { offset: number; line: number; column: number };
+    end: { offset: number; line: number; column: number };
+  };
+}
+
+function isDiagnosticList(value: unknown): value is LunasDiagnostic[] {
+  return (
+    Array.isArray(value) &&
+    value.every((d) => typeof d?.message === "string" && typeof d?.code === "string")
+  );
+}
+
+function formatDiagnostic(diagnostic: LunasDiagnostic): string {
+  const location = diagnostic.range
+    ? `${diagnostic.range.start.line}:${diagnostic.range.start.column}: `
+    : "";
+  return `${location}${diagnostic.message} [${diagnostic.code}]`;
+}
+
+interface LunasPluginOptions {
+  /**
+   * Compile client builds to hydrate the markup of SSR builds with `hydrate()`
+   * instead of rendering components with `mount()`.
+   */
+  hydrate?: boolean;
+}
+
 /**
  * Vite plugin for handling `.lun` files with custom compilation and CSS extraction.
  *
  * This plugin performs the following:
  * - Transforms `.lun` files by compiling them and extracting their CSS.
+ *   SSR builds compile them for server-side rendering, and with `hydrate`
+ *   client builds adopt the markup rendered on the server.
  * - Stores generated CSS in a map for each `.lun` file.
  * - Injects a virtual CSS module import into the transformed JavaScript code.
  * - Resolves and serves the virtual CSS module when requested by Vite.
  *
+ * @param {LunasPluginOptions} pluginOptions - Options of the plugin.
  * @returns {Plugin} A Vite plugin object for processing `.lun` files.
  */
-export function lunas(): Plugin {
-  // Map to store generated CSS for each .lun file
-  const cssCodeMap = new Map<string, string>();
+export function lunas(pluginOptions: LunasPluginOptions = {}): Plugin {
+  // Map to store generated CSS and its source map for each .lun file
+  const cssCodeMap = new Map<string, { code: string; map?: string }>();
+  // Style queries look like `?style.css` or `?style.scss`, so that Vite
+  // runs the matching preprocessor on the extracted styles.
+  const styleQuery = /^style\.[a-z]+$/;
 
   return {
     name: "vite-plugin-lunas", // Plugin name
     resolveId(id) {
       // Handle virtual CSS module for .lun files
       const [filename, query] = id.split("?", 2);
-      if (filename.endsWith(".lun") && query === "style.css") {
+      if (filename.endsWith(".lun") && query && styleQuery.test(query)) {
         return id; // Mark as resolved for Vite
       }
     },
-    async transform(code, id: string) {
+    async transform(code, id: string, options) {
       // Transform .lun files
       if (id.endsWith(".lun")) {
-        const result = compile(code);
+        let result: ReturnType<typeof compile>;
+        try {
+          // SSR builds get modules that render components to HTML strings,
+          // which carry hydration markers when the client hydrates them
+          const target = options?.ssr
+            ? pluginOptions.hydrate
+              ? "hydratable-server"
+              : "server"
+            : pluginOptions.hydrate
+              ? "hydrate"
+              : "client";
+          result = compile(code, undefined, id, target);
+        } catch (e) {
+          if (!isDiagnosticList(e) || e.length === 0) throw e;
+          const [first] = e;
+          return this.error({
+            message: e.map(formatDiagnostic).join("\n"),
+            id,
+            loc: first.range && {
+              file: id,
+              line: first.range.start.line,
+              column: first.range.start.column - 1,
+            },
+          });
+        }
+        const map = JSON.parse(result.jsSourceMap);
         if (result.css) {
           // Store CSS for later retrieval
-          cssCodeMap.set(id, result.css);
+          cssCodeMap.set(id, { code: result.css, map: result.cssSourceMap });
+          // The import line shifts the compiled code down by one line
+          map.mappings = `;${map.mappings}`;
           return {
-            code: `import '${id}?style.css';\n${result.js}`, // Import virtual CSS module
+            code: `import '${id}?style.${result.cssLang}';\n${result.js}`, // Import virtual CSS module
+            map,
           };
         }
         return {
           code: result.js,
+          map,
         };
       }
     },
     load(id) {
       // Load the virtual CSS module for .lun files
-      if (id.endsWith(".lun?style.css")) {
-        const originalId = id.replace("?style.css", "");
-        return cssCodeMap.get(originalId) || ""; // Return CSS or empty string
+      const [filename, query] = id.split("?", 2);
+      if (filename.endsWith(".lun") && query && styleQuery.test(query)) {
+        const css = cssCodeMap.get(filename);
+        if (!css) return ""; // Return empty string when the file has no CSS
+        return { code: css.code, map: css.map && JSON.parse(css.map) };
       }
     },
   };